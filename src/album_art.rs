@@ -0,0 +1,328 @@
+use anyhow::Result;
+use ratatui::layout::Rect;
+
+/// Cover images drawn inline in the TUI detail view.
+///
+/// Built with the `album-art` feature, images are downloaded in the
+/// background and handed to the terminal undecoded: the iTerm2 inline image
+/// protocol (iTerm2, WezTerm) takes any common format, while the kitty
+/// graphics protocol (kitty, Ghostty) is only used for PNG covers. Anywhere
+/// else, or when a download fails, the detail view keeps showing the URL.
+pub struct AlbumArt {
+    #[cfg(feature = "album-art")]
+    inner: imp::Images,
+}
+
+#[cfg(feature = "album-art")]
+impl AlbumArt {
+    /// Inline images for this terminal, or `None` when it speaks neither
+    /// protocol.
+    pub fn detect() -> Option<Self> {
+        imp::Images::detect().map(|inner| Self { inner })
+    }
+
+    /// Whether the image at `url` can be drawn. The first call for a URL
+    /// starts its download.
+    pub fn ready(&mut self, url: &str) -> bool {
+        self.inner.ready(url)
+    }
+
+    /// Whether a download is still running, so the caller should redraw
+    /// when it finishes.
+    pub fn loading(&self) -> bool {
+        self.inner.loading()
+    }
+
+    /// Draw the image at `url` over `area`, after the frame's text has been
+    /// flushed. Redrawing the same image in the same place does nothing.
+    pub fn draw(&mut self, url: &str, area: Rect) -> Result<()> {
+        self.inner.draw(url, area)
+    }
+
+    /// Remove the image on screen, if any. Returns `true` when the screen
+    /// must be repainted to get rid of it.
+    pub fn clear(&mut self) -> Result<bool> {
+        self.inner.clear()
+    }
+}
+
+/// Without the `album-art` feature the detail view only shows the URL.
+#[cfg(not(feature = "album-art"))]
+impl AlbumArt {
+    pub fn detect() -> Option<Self> {
+        None
+    }
+
+    pub fn ready(&mut self, _url: &str) -> bool {
+        false
+    }
+
+    pub fn loading(&self) -> bool {
+        false
+    }
+
+    pub fn draw(&mut self, _url: &str, _area: Rect) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(feature = "album-art")]
+mod imp {
+    use anyhow::{bail, Context, Result};
+    use base64::Engine;
+    use crossterm::{cursor::MoveTo, queue};
+    use ratatui::layout::Rect;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use std::sync::mpsc;
+
+    /// Covers are a few hundred KB; anything far bigger isn't one.
+    const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+
+    /// Bytes of base64 per kitty graphics escape, the protocol's limit.
+    const KITTY_CHUNK: usize = 4096;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(super) enum Protocol {
+        Kitty,
+        ITerm,
+    }
+
+    impl Protocol {
+        fn from_env(term: &str, term_program: &str, kitty_window: bool) -> Option<Self> {
+            match term_program {
+                "iTerm.app" | "WezTerm" => Some(Self::ITerm),
+                "ghostty" => Some(Self::Kitty),
+                _ if kitty_window || term == "xterm-kitty" => Some(Self::Kitty),
+                _ => None,
+            }
+        }
+
+        /// Whether the terminal can show these bytes without decoding them
+        /// here first.
+        pub(super) fn accepts(self, image: &[u8]) -> bool {
+            match self {
+                Self::ITerm => true,
+                Self::Kitty => image.starts_with(b"\x89PNG\r\n\x1a\n"),
+            }
+        }
+    }
+
+    enum Art {
+        Loading,
+        Ready(Vec<u8>),
+        Failed,
+    }
+
+    pub(super) struct Images {
+        protocol: Protocol,
+        runtime: tokio::runtime::Handle,
+        images: HashMap<String, Art>,
+        sender: mpsc::Sender<(String, Option<Vec<u8>>)>,
+        receiver: mpsc::Receiver<(String, Option<Vec<u8>>)>,
+        /// The image currently on screen and where.
+        shown: Option<(String, Rect)>,
+    }
+
+    impl Images {
+        pub(super) fn detect() -> Option<Self> {
+            let var = |name: &str| std::env::var(name).unwrap_or_default();
+            let protocol = Protocol::from_env(
+                &var("TERM"),
+                &var("TERM_PROGRAM"),
+                std::env::var_os("KITTY_WINDOW_ID").is_some(),
+            )?;
+            // Downloads run on the runtime the TUI was started from.
+            let runtime = tokio::runtime::Handle::try_current().ok()?;
+            let (sender, receiver) = mpsc::channel();
+            Some(Self {
+                protocol,
+                runtime,
+                images: HashMap::new(),
+                sender,
+                receiver,
+                shown: None,
+            })
+        }
+
+        pub(super) fn ready(&mut self, url: &str) -> bool {
+            while let Ok((url, image)) = self.receiver.try_recv() {
+                let art = match image {
+                    Some(image) if self.protocol.accepts(&image) => Art::Ready(image),
+                    _ => Art::Failed,
+                };
+                self.images.insert(url, art);
+            }
+
+            match self.images.get(url) {
+                Some(Art::Ready(_)) => true,
+                Some(Art::Loading | Art::Failed) => false,
+                None => {
+                    self.images.insert(url.to_string(), Art::Loading);
+                    let sender = self.sender.clone();
+                    let url = url.to_string();
+                    self.runtime.spawn(async move {
+                        // A failed download just leaves the URL on screen.
+                        let image = download(&url).await.ok();
+                        let _ = sender.send((url, image));
+                    });
+                    false
+                }
+            }
+        }
+
+        pub(super) fn loading(&self) -> bool {
+            self.images.values().any(|art| matches!(art, Art::Loading))
+        }
+
+        pub(super) fn draw(&mut self, url: &str, area: Rect) -> Result<()> {
+            if self.shown.as_ref() == Some(&(url.to_string(), area)) {
+                return Ok(());
+            }
+            let Some(Art::Ready(image)) = self.images.get(url) else {
+                return Ok(());
+            };
+
+            let mut out = std::io::stdout();
+            if self.protocol == Protocol::Kitty {
+                write!(out, "{}", kitty_delete())?;
+            }
+            queue!(out, MoveTo(area.x, area.y))?;
+            let escape = match self.protocol {
+                Protocol::Kitty => kitty_escape(image, area.width, area.height),
+                Protocol::ITerm => iterm_escape(image, area.width, area.height),
+            };
+            write!(out, "{}", escape)?;
+            out.flush()?;
+            self.shown = Some((url.to_string(), area));
+            Ok(())
+        }
+
+        pub(super) fn clear(&mut self) -> Result<bool> {
+            if self.shown.take().is_none() {
+                return Ok(false);
+            }
+            match self.protocol {
+                Protocol::Kitty => {
+                    let mut out = std::io::stdout();
+                    write!(out, "{}", kitty_delete())?;
+                    out.flush()?;
+                    Ok(false)
+                }
+                // Inline images are part of the cell contents, so only a
+                // full repaint covers them up.
+                Protocol::ITerm => Ok(true),
+            }
+        }
+    }
+
+    async fn download(url: &str) -> Result<Vec<u8>> {
+        let image = match url.strip_prefix("file://") {
+            Some(path) => tokio::fs::read(path)
+                .await
+                .with_context(|| format!("Failed to read {}", path))?,
+            None => reqwest::get(url)
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?
+                .to_vec(),
+        };
+        if image.len() > MAX_IMAGE_BYTES {
+            bail!("Image at {} is too large", url);
+        }
+        Ok(image)
+    }
+
+    /// Transmit a PNG and show it scaled to `columns` x `rows` cells at the
+    /// cursor, split into the chunks the kitty protocol requires.
+    pub(super) fn kitty_escape(png: &[u8], columns: u16, rows: u16) -> String {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+        let mut escape = String::with_capacity(encoded.len() + chunks.len() * 32);
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+            if i == 0 {
+                escape.push_str(&format!(
+                    "\x1b_Ga=T,f=100,c={},r={},C=1,q=2,m={};{}\x1b\\",
+                    columns, rows, more, chunk
+                ));
+            } else {
+                escape.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+            }
+        }
+        escape
+    }
+
+    /// Delete every kitty image placement on screen.
+    fn kitty_delete() -> &'static str {
+        "\x1b_Ga=d,d=A,q=2\x1b\\"
+    }
+
+    /// Show an image of any format the terminal understands, fitted into
+    /// `columns` x `rows` cells at the cursor.
+    pub(super) fn iterm_escape(image: &[u8], columns: u16, rows: u16) -> String {
+        format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            image.len(),
+            columns,
+            rows,
+            base64::engine::general_purpose::STANDARD.encode(image)
+        )
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn protocol_follows_terminal() {
+            assert_eq!(
+                Protocol::from_env("xterm-256color", "iTerm.app", false),
+                Some(Protocol::ITerm)
+            );
+            assert_eq!(
+                Protocol::from_env("xterm-kitty", "", false),
+                Some(Protocol::Kitty)
+            );
+            assert_eq!(
+                Protocol::from_env("xterm-256color", "", true),
+                Some(Protocol::Kitty)
+            );
+            assert_eq!(
+                Protocol::from_env("xterm-256color", "Apple_Terminal", false),
+                None
+            );
+        }
+
+        #[test]
+        fn kitty_only_takes_png() {
+            assert!(Protocol::Kitty.accepts(b"\x89PNG\r\n\x1a\nrest"));
+            assert!(!Protocol::Kitty.accepts(b"\xff\xd8\xff\xe0jpeg"));
+            assert!(Protocol::ITerm.accepts(b"\xff\xd8\xff\xe0jpeg"));
+        }
+
+        #[test]
+        fn kitty_payload_is_chunked() {
+            let escape = kitty_escape(&[0u8; 4000], 30, 15);
+            let parts: Vec<&str> = escape.split("\x1b\\").filter(|p| !p.is_empty()).collect();
+            assert_eq!(parts.len(), 2);
+            assert!(parts[0].starts_with("\x1b_Ga=T,f=100,c=30,r=15,C=1,q=2,m=1;"));
+            assert!(parts[1].starts_with("\x1b_Gm=0;"));
+        }
+
+        #[test]
+        fn iterm_escape_carries_size_and_cells() {
+            let escape = iterm_escape(b"abc", 30, 15);
+            assert_eq!(
+                escape,
+                "\x1b]1337;File=inline=1;size=3;width=30;height=15;preserveAspectRatio=1:YWJj\x07"
+            );
+        }
+    }
+}
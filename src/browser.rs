@@ -0,0 +1,20 @@
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+/// Open `url` in the default web browser without waiting for it.
+///
+/// Output from the opener is discarded so it can't scribble over the TUI.
+pub fn open(url: &str) -> Result<()> {
+    let opener = if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    Command::new(opener)
+        .arg(url)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {} to open {}", opener, url))?;
+    Ok(())
+}
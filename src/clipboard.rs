@@ -0,0 +1,38 @@
+use anyhow::Result;
+
+/// The system clipboard, for copying lyrics out of the TUI.
+///
+/// Built with the `clipboard` feature, text goes through `arboard` (the
+/// macOS pasteboard, or X11/XWayland on Linux). Without it, or on a headless
+/// machine, copying fails with an explanation instead.
+///
+/// Connecting is deferred to the first copy, so a session that never copies
+/// never touches the display server.
+#[derive(Default)]
+pub struct Clipboard {
+    #[cfg(feature = "clipboard")]
+    inner: Option<arboard::Clipboard>,
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard {
+    /// Put `text` on the clipboard.
+    ///
+    /// The connection is kept for the rest of the session: on X11 the copied
+    /// text is only available while its owner is still running.
+    pub fn copy(&mut self, text: &str) -> Result<()> {
+        let clipboard = match self.inner.take() {
+            Some(clipboard) => clipboard,
+            None => arboard::Clipboard::new()?,
+        };
+        self.inner.insert(clipboard).set_text(text)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+impl Clipboard {
+    pub fn copy(&mut self, _text: &str) -> Result<()> {
+        anyhow::bail!("pb was built without clipboard support (enable the `clipboard` feature)")
+    }
+}
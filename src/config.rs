@@ -1,53 +1,456 @@
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Application configuration loaded from a TOML file.
 #[derive(Debug, Deserialize)]
 pub struct Config {
+    /// What `pb` does when run without an action flag.
+    #[serde(default)]
+    pub default_action: DefaultAction,
+    #[serde(default)]
     pub database: DatabaseConfig,
+    #[serde(default)]
+    pub lyrics: LyricsConfig,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub tui: TuiConfig,
+    #[serde(default)]
+    pub player: PlayerConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// Present only when the `[scrobble_log]` section is in the config file.
+    pub scrobble_log: Option<ScrobbleLogConfig>,
+    /// Present only when the `[spotify]` section is in the config file.
+    pub spotify: Option<SpotifyApiConfig>,
+    /// Present only when the `[genius]` section is in the config file.
+    pub genius: Option<GeniusApiConfig>,
+}
+
+/// Command run by a bare `pb` invocation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DefaultAction {
+    /// Show the currently playing track.
+    #[default]
+    NowPlaying,
+    /// Open the interactive browser.
+    Browse,
+    /// List recently queried tracks.
+    Recent,
 }
 
 /// Database configuration section.
 #[derive(Debug, Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
     /// Path to the SQLite database file. Supports `~` for the home directory.
     pub path: String,
 }
 
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(Config::get_default_db_path(), "playbot.db"),
+        }
+    }
+}
+
+/// Lyrics fetching configuration section.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LyricsConfig {
+    /// Where lyrics are looked up.
+    pub provider: LyricsSource,
+    /// Lyrics whose matched title/artist score below this similarity (0.0–1.0)
+    /// are treated as a wrong match and not stored.
+    pub min_match_confidence: f64,
+    /// When running in a terminal, lyric matches scoring below this are shown
+    /// for confirmation before being stored, even if they clear
+    /// `min_match_confidence`. `0` never asks.
+    pub confirm_below: f64,
+    /// Extra regexes whose matches are removed from fetched lyrics, applied
+    /// after the built-in cleanup patterns.
+    pub cleanup_patterns: Vec<String>,
+    /// How many times a lookup that times out or hits a network or server
+    /// error is retried, with exponential backoff. `0` tries once.
+    pub max_retries: u32,
+    /// Seconds a single lookup may take before it is abandoned (and retried).
+    pub timeout_secs: u64,
+}
+
+impl Default for LyricsConfig {
+    fn default() -> Self {
+        Self {
+            provider: LyricsSource::default(),
+            min_match_confidence: 0.5,
+            confirm_below: 0.8,
+            cleanup_patterns: Vec::new(),
+            max_retries: 2,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// Lyrics service named by `[lyrics] provider`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LyricsSource {
+    /// Genius, searched through the `lyric_finder` crate.
+    #[default]
+    Genius,
+    /// LRCLIB (lrclib.net), which often has songs Genius lacks.
+    Lrclib,
+    /// Never look up lyrics; tracks keep whatever lyrics they have cached.
+    None,
+}
+
+/// Cache storage configuration section.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Lyrics longer than this many bytes are truncated before being stored.
+    pub max_lyrics_bytes: usize,
+    /// Cached tracks older than this many hours are fetched again when they
+    /// play. `0` keeps them forever.
+    pub ttl_hours: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            max_lyrics_bytes: 100_000,
+            ttl_hours: 0,
+        }
+    }
+}
+
+/// Now-playing detection configuration section.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PlayerConfig {
+    /// MPRIS player to read on Linux, the part of its bus name after
+    /// `org.mpris.MediaPlayer2.` (e.g. `spotify`, `vlc`, `ncspot`). macOS
+    /// always reads Spotify.
+    pub name: String,
+    /// How many times to query the player when it returns empty or unparsable
+    /// metadata (common right after a track change). `1` disables retrying.
+    pub detection_attempts: u32,
+    /// Delay between detection attempts, in milliseconds.
+    pub detection_retry_ms: u64,
+    /// How often `--watch` asks the player for its current track, in seconds.
+    pub watch_interval_secs: u64,
+    /// What to do when a file from the user's own library is playing.
+    pub local_files: ContentPolicy,
+    /// What to do when a podcast episode is playing.
+    pub podcasts: ContentPolicy,
+    /// How to read the player on Linux, tried in order until one answers.
+    /// There is no Web API backend: the `[spotify]` app credentials can't see
+    /// what a user is playing. macOS always asks the app through AppleScript.
+    pub backends: Vec<PlayerBackend>,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            name: "spotify".to_string(),
+            detection_attempts: 3,
+            detection_retry_ms: 250,
+            watch_interval_secs: 5,
+            local_files: ContentPolicy::Cache,
+            podcasts: ContentPolicy::Skip,
+            backends: vec![PlayerBackend::Playerctl, PlayerBackend::DbusSend],
+        }
+    }
+}
+
+/// A way of reading the player's MPRIS metadata on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayerBackend {
+    /// The `playerctl` command.
+    Playerctl,
+    /// Raw D-Bus calls through `dbus-send`, for systems without playerctl.
+    DbusSend,
+}
+
+impl PlayerBackend {
+    /// The name used in the config file.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Playerctl => "playerctl",
+            Self::DbusSend => "dbus-send",
+        }
+    }
+}
+
+/// Outgoing request configuration section.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Most lookups made per minute across the whole process; extra requests
+    /// wait for the budget to refill. `0` removes the limit.
+    pub requests_per_minute: u32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 30,
+        }
+    }
+}
+
+/// How a kind of playing item is handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentPolicy {
+    /// Fetch lyrics and cache it like any other track.
+    Cache,
+    /// Cache the metadata but don't look up lyrics.
+    NoLyrics,
+    /// Show it, but neither fetch lyrics nor cache or log it.
+    Skip,
+}
+
+/// Play log configuration section. Logging is enabled by including the
+/// section at all.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ScrobbleLogConfig {
+    /// File each detected play is appended to. Supports `~` for the home directory.
+    pub path: String,
+}
+
+impl Default for ScrobbleLogConfig {
+    fn default() -> Self {
+        Self {
+            path: default_path(
+                Config::get_data_dir().map(|dir| dir.join("scrobbles.log")),
+                "scrobbles.log",
+            ),
+        }
+    }
+}
+
+/// Spotify Web API credentials (from an app registered on the Spotify
+/// developer dashboard). Metadata lookups are enabled by including the
+/// section at all.
+#[derive(Debug, Deserialize)]
+pub struct SpotifyApiConfig {
+    /// The app's client ID.
+    pub client_id: String,
+    /// The app's client secret.
+    pub client_secret: String,
+}
+
+/// Genius API settings. Song credits are looked up when the section has an
+/// access token; lyrics never need one.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct GeniusApiConfig {
+    /// Client access token of an API client from https://genius.com/api-clients.
+    pub access_token: Option<String>,
+}
+
+/// Commands run on player events. Nothing runs unless one is configured.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Shell command run by `--watch` each time the song changes, with the
+    /// `--format` placeholders filled in as quoted arguments.
+    pub on_track_change: Option<String>,
+}
+
+/// Interactive browser configuration section.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// Lines scrolled by `J`/`K` (and Shift+arrows) in the detail view.
+    pub scroll_step: u16,
+    /// Speed up `j`/`k` scrolling while the key is held down.
+    pub accelerate_scroll: bool,
+    /// Start the detail view in two-column layout (toggled with `c`) when the
+    /// terminal is wide enough.
+    pub two_column_lyrics: bool,
+    /// Draw the cover next to the track details in terminals with inline
+    /// image support (builds with the `album-art` feature only).
+    pub album_art: bool,
+}
+
+impl Default for TuiConfig {
+    fn default() -> Self {
+        Self {
+            scroll_step: 5,
+            accelerate_scroll: true,
+            two_column_lyrics: false,
+            album_art: true,
+        }
+    }
+}
+
+/// Output presentation configuration section.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    pub symbols: Symbols,
+    /// Style of the currently playing track in `--search` results.
+    pub now_playing_style: AnsiStyle,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            symbols: Symbols::default(),
+            now_playing_style: AnsiStyle::try_from("bold bright-green".to_string())
+                .expect("default style is valid"),
+        }
+    }
+}
+
+/// Terminal text style written as space-separated words, e.g.
+/// `"bold bright-green"` or `"underline cyan"`. `"none"` means unstyled.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct AnsiStyle {
+    sgr: String,
+}
+
+impl AnsiStyle {
+    /// Wrap `text` in the style's escape codes.
+    pub fn paint(&self, text: &str) -> String {
+        if self.sgr.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", self.sgr, text)
+    }
+}
+
+impl TryFrom<String> for AnsiStyle {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        const COLORS: [&str; 8] = [
+            "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+        ];
+        let mut codes = Vec::new();
+        for word in spec.split_whitespace() {
+            let code = match word {
+                "none" => continue,
+                "bold" => 1,
+                "dim" => 2,
+                "italic" => 3,
+                "underline" => 4,
+                "reverse" => 7,
+                _ => match word.strip_prefix("bright-") {
+                    Some(color) => COLORS.iter().position(|c| *c == color).map(|i| 90 + i),
+                    None => COLORS.iter().position(|c| *c == word).map(|i| 30 + i),
+                }
+                .ok_or_else(|| {
+                    format!(
+                        "unknown style '{}' (use bold, dim, italic, underline, reverse, \
+                         a color such as green, or bright-<color>)",
+                        word
+                    )
+                })?,
+            };
+            codes.push(code.to_string());
+        }
+        Ok(Self {
+            sgr: codes.join(";"),
+        })
+    }
+}
+
+/// Prefixes printed before each field of the track summary. Any field left
+/// out of `[display.symbols]` keeps its default emoji; set one to `""` to
+/// print the label alone.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Symbols {
+    pub track: String,
+    pub artist: String,
+    pub album: String,
+    pub release_date: String,
+    pub duration: String,
+    pub popularity: String,
+    pub genres: String,
+    pub producers: String,
+    pub writers: String,
+    pub lyrics_match: String,
+    pub cached: String,
+    pub plays: String,
+    pub lyrics: String,
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        // Variation-selector emoji render narrow in most terminals, so they
+        // carry an extra space to line up with the others.
+        Self {
+            track: "📀".to_string(),
+            artist: "👤".to_string(),
+            album: "💿".to_string(),
+            release_date: "📅".to_string(),
+            duration: "⏱️ ".to_string(),
+            popularity: "⭐".to_string(),
+            genres: "🎸".to_string(),
+            producers: "🎛️ ".to_string(),
+            writers: "✍️ ".to_string(),
+            lyrics_match: "🎯".to_string(),
+            cached: "🕒".to_string(),
+            plays: "🔁".to_string(),
+            lyrics: "📝".to_string(),
+        }
+    }
+}
+
 impl Config {
-    /// Get the default application directory (`~/.pb/`).
-    pub fn get_app_dir() -> Result<PathBuf> {
-        let home = std::env::var("HOME").context("Failed to get HOME environment variable")?;
-        Ok(PathBuf::from(home).join(".pb"))
+    /// Get the directory holding the config file: `$XDG_CONFIG_HOME/playbot`
+    /// (`~/.config/playbot/` by default), or `~/.pb/` if that already exists.
+    pub fn get_config_dir() -> Result<PathBuf> {
+        app_dir("XDG_CONFIG_HOME", ".config")
     }
 
-    /// Get the default config file path (`~/.pb/config.toml`).
+    /// Get the directory holding the database and play log:
+    /// `$XDG_DATA_HOME/playbot` (`~/.local/share/playbot/` by default), or
+    /// `~/.pb/` if that already exists.
+    pub fn get_data_dir() -> Result<PathBuf> {
+        app_dir("XDG_DATA_HOME", ".local/share")
+    }
+
+    /// Get the default config file path (`config.toml` in the config directory).
     pub fn get_default_config_path() -> Result<PathBuf> {
-        Ok(Self::get_app_dir()?.join("config.toml"))
+        Ok(Self::get_config_dir()?.join("config.toml"))
     }
 
-    /// Get the default database path (`~/.pb/playbot.db`).
-    #[allow(dead_code)]
+    /// Get the default database path (`playbot.db` in the data directory).
     pub fn get_default_db_path() -> Result<PathBuf> {
-        Ok(Self::get_app_dir()?.join("playbot.db"))
+        Ok(Self::get_data_dir()?.join("playbot.db"))
     }
 
-    /// Ensure the application directory (`~/.pb/`) exists, creating it if needed.
-    pub fn ensure_app_dir() -> Result<PathBuf> {
-        let app_dir = Self::get_app_dir()?;
-        if !app_dir.exists() {
-            fs::create_dir_all(&app_dir)
-                .with_context(|| format!("Failed to create directory: {:?}", app_dir))?;
-            println!("✨ Created playbot directory at {:?}", app_dir);
+    /// Ensure the config and data directories exist, creating them if needed.
+    pub fn ensure_app_dir() -> Result<()> {
+        for dir in [Self::get_config_dir()?, Self::get_data_dir()?] {
+            if !dir.exists() {
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("Failed to create directory: {:?}", dir))?;
+                println!("✨ Created playbot directory at {:?}", dir);
+            }
         }
-        Ok(app_dir)
+        Ok(())
     }
 
     /// Load configuration from a TOML file at the given path.
     ///
-    /// Expands `~` to the home directory in the database path.
+    /// Expands `~` to the home directory in the database and scrobble log paths.
     pub fn load(path: &str) -> Result<Self> {
         let contents = fs::read_to_string(path)
             .with_context(|| format!("Failed to read config file: {}", path))?;
@@ -55,12 +458,156 @@ impl Config {
         let mut config: Config =
             toml::from_str(&contents).with_context(|| "Failed to parse config file")?;
 
-        // Expand ~ in database path if present
-        if config.database.path.starts_with("~/") {
-            let home = std::env::var("HOME").context("Failed to get HOME environment variable")?;
-            config.database.path = config.database.path.replacen("~", &home, 1);
+        config.database.path = expand_home(&config.database.path)?;
+        if let Some(path) = env_path(DATABASE_ENV) {
+            config.database.path = expand_home(&path)?;
+        }
+        if let Some(scrobble_log) = &mut config.scrobble_log {
+            scrobble_log.path = expand_home(&scrobble_log.path)?;
         }
 
         Ok(config)
     }
 }
+
+/// Environment variable naming the config file, used when `--config` isn't
+/// given.
+pub const CONFIG_ENV: &str = "PLAYBOT_CONFIG";
+
+/// Environment variable that overrides `database.path` from the config file.
+pub const DATABASE_ENV: &str = "PLAYBOT_DB";
+
+/// The path in environment variable `var`, if it is set and not empty.
+pub fn env_path(var: &str) -> Option<String> {
+    non_empty(std::env::var(var).ok())
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+/// Directory name used under the XDG base directories.
+const APP_NAME: &str = "playbot";
+
+/// Resolve one of playbot's directories. A pre-XDG `~/.pb/` wins so existing
+/// installs keep their config and database where they are.
+fn app_dir(xdg_var: &str, fallback: &str) -> Result<PathBuf> {
+    let home =
+        PathBuf::from(std::env::var("HOME").context("Failed to get HOME environment variable")?);
+    let legacy = home.join(".pb");
+    if legacy.is_dir() {
+        return Ok(legacy);
+    }
+    Ok(xdg_dir(&home, std::env::var(xdg_var).ok(), fallback).join(APP_NAME))
+}
+
+/// An XDG base directory: the variable's value when it is set to an absolute
+/// path (the spec says to ignore anything else), otherwise `fallback` under
+/// the home directory.
+fn xdg_dir(home: &Path, value: Option<String>, fallback: &str) -> PathBuf {
+    match value.map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => home.join(fallback),
+    }
+}
+
+/// A default file path as a config string, falling back to `name` in the
+/// working directory if `HOME` isn't set.
+fn default_path(path: Result<PathBuf>, name: &str) -> String {
+    path.map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| name.to_string())
+}
+
+/// Expand a leading `~/` to the home directory.
+fn expand_home(path: &str) -> Result<String> {
+    if !path.starts_with("~/") {
+        return Ok(path.to_string());
+    }
+    let home = std::env::var("HOME").context("Failed to get HOME environment variable")?;
+    Ok(path.replacen("~", &home, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ansi_style_parses_attributes_and_colors() {
+        let style = AnsiStyle::try_from("bold bright-green".to_string()).unwrap();
+        assert_eq!(style.paint("x"), "\x1b[1;92mx\x1b[0m");
+
+        let style = AnsiStyle::try_from("underline blue".to_string()).unwrap();
+        assert_eq!(style.paint("x"), "\x1b[4;34mx\x1b[0m");
+
+        let style = AnsiStyle::try_from("none".to_string()).unwrap();
+        assert_eq!(style.paint("x"), "x");
+
+        assert!(AnsiStyle::try_from("bright-purple".to_string()).is_err());
+    }
+
+    #[test]
+    fn player_backends_keep_their_order() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(
+            config.player.backends,
+            [PlayerBackend::Playerctl, PlayerBackend::DbusSend]
+        );
+
+        let config: Config = toml::from_str("[player]\nbackends = [\"dbus-send\"]").unwrap();
+        assert_eq!(config.player.backends, [PlayerBackend::DbusSend]);
+
+        assert!(toml::from_str::<Config>("[player]\nbackends = [\"web-api\"]").is_err());
+    }
+
+    #[test]
+    fn xdg_dirs_fall_back_to_home() {
+        let home = Path::new("/home/me");
+        assert_eq!(
+            xdg_dir(home, Some("/xdg/config".to_string()), ".config"),
+            PathBuf::from("/xdg/config")
+        );
+        assert_eq!(
+            xdg_dir(home, None, ".local/share"),
+            PathBuf::from("/home/me/.local/share")
+        );
+        // Relative and empty values are invalid per the spec
+        assert_eq!(
+            xdg_dir(home, Some("relative".to_string()), ".config"),
+            PathBuf::from("/home/me/.config")
+        );
+        assert_eq!(
+            xdg_dir(home, Some(String::new()), ".config"),
+            PathBuf::from("/home/me/.config")
+        );
+    }
+
+    #[test]
+    fn blank_env_paths_are_unset() {
+        assert_eq!(non_empty(None), None);
+        assert_eq!(non_empty(Some(" ".to_string())), None);
+        assert_eq!(
+            non_empty(Some("/data/pb.db".to_string())).as_deref(),
+            Some("/data/pb.db")
+        );
+    }
+
+    #[test]
+    fn lyrics_provider_parses() {
+        let config: Config = toml::from_str("[lyrics]\nprovider = \"lrclib\"").unwrap();
+        assert_eq!(config.lyrics.provider, LyricsSource::Lrclib);
+        let config: Config = toml::from_str("[lyrics]\nprovider = \"none\"").unwrap();
+        assert_eq!(config.lyrics.provider, LyricsSource::None);
+        assert!(toml::from_str::<Config>("[lyrics]\nprovider = \"azlyrics\"").is_err());
+    }
+
+    #[test]
+    fn example_config_parses() {
+        let config: Config = toml::from_str(include_str!("../config.toml.example")).unwrap();
+        assert_eq!(config.default_action, DefaultAction::NowPlaying);
+        assert_eq!(config.lyrics.provider, LyricsSource::Genius);
+        assert_eq!(
+            config.display.now_playing_style,
+            DisplayConfig::default().now_playing_style
+        );
+    }
+}
@@ -1,5 +1,13 @@
-use anyhow::{Context, Result};
-use rusqlite::{params, Connection, Row};
+use anyhow::{bail, Context, Result};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, ErrorCode, OptionalExtension, Row};
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::filter::Filter;
 
 /// Persistent track cache backed by SQLite.
 ///
@@ -9,7 +17,7 @@ pub struct Database {
 }
 
 /// Full track information stored in the cache.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct TrackInfo {
     pub track_id: String,
     pub track_name: String,
@@ -25,6 +33,239 @@ pub struct TrackInfo {
     pub producers: String,
     /// Comma-separated songwriter names.
     pub writers: String,
+    /// How well the fetched lyrics matched this track's title and artist
+    /// (0.0–1.0). `None` when no lyrics lookup produced a match.
+    pub lyrics_confidence: Option<f64>,
+    /// The lyrics in LRC format, each line stamped with when it is sung
+    /// (`[01:23.45]`), when the provider has them. Stored and kept alongside
+    /// `lyrics`, which holds the same text without timestamps.
+    pub synced_lyrics: Option<String>,
+    /// Position on its album and which disc of the album it is on, when the
+    /// player or the Web API reports them.
+    pub track_number: Option<i64>,
+    pub disc_number: Option<i64>,
+    /// When the row was last written, as SQLite's UTC `YYYY-MM-DD HH:MM:SS`.
+    /// `None` for tracks not read from the cache; inserts always stamp the
+    /// current time, so this is never written back.
+    pub cached_at: Option<String>,
+    /// How many times the track has been seen playing. Maintained by
+    /// [`Database::record_play`]; inserts keep the stored count.
+    pub play_count: i64,
+    /// Starred by the user. Kept across re-fetches, like `play_count`.
+    pub favorite: bool,
+    /// Cover image URL, as reported by the player or the Spotify Web API.
+    pub album_art_url: Option<String>,
+    /// Marked explicit by the Spotify Web API. Only enrichment sets it, and
+    /// like `favorite` it is kept across re-fetches.
+    pub explicit: bool,
+    /// The song's page on Genius, when a lookup matched one. Kept across
+    /// re-fetches that don't find it again.
+    pub genius_url: Option<String>,
+}
+
+#[cfg(test)]
+impl TrackInfo {
+    /// A track with only its ID, title, and artist set, for tests to fill in
+    /// the fields they care about with struct update syntax.
+    pub fn sample(track_id: &str, track_name: &str, artist_name: &str) -> Self {
+        Self {
+            track_id: track_id.to_string(),
+            track_name: track_name.to_string(),
+            artist_name: artist_name.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Rowids of the most recently cached row in each group of tracks sharing a
+/// normalized title/artist/album key.
+const NEWEST_OF_EACH_SONG: &str = "SELECT rowid FROM (
+         SELECT rowid, ROW_NUMBER() OVER (
+             PARTITION BY lower(trim(track_name)), lower(trim(artist_name)),
+                          lower(trim(album_name))
+             ORDER BY cached_at DESC, rowid DESC
+         ) AS rank
+         FROM tracks
+     ) WHERE rank = 1";
+
+/// FTS5 index over the searchable text of `tracks`, reading its content from
+/// the table itself and updated by triggers on every write.
+const LYRICS_INDEX_SCHEMA: &str = "
+    CREATE VIRTUAL TABLE tracks_fts USING fts5(
+        track_name, artist_name, album_name, lyrics, content = 'tracks'
+    );
+    CREATE TRIGGER tracks_fts_insert AFTER INSERT ON tracks BEGIN
+        INSERT INTO tracks_fts (rowid, track_name, artist_name, album_name, lyrics)
+        VALUES (new.rowid, new.track_name, new.artist_name, new.album_name, new.lyrics);
+    END;
+    CREATE TRIGGER tracks_fts_delete AFTER DELETE ON tracks BEGIN
+        INSERT INTO tracks_fts (tracks_fts, rowid, track_name, artist_name, album_name, lyrics)
+        VALUES ('delete', old.rowid, old.track_name, old.artist_name, old.album_name, old.lyrics);
+    END;
+    CREATE TRIGGER tracks_fts_update
+    AFTER UPDATE OF track_name, artist_name, album_name, lyrics ON tracks BEGIN
+        INSERT INTO tracks_fts (tracks_fts, rowid, track_name, artist_name, album_name, lyrics)
+        VALUES ('delete', old.rowid, old.track_name, old.artist_name, old.album_name, old.lyrics);
+        INSERT INTO tracks_fts (rowid, track_name, artist_name, album_name, lyrics)
+        VALUES (new.rowid, new.track_name, new.artist_name, new.album_name, new.lyrics);
+    END;";
+
+/// Shorten a stored `cached_at` timestamp to minutes ("2024-01-02 13:45").
+pub fn format_cached_at(cached_at: &str) -> &str {
+    cached_at.get(..16).unwrap_or(cached_at)
+}
+
+/// Column list matching the field order expected by [`row_to_track_info`].
+const TRACK_COLUMNS: &str = "track_id, track_name, artist_name, album_name, release_date,
+     duration_ms, popularity, genres, lyrics, producers, writers, lyrics_confidence, cached_at,
+     play_count, favorite, album_art_url, synced_lyrics, track_number, disc_number,
+     explicit, genius_url";
+
+/// Field used to order track listings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    /// Track title
+    Name,
+    /// Artist, then track title
+    Artist,
+    /// Spotify popularity score
+    Popularity,
+    /// Release year; tracks without a release date sort last
+    Year,
+    /// Number of times the track has been played
+    Plays,
+}
+
+/// What `--merge-db` does with a track that exists in both databases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Keep the local row
+    #[default]
+    Skip,
+    /// Overwrite the local row with the other database's
+    Replace,
+    /// Keep whichever row was cached more recently
+    Newer,
+}
+
+/// Row counts reported by [`Database::merge_from`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeStats {
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+/// What [`Database::insert_track_info`] did with a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Written {
+    /// The song wasn't cached yet.
+    Inserted,
+    /// A row under the same ID was replaced.
+    Replaced,
+    /// Rows for the same song under other IDs were folded into it.
+    Merged,
+}
+
+/// Row counts reported by [`Database::import_tracks`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub inserted: usize,
+    pub replaced: usize,
+    pub merged: usize,
+}
+
+/// Row counts reported by [`Database::reindex`].
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReindexStats {
+    pub headers_stripped: usize,
+    pub duplicates_removed: usize,
+}
+
+/// Rows recorded separately for one song, as found by
+/// [`Database::find_duplicates`].
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    /// The row kept: one with lyrics if any, then the most played, then the
+    /// most recently cached.
+    pub keep: TrackInfo,
+    /// The rows merged into `keep` and deleted.
+    pub remove: Vec<TrackInfo>,
+}
+
+/// Collection overview reported by [`Database::stats`].
+#[derive(Debug, Default, PartialEq)]
+pub struct LibraryStats {
+    pub tracks: usize,
+    pub artists: usize,
+    /// Distinct album names per artist, ignoring tracks without an album.
+    pub albums: usize,
+    /// `None` for an empty cache.
+    pub average_popularity: Option<f64>,
+    pub total_duration_ms: i64,
+    /// Artists with the most cached tracks, most first.
+    pub top_artists: Vec<(String, usize)>,
+    /// Earliest and latest known release dates.
+    pub oldest_release: Option<String>,
+    pub newest_release: Option<String>,
+}
+
+/// How many artists [`Database::stats`] ranks.
+const TOP_ARTISTS: usize = 5;
+
+/// Ordering applied to a track listing query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackSort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl TrackSort {
+    fn order_by_clause(&self) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        match self.key {
+            SortKey::Name => format!("track_name COLLATE NOCASE {}", direction),
+            SortKey::Artist => format!(
+                "artist_name COLLATE NOCASE {0}, track_name COLLATE NOCASE {0}",
+                direction
+            ),
+            SortKey::Popularity => format!("popularity {}", direction),
+            SortKey::Plays => format!("play_count {}", direction),
+            SortKey::Year => format!(
+                "NULLIF(substr(release_date, 1, 4), '') {} NULLS LAST",
+                direction
+            ),
+        }
+    }
+}
+
+/// Sorting and filtering applied to a track listing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ListOptions<'a> {
+    pub sort: Option<TrackSort>,
+    pub filter: Option<&'a Filter>,
+    /// Leave out tracks marked explicit.
+    pub clean_only: bool,
+}
+
+impl ListOptions<'_> {
+    fn order_by(&self, default: &str) -> String {
+        self.sort
+            .map_or_else(|| default.to_string(), |s| s.order_by_clause())
+    }
+
+    /// SQL condition for the filter (`1` when unfiltered) with placeholders
+    /// starting at `?{first_param}`, plus the values to bind.
+    fn condition(&self, first_param: usize) -> (String, Vec<Value>) {
+        let (condition, params) = self
+            .filter
+            .map_or_else(|| ("1".to_string(), Vec::new()), |f| f.to_sql(first_param));
+        if self.clean_only {
+            (format!("({}) AND NOT explicit", condition), params)
+        } else {
+            (condition, params)
+        }
+    }
 }
 
 fn row_to_track_info(row: &Row) -> rusqlite::Result<TrackInfo> {
@@ -40,9 +281,91 @@ fn row_to_track_info(row: &Row) -> rusqlite::Result<TrackInfo> {
         lyrics: row.get(8)?,
         producers: row.get(9)?,
         writers: row.get(10)?,
+        lyrics_confidence: row.get(11)?,
+        cached_at: row.get(12)?,
+        play_count: row.get(13)?,
+        favorite: row.get(14)?,
+        album_art_url: row.get(15)?,
+        synced_lyrics: row.get(16)?,
+        track_number: row.get(17)?,
+        disc_number: row.get(18)?,
+        explicit: row.get(19)?,
+        genius_url: row.get(20)?,
+    })
+}
+
+/// How long SQLite waits for another connection's lock before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Extra attempts made by [`retry_on_busy`] after SQLite's own wait expires.
+const BUSY_RETRIES: u32 = 2;
+
+/// Whether `error` is SQLite reporting that another connection holds a lock.
+fn is_busy(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<rusqlite::Error>(),
+            Some(rusqlite::Error::SqliteFailure(e, _))
+                if matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+        )
     })
 }
 
+/// Run a write, retrying it a couple of times if the database stays locked,
+/// and explain the failure if it never frees up.
+fn retry_on_busy<T>(mut write: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match write() {
+            Err(e) if is_busy(&e) && attempt < BUSY_RETRIES => {
+                attempt += 1;
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            Err(e) if is_busy(&e) => {
+                return Err(e.context(
+                    "The database is locked by another playbot process (such as --browse); \
+                     close it or try again in a moment",
+                ));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Lowercase `text` and drop its accents, so "Beyoncé" and "BEYONCE" compare
+/// equal. Registered on every connection as the SQL function `fold`.
+pub fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Title and artist reduced to their folded letters and digits, so
+/// synthesized IDs that differ only in punctuation ("Don't Stop", "Dont
+/// Stop", "Don’t Stop") name the same song.
+fn song_key(track: &TrackInfo) -> (String, String) {
+    let squeeze = |text: &str| fold(text).chars().filter(|c| c.is_alphanumeric()).collect();
+    (squeeze(&track.track_name), squeeze(&track.artist_name))
+}
+
+/// Strip the "🎵 track / 👤 artist" heading that older versions embedded at
+/// the top of stored lyrics.
+///
+/// Only matches the exact three-line shape the old fetcher produced (title line,
+/// artist line, blank line) so real lyric content is never touched.
+fn strip_legacy_lyrics_header(lyrics: &str) -> Option<&str> {
+    let mut lines = lyrics.splitn(4, '\n');
+    let title = lines.next()?;
+    let artist = lines.next()?;
+    let blank = lines.next()?;
+    if title.starts_with("🎵 ") && artist.starts_with("👤 ") && blank.is_empty() {
+        Some(lines.next().unwrap_or(""))
+    } else {
+        None
+    }
+}
+
 impl Database {
     /// Open (or create) the database at the given path.
     ///
@@ -50,6 +373,21 @@ impl Database {
     pub fn new(path: &str) -> Result<Self> {
         let conn =
             Connection::open(path).with_context(|| format!("Failed to open database: {}", path))?;
+        // Another playbot (e.g. the TUI) may be writing; wait for it rather
+        // than failing straight away.
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Write-ahead logging lets the TUI keep reading while a now-playing
+        // lookup writes. In-memory databases keep their own journal mode.
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        // Rows removed by INSERT OR REPLACE only fire delete triggers (which
+        // keep the lyrics search index in sync) with this on.
+        conn.pragma_update(None, "recursive_triggers", true)?;
+        conn.create_scalar_function(
+            "fold",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| Ok(ctx.get::<Option<String>>(0)?.map(|text| fold(&text))),
+        )?;
 
         Ok(Self { conn })
     }
@@ -86,22 +424,15 @@ impl Database {
             )
             .context("Failed to create schema_version table")?;
 
-        let current_version: i32 = self.conn.query_row(
-            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
-            [],
-            |row| row.get(0),
-        )?;
+        let current_version = self.schema_version()?;
 
         // Migration 1: transition to Spotify URI track IDs.
         // Old entries using "title-artist" format continue to work;
         // new entries use "spotify:track:xxxxx" format.
-        if current_version < 1 {
-            self.conn
-                .execute("INSERT INTO schema_version (version) VALUES (1)", [])?;
-        }
+        self.migrate(current_version, 1, || Ok(()))?;
 
         // Migration 2: add indexes for query performance.
-        if current_version < 2 {
+        self.migrate(current_version, 2, || {
             self.conn.execute(
                 "CREATE INDEX IF NOT EXISTS idx_tracks_cached_at ON tracks(cached_at)",
                 [],
@@ -110,22 +441,432 @@ impl Database {
                 "CREATE INDEX IF NOT EXISTS idx_tracks_artist ON tracks(artist_name COLLATE NOCASE)",
                 [],
             )?;
+            Ok(())
+        })?;
+
+        // Migration 3: lyric headings moved to the display layer, so strip the
+        // ones embedded in previously cached lyrics.
+        self.migrate(current_version, 3, || {
+            self.strip_lyrics_headers()?;
+            Ok(())
+        })?;
+
+        // Migration 4: record how confidently fetched lyrics matched the track.
+        self.migrate(current_version, 4, || {
             self.conn
-                .execute("INSERT INTO schema_version (version) VALUES (2)", [])?;
-        }
+                .execute("ALTER TABLE tracks ADD COLUMN lyrics_confidence REAL", [])?;
+            Ok(())
+        })?;
+
+        // Migration 5: index the normalized title/artist/album key that
+        // inserts use to find near-duplicate rows.
+        self.migrate(current_version, 5, || {
+            self.conn.execute(
+                "CREATE INDEX IF NOT EXISTS idx_tracks_normalized ON tracks(
+                    lower(trim(track_name)), lower(trim(artist_name)), lower(trim(album_name)))",
+                [],
+            )?;
+            Ok(())
+        })?;
+
+        // Migration 6: count how often each track is played.
+        self.migrate(current_version, 6, || {
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN play_count INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+            Ok(())
+        })?;
+
+        // Migration 7: let the user star tracks.
+        self.migrate(current_version, 7, || {
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN favorite INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+            Ok(())
+        })?;
+
+        // Migration 8: full-text index over titles and lyrics, kept in sync by
+        // triggers. SQLite builds without FTS5 skip it and search lyrics with
+        // LIKE instead.
+        self.migrate(current_version, 8, || {
+            let has_fts5: bool = self.conn.query_row(
+                "SELECT sqlite_compileoption_used('ENABLE_FTS5')",
+                [],
+                |row| row.get(0),
+            )?;
+            if has_fts5 {
+                self.conn.execute_batch(LYRICS_INDEX_SCHEMA)?;
+                self.rebuild_lyrics_index()?;
+            }
+            Ok(())
+        })?;
+
+        // Migration 9: remember where each track's cover image is.
+        self.migrate(current_version, 9, || {
+            self.conn
+                .execute("ALTER TABLE tracks ADD COLUMN album_art_url TEXT", [])?;
+            Ok(())
+        })?;
+
+        // Migration 10: keep time-synced (LRC) lyrics next to the plain text.
+        self.migrate(current_version, 10, || {
+            self.conn
+                .execute("ALTER TABLE tracks ADD COLUMN synced_lyrics TEXT", [])?;
+            Ok(())
+        })?;
+
+        // Migration 11: album position, so album listings can be in order.
+        self.migrate(current_version, 11, || {
+            self.conn
+                .execute("ALTER TABLE tracks ADD COLUMN track_number INTEGER", [])?;
+            self.conn
+                .execute("ALTER TABLE tracks ADD COLUMN disc_number INTEGER", [])?;
+            Ok(())
+        })?;
+
+        // Migration 12: the Web API's explicit flag, for --clean-only.
+        self.migrate(current_version, 12, || {
+            self.conn.execute(
+                "ALTER TABLE tracks ADD COLUMN explicit INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+            Ok(())
+        })?;
+
+        // Migration 13: the matched song's Genius page, for --open-genius.
+        self.migrate(current_version, 13, || {
+            self.conn
+                .execute("ALTER TABLE tracks ADD COLUMN genius_url TEXT", [])?;
+            Ok(())
+        })?;
+
+        Ok(())
+    }
 
+    /// Apply migration `version` by running `step`, unless the database is
+    /// already at or past it. New migrations go at the end of [`Database::init`]
+    /// with the next version number.
+    ///
+    /// The step and its `schema_version` row commit in one transaction, so a
+    /// migration interrupted halfway (say, after an `ALTER TABLE`) is rolled
+    /// back and simply runs again next time.
+    fn migrate(
+        &self,
+        current_version: i32,
+        version: i32,
+        step: impl FnOnce() -> Result<()>,
+    ) -> Result<()> {
+        if current_version >= version {
+            return Ok(());
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        step().with_context(|| format!("Failed to apply database migration {}", version))?;
+        tx.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            params![version],
+        )?;
+        tx.commit()?;
         Ok(())
     }
 
+    /// Highest applied migration, or 0 for a database that has never been
+    /// initialized.
+    fn schema_version(&self) -> Result<i32> {
+        let has_table: bool = self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master
+             WHERE type = 'table' AND name = 'schema_version')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(0);
+        }
+        Ok(self.conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Copy tracks from another playbot database at `path` into this one, in a
+    /// single transaction.
+    ///
+    /// The other database is migrated to the current schema first; one written
+    /// by a newer playbot is refused. A track already present here, under the
+    /// same ID or as a near-duplicate, keeps one row chosen by `on_conflict`,
+    /// with the play counts of both summed and starred if either side is.
+    ///
+    /// Nothing records which databases were merged, so merging the same one
+    /// again adds its plays a second time.
+    pub fn merge_from(&self, path: &str, on_conflict: OnConflict) -> Result<MergeStats> {
+        if !std::path::Path::new(path).exists() {
+            bail!("Database to merge not found: {}", path);
+        }
+        let source = Database::new(path)?;
+        if source.schema_version()? > self.schema_version()? {
+            bail!(
+                "{} was written by a newer version of playbot; upgrade before merging",
+                path
+            );
+        }
+        source.init()?;
+        drop(source);
+
+        self.conn
+            .execute("ATTACH DATABASE ?1 AS merge_source", params![path])
+            .with_context(|| format!("Failed to attach database: {}", path))?;
+        let result = self.merge_attached(on_conflict);
+        self.conn.execute("DETACH DATABASE merge_source", [])?;
+        result
+    }
+
+    fn merge_attached(&self, on_conflict: OnConflict) -> Result<MergeStats> {
+        let tx = self.conn.unchecked_transaction()?;
+        let incoming = tx
+            .prepare(&format!(
+                "SELECT {} FROM merge_source.tracks",
+                TRACK_COLUMNS
+            ))?
+            .query_map([], row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let has_lyrics = |t: &TrackInfo| t.lyrics.as_deref().is_some_and(|l| !l.trim().is_empty());
+        let mut stats = MergeStats::default();
+        for theirs in incoming {
+            // The same song here: the same ID, or a row `insert_track_info`
+            // would fold into it. The row under the same ID comes first.
+            let ours = tx
+                .prepare_cached(&format!(
+                    "SELECT {} FROM main.tracks
+                     WHERE track_id = ?1
+                        OR (lower(trim(track_name)) = lower(trim(?2))
+                            AND lower(trim(artist_name)) = lower(trim(?3))
+                            AND lower(trim(album_name)) = lower(trim(?4)))
+                     ORDER BY track_id = ?1 DESC, cached_at DESC",
+                    TRACK_COLUMNS
+                ))?
+                .query_map(
+                    params![
+                        theirs.track_id,
+                        theirs.track_name,
+                        theirs.artist_name,
+                        theirs.album_name
+                    ],
+                    row_to_track_info,
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let take_theirs = match ours.first() {
+                None => true,
+                Some(local) => match on_conflict {
+                    OnConflict::Skip => false,
+                    OnConflict::Replace => true,
+                    OnConflict::Newer => theirs.cached_at > local.cached_at,
+                },
+            };
+            match (ours.is_empty(), take_theirs) {
+                (true, _) => stats.added += 1,
+                (false, true) => stats.updated += 1,
+                (false, false) => stats.skipped += 1,
+            }
+
+            // Whichever row is kept, plays from both sides add up and a star
+            // on either side survives.
+            let mut row = if take_theirs {
+                theirs.clone()
+            } else {
+                ours[0].clone()
+            };
+            row.play_count = theirs.play_count + ours.iter().map(|t| t.play_count).sum::<i64>();
+            row.favorite = theirs.favorite || ours.iter().any(|t| t.favorite);
+            row.explicit = theirs.explicit || ours.iter().any(|t| t.explicit);
+            if row.genius_url.is_none() {
+                row.genius_url = ours.iter().find_map(|t| t.genius_url.clone());
+            }
+            // Lyrics cached here fill a row that has none.
+            if !has_lyrics(&row) {
+                if let Some(local) = ours.iter().find(|t| has_lyrics(t)) {
+                    row.lyrics = local.lyrics.clone();
+                    row.lyrics_confidence = local.lyrics_confidence;
+                    row.synced_lyrics = local.synced_lyrics.clone();
+                }
+            }
+
+            for local in &ours {
+                tx.execute(
+                    "DELETE FROM main.tracks WHERE track_id = ?1",
+                    params![local.track_id],
+                )?;
+            }
+            tx.execute(
+                &format!(
+                    "INSERT INTO main.tracks ({}) VALUES
+                     (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                      ?17, ?18, ?19, ?20, ?21)",
+                    TRACK_COLUMNS
+                ),
+                params![
+                    row.track_id,
+                    row.track_name,
+                    row.artist_name,
+                    row.album_name,
+                    row.release_date,
+                    row.duration_ms,
+                    row.popularity,
+                    row.genres,
+                    row.lyrics,
+                    row.producers,
+                    row.writers,
+                    row.lyrics_confidence,
+                    row.cached_at,
+                    row.play_count,
+                    row.favorite,
+                    row.album_art_url,
+                    row.synced_lyrics,
+                    row.track_number,
+                    row.disc_number,
+                    row.explicit,
+                    row.genius_url,
+                ],
+            )
+            .context("Failed to merge track")?;
+        }
+
+        tx.commit()?;
+        Ok(stats)
+    }
+
+    /// Remove legacy lyric headings, returning how many rows changed.
+    fn strip_lyrics_headers(&self) -> Result<usize> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT track_id, lyrics FROM tracks WHERE lyrics LIKE '🎵 %'")?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stripped_count = 0;
+        for (track_id, lyrics) in rows {
+            if let Some(stripped) = strip_legacy_lyrics_header(&lyrics) {
+                self.conn.execute(
+                    "UPDATE tracks SET lyrics = ?1 WHERE track_id = ?2",
+                    params![stripped, track_id],
+                )?;
+                stripped_count += 1;
+            }
+        }
+
+        Ok(stripped_count)
+    }
+
+    /// Delete all but the most recently cached row of each group sharing a
+    /// normalized title/artist/album key, returning how many were removed.
+    fn remove_near_duplicates(&self) -> Result<usize> {
+        const SAME_SONG: &str = "lower(trim(other.track_name)) = lower(trim(tracks.track_name))
+             AND lower(trim(other.artist_name)) = lower(trim(tracks.artist_name))
+             AND lower(trim(other.album_name)) = lower(trim(tracks.album_name))";
+        // The surviving row of each group takes over the plays, star, and
+        // explicit flag of the ones removed. Only survivors are updated, so
+        // no row's total is counted into another's.
+        self.conn.execute(
+            &format!(
+                "UPDATE tracks SET (play_count, favorite, explicit) = (
+                     SELECT SUM(other.play_count), MAX(other.favorite), MAX(other.explicit)
+                     FROM tracks AS other WHERE {}
+                 )
+                 WHERE rowid IN ({})",
+                SAME_SONG, NEWEST_OF_EACH_SONG
+            ),
+            [],
+        )?;
+        // A survivor's empty columns are filled from the newest removed row
+        // that has them, the lyrics together with their confidence and
+        // synced lines.
+        let newest_with = |column: &str, present: &str| {
+            format!(
+                "{0} = COALESCE((
+                     SELECT other.{0} FROM tracks AS other
+                     WHERE {1} AND trim(COALESCE(other.{2}, '')) != ''
+                     ORDER BY other.cached_at DESC, other.rowid DESC LIMIT 1
+                 ), {0})",
+                column, SAME_SONG, present
+            )
+        };
+        self.conn.execute(
+            &format!(
+                "UPDATE tracks SET {}, {}, {}
+                 WHERE rowid IN ({}) AND trim(COALESCE(lyrics, '')) = ''",
+                newest_with("lyrics", "lyrics"),
+                newest_with("lyrics_confidence", "lyrics"),
+                newest_with("synced_lyrics", "lyrics"),
+                NEWEST_OF_EACH_SONG
+            ),
+            [],
+        )?;
+        for column in [
+            "album_art_url",
+            "genres",
+            "producers",
+            "writers",
+            "genius_url",
+        ] {
+            self.conn.execute(
+                &format!(
+                    "UPDATE tracks SET {}
+                     WHERE rowid IN ({}) AND trim(COALESCE({}, '')) = ''",
+                    newest_with(column, column),
+                    NEWEST_OF_EACH_SONG,
+                    column
+                ),
+                [],
+            )?;
+        }
+        let removed = self.conn.execute(
+            &format!(
+                "DELETE FROM tracks WHERE rowid NOT IN ({})",
+                NEWEST_OF_EACH_SONG
+            ),
+            [],
+        )?;
+        Ok(removed)
+    }
+
+    /// Recompute everything derived from the stored columns, in one
+    /// transaction: legacy lyric headings, near-duplicate rows, and indexes.
+    ///
+    /// `progress` is called with a description before each step.
+    pub fn reindex(&self, mut progress: impl FnMut(&str)) -> Result<ReindexStats> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        progress("Stripping legacy lyric headings");
+        let headers_stripped = self.strip_lyrics_headers()?;
+
+        progress("Merging near-duplicate tracks");
+        let duplicates_removed = self.remove_near_duplicates()?;
+
+        progress("Rebuilding indexes");
+        self.conn.execute("REINDEX tracks", [])?;
+        self.rebuild_lyrics_index()?;
+
+        tx.commit()?;
+        Ok(ReindexStats {
+            headers_stripped,
+            duplicates_removed,
+        })
+    }
+
     /// Look up a track by its Spotify URI (e.g. `spotify:track:xxxxx`).
     ///
     /// Returns `None` if the track is not in the cache.
     pub fn get_track_info(&self, track_id: &str) -> Result<Option<TrackInfo>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT track_id, track_name, artist_name, album_name, release_date,
-                    duration_ms, popularity, genres, lyrics, producers, writers
-             FROM tracks WHERE track_id = ?1",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks WHERE track_id = ?1",
+            TRACK_COLUMNS
+        ))?;
 
         match stmt.query_row(params![track_id], row_to_track_info) {
             Ok(info) => Ok(Some(info)),
@@ -134,15 +875,158 @@ impl Database {
         }
     }
 
+    /// Look up a track like [`Database::get_track_info`], but treat a row
+    /// cached more than `ttl_hours` ago as missing so it gets fetched again.
+    /// A TTL of `0` never expires.
+    pub fn get_fresh_track_info(
+        &self,
+        track_id: &str,
+        ttl_hours: u64,
+    ) -> Result<Option<TrackInfo>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks
+             WHERE track_id = ?1
+               AND (?2 = 0 OR cached_at > datetime('now', '-' || ?2 || ' hours'))",
+            TRACK_COLUMNS
+        ))?;
+
+        match stmt.query_row(params![track_id, ttl_hours as i64], row_to_track_info) {
+            Ok(info) => Ok(Some(info)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Insert or replace a track in the cache.
-    pub fn insert_track_info(&self, info: &TrackInfo) -> Result<()> {
+    ///
+    /// A row under a different ID whose title, artist, and album match after
+    /// trimming and case folding is the same song captured differently, so it
+    /// is replaced rather than kept alongside the new one.
+    pub fn insert_track_info(&self, info: &TrackInfo) -> Result<Written> {
+        retry_on_busy(|| {
+            let tx = self.conn.unchecked_transaction()?;
+            let written = self.write_track_info(info)?;
+            tx.commit()?;
+            Ok(written)
+        })
+    }
+
+    /// Insert tracks read from an export in one transaction, so a failure
+    /// partway leaves the cache as it was. Each track is written as by
+    /// [`Database::insert_track_info`], then its play count and star are
+    /// raised to at least the exported values, so importing the same export
+    /// twice doesn't inflate them.
+    pub fn import_tracks(&self, tracks: &[TrackInfo]) -> Result<ImportStats> {
+        retry_on_busy(|| {
+            let tx = self.conn.unchecked_transaction()?;
+            let mut stats = ImportStats::default();
+            for track in tracks {
+                match self.write_track_info(track)? {
+                    Written::Inserted => stats.inserted += 1,
+                    Written::Replaced => stats.replaced += 1,
+                    Written::Merged => stats.merged += 1,
+                }
+                tx.execute(
+                    "UPDATE tracks SET play_count = MAX(play_count, ?2),
+                                       favorite = MAX(favorite, ?3)
+                     WHERE track_id = ?1",
+                    params![track.track_id, track.play_count, track.favorite],
+                )
+                .context("Failed to update play count")?;
+            }
+            tx.commit()?;
+            Ok(stats)
+        })
+    }
+
+    /// Write one track, within the caller's transaction.
+    fn write_track_info(&self, info: &TrackInfo) -> Result<Written> {
+        // Plays, the star, the explicit flag, and the Genius page of the row
+        // being replaced, and of any duplicate folded into it, carry over to
+        // the new row.
+        let (play_count, favorite, explicit, genius_url, written): (
+            i64,
+            bool,
+            bool,
+            Option<String>,
+            Written,
+        ) = self.conn.query_row(
+            "SELECT COALESCE(SUM(play_count), 0), COALESCE(MAX(favorite), 0),
+                    COALESCE(MAX(explicit), 0), MAX(genius_url),
+                    COALESCE(MAX(track_id = ?1), 0), COUNT(*) FROM tracks
+             WHERE track_id = ?1
+                OR (lower(trim(track_name)) = lower(trim(?2))
+                    AND lower(trim(artist_name)) = lower(trim(?3))
+                    AND lower(trim(album_name)) = lower(trim(?4)))",
+            params![
+                info.track_id,
+                info.track_name,
+                info.artist_name,
+                info.album_name
+            ],
+            |row| {
+                let written = match (row.get::<_, bool>(4)?, row.get::<_, i64>(5)?) {
+                    (true, _) => Written::Replaced,
+                    (false, 0) => Written::Inserted,
+                    (false, _) => Written::Merged,
+                };
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, written))
+            },
+        )?;
+        // A lookup that found nothing this time keeps the lyrics cached
+        // before, preferring the row under the same ID.
+        let (lyrics, lyrics_confidence, synced_lyrics) = match info.lyrics.as_deref() {
+            Some(lyrics) if !lyrics.trim().is_empty() => (
+                Some(lyrics.to_string()),
+                info.lyrics_confidence,
+                info.synced_lyrics.clone(),
+            ),
+            _ => self
+                .conn
+                .query_row(
+                    "SELECT lyrics, lyrics_confidence, synced_lyrics FROM tracks
+                     WHERE trim(COALESCE(lyrics, '')) != ''
+                       AND (track_id = ?1
+                            OR (lower(trim(track_name)) = lower(trim(?2))
+                                AND lower(trim(artist_name)) = lower(trim(?3))
+                                AND lower(trim(album_name)) = lower(trim(?4))))
+                     ORDER BY track_id = ?1 DESC
+                     LIMIT 1",
+                    params![
+                        info.track_id,
+                        info.track_name,
+                        info.artist_name,
+                        info.album_name
+                    ],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?
+                .unwrap_or((None, None, None)),
+        };
+        self.conn
+            .execute(
+                "DELETE FROM tracks
+             WHERE track_id != ?1
+               AND lower(trim(track_name)) = lower(trim(?2))
+               AND lower(trim(artist_name)) = lower(trim(?3))
+               AND lower(trim(album_name)) = lower(trim(?4))",
+                params![
+                    info.track_id,
+                    info.track_name,
+                    info.artist_name,
+                    info.album_name
+                ],
+            )
+            .context("Failed to replace duplicate track")?;
         self.conn
             .execute(
                 "INSERT OR REPLACE INTO tracks
              (track_id, track_name, artist_name, album_name, release_date,
               duration_ms, popularity, genres, lyrics, producers, writers,
-              cached_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, CURRENT_TIMESTAMP)",
+              lyrics_confidence, play_count, favorite, album_art_url, synced_lyrics,
+              track_number, disc_number, explicit, genius_url, cached_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                     ?17, ?18, ?19, ?20, CURRENT_TIMESTAMP)",
                 params![
                     info.track_id,
                     info.track_name,
@@ -152,46 +1036,173 @@ impl Database {
                     info.duration_ms,
                     info.popularity,
                     info.genres,
-                    info.lyrics,
+                    lyrics,
                     info.producers,
                     info.writers,
+                    lyrics_confidence,
+                    play_count,
+                    favorite,
+                    info.album_art_url,
+                    synced_lyrics,
+                    info.track_number,
+                    info.disc_number,
+                    info.explicit || explicit,
+                    info.genius_url.as_ref().or(genius_url.as_ref()),
                 ],
             )
             .context("Failed to insert track info")?;
 
-        Ok(())
+        Ok(written)
     }
 
-    /// Return the most recently cached tracks, up to `limit`.
-    pub fn get_recent_tracks(&self, limit: usize) -> Result<Vec<TrackInfo>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT track_id, track_name, artist_name, album_name, release_date,
-                    duration_ms, popularity, genres, lyrics, producers, writers
-             FROM tracks
-             ORDER BY cached_at DESC
-             LIMIT ?1",
-        )?;
+    /// Return the most recently cached tracks matching the options' filter, up
+    /// to `limit`, ordered by the options' sort (newest first otherwise).
+    pub fn get_recent_tracks(&self, limit: usize, options: &ListOptions) -> Result<Vec<TrackInfo>> {
+        let (condition, filter_params) = options.condition(2);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {0} FROM (
+                 SELECT {0} FROM tracks
+                 WHERE {1}
+                 ORDER BY cached_at DESC LIMIT ?1
+             )
+             ORDER BY {2}",
+            TRACK_COLUMNS,
+            condition,
+            options.order_by("cached_at DESC")
+        ))?;
+
+        let mut values = vec![Value::Integer(limit as i64)];
+        values.extend(filter_params);
+        let tracks = stmt
+            .query_map(params_from_iter(values), row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tracks)
+    }
+
+    /// Search titles, artists, albums, and lyrics for `phrase` (e.g. a
+    /// remembered lyric line), best matches first.
+    ///
+    /// Uses the full-text index where SQLite supports it, matching the words
+    /// of the phrase in order regardless of case and punctuation; otherwise
+    /// falls back to a substring match, newest first.
+    pub fn search_lyrics(&self, phrase: &str) -> Result<Vec<TrackInfo>> {
+        if phrase.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let (sql, param) = if self.has_lyrics_index()? {
+            (
+                format!(
+                    "SELECT {} FROM tracks
+                     JOIN (SELECT rowid AS hit, rank FROM tracks_fts WHERE tracks_fts MATCH ?1)
+                       ON tracks.rowid = hit
+                     ORDER BY rank",
+                    TRACK_COLUMNS
+                ),
+                // Quoted as one FTS5 phrase so the user's punctuation is never
+                // parsed as query syntax.
+                format!("\"{}\"", phrase.replace('"', "\"\"")),
+            )
+        } else {
+            (
+                format!(
+                    "SELECT {} FROM tracks
+                     WHERE lyrics LIKE ?1 OR track_name LIKE ?1
+                        OR artist_name LIKE ?1 OR album_name LIKE ?1
+                     ORDER BY cached_at DESC",
+                    TRACK_COLUMNS
+                ),
+                format!("%{}%", phrase),
+            )
+        };
 
+        let mut stmt = self.conn.prepare(&sql)?;
         let tracks = stmt
-            .query_map(params![limit], row_to_track_info)?
+            .query_map(params![param], row_to_track_info)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(tracks)
     }
 
-    /// Search for tracks by name, artist, or album (case-insensitive substring match).
+    fn has_lyrics_index(&self) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master
+             WHERE type = 'table' AND name = 'tracks_fts')",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Repopulate the full-text index from the tracks table, if there is one.
+    fn rebuild_lyrics_index(&self) -> Result<()> {
+        if self.has_lyrics_index()? {
+            self.conn
+                .execute("INSERT INTO tracks_fts (tracks_fts) VALUES ('rebuild')", [])?;
+        }
+        Ok(())
+    }
+
+    /// Search for tracks by name, artist, or album (substring match ignoring
+    /// case and accents).
     pub fn search_tracks(&self, query: &str) -> Result<Vec<TrackInfo>> {
-        let search_pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
-            "SELECT track_id, track_name, artist_name, album_name, release_date,
-                    duration_ms, popularity, genres, lyrics, producers, writers
-             FROM tracks
-             WHERE track_name LIKE ?1 OR artist_name LIKE ?1 OR album_name LIKE ?1
-             ORDER BY cached_at DESC",
-        )?;
+        self.search_tracks_with(query, &ListOptions::default())
+    }
+
+    /// Search like [`Database::search_tracks`], applying the options' filter and
+    /// sort (newest first otherwise).
+    pub fn search_tracks_with(&self, query: &str, options: &ListOptions) -> Result<Vec<TrackInfo>> {
+        self.search_tracks_limited(query, None, options)
+    }
+
+    /// Search like [`Database::search_tracks_with`], returning at most `limit`
+    /// results (all of them when `None`). The cap is applied in SQL after
+    /// sorting, so a broad query never loads every match.
+    pub fn search_tracks_limited(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        options: &ListOptions,
+    ) -> Result<Vec<TrackInfo>> {
+        let (condition, filter_params) = options.condition(3);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks
+             WHERE (fold(track_name) LIKE ?1 OR fold(artist_name) LIKE ?1
+                    OR fold(album_name) LIKE ?1)
+               AND {}
+             ORDER BY {}
+             LIMIT ?2",
+            TRACK_COLUMNS,
+            condition,
+            options.order_by("cached_at DESC")
+        ))?;
+
+        // SQLite treats a negative LIMIT as no limit.
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        let mut values = vec![
+            Value::Text(format!("%{}%", fold(query))),
+            Value::Integer(limit),
+        ];
+        values.extend(filter_params);
+        let tracks = stmt
+            .query_map(params_from_iter(values), row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tracks)
+    }
+
+    /// Return every track matching the options' filter, ordered by the options'
+    /// sort (artist and track name otherwise).
+    pub fn list_tracks(&self, options: &ListOptions) -> Result<Vec<TrackInfo>> {
+        let (condition, filter_params) = options.condition(1);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks WHERE {} ORDER BY {}",
+            TRACK_COLUMNS,
+            condition,
+            options.order_by("artist_name, track_name")
+        ))?;
 
         let tracks = stmt
-            .query_map(params![search_pattern], row_to_track_info)?
+            .query_map(params_from_iter(filter_params), row_to_track_info)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(tracks)
@@ -199,12 +1210,10 @@ impl Database {
 
     /// Return all cached tracks sorted by artist and track name.
     pub fn get_all_tracks(&self) -> Result<Vec<TrackInfo>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT track_id, track_name, artist_name, album_name, release_date,
-                    duration_ms, popularity, genres, lyrics, producers, writers
-             FROM tracks
-             ORDER BY artist_name, track_name",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks ORDER BY artist_name, track_name",
+            TRACK_COLUMNS
+        ))?;
 
         let tracks = stmt
             .query_map([], row_to_track_info)?
@@ -213,6 +1222,61 @@ impl Database {
         Ok(tracks)
     }
 
+    /// Every genre named by a cached track, sorted. The comma-joined
+    /// `genres` column is split so each genre appears once.
+    pub fn distinct_genres(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT genres FROM tracks WHERE genres != ''")?;
+        let mut genres = BTreeSet::new();
+        for joined in stmt.query_map([], |row| row.get::<_, String>(0))? {
+            genres.extend(
+                joined?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|genre| !genre.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        Ok(genres.into_iter().collect())
+    }
+
+    /// Every track credited to exactly this artist, ignoring case, in release
+    /// order with each album's tracks together.
+    ///
+    /// Unlike [`Self::search_tracks`], the name isn't matched inside titles,
+    /// albums, or other artist names.
+    pub fn tracks_by_artist(&self, name: &str) -> Result<Vec<TrackInfo>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks WHERE artist_name = ?1 COLLATE NOCASE
+             ORDER BY release_date, album_name, track_name",
+            TRACK_COLUMNS
+        ))?;
+
+        let tracks = stmt
+            .query_map(params![name.trim()], row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tracks)
+    }
+
+    /// Every track on the album with exactly this name, ignoring case, in
+    /// album order. Tracks without a known position follow, by title.
+    pub fn tracks_by_album(&self, name: &str) -> Result<Vec<TrackInfo>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks WHERE album_name = ?1 COLLATE NOCASE
+             ORDER BY track_number IS NULL, COALESCE(disc_number, 1), track_number,
+                      track_name COLLATE NOCASE",
+            TRACK_COLUMNS
+        ))?;
+
+        let tracks = stmt
+            .query_map(params![name.trim()], row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tracks)
+    }
+
     /// Return the total number of tracks in the cache.
     pub fn count_tracks(&self) -> Result<usize> {
         let count: usize = self
@@ -220,23 +1284,257 @@ impl Database {
             .query_row("SELECT COUNT(*) FROM tracks", [], |row| row.get(0))?;
         Ok(count)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Summarize the cache: counts, averages, and ranges are computed in SQL
+    /// rather than by loading every row.
+    pub fn stats(&self) -> Result<LibraryStats> {
+        let mut stats = self.conn.query_row(
+            "SELECT COUNT(*), COUNT(DISTINCT artist_name),
+                    COUNT(DISTINCT CASE WHEN album_name != ''
+                                        THEN artist_name || char(31) || album_name END),
+                    AVG(popularity), COALESCE(SUM(duration_ms), 0),
+                    MIN(NULLIF(release_date, '')), MAX(NULLIF(release_date, ''))
+             FROM tracks",
+            [],
+            |row| {
+                Ok(LibraryStats {
+                    tracks: row.get(0)?,
+                    artists: row.get(1)?,
+                    albums: row.get(2)?,
+                    average_popularity: row.get(3)?,
+                    total_duration_ms: row.get(4)?,
+                    top_artists: Vec::new(),
+                    oldest_release: row.get(5)?,
+                    newest_release: row.get(6)?,
+                })
+            },
+        )?;
 
-    fn test_db() -> Database {
-        let db = Database::new(":memory:").unwrap();
-        db.init().unwrap();
-        db
-    }
+        let mut stmt = self.conn.prepare(
+            "SELECT artist_name, COUNT(*) AS tracks FROM tracks
+             GROUP BY artist_name ORDER BY tracks DESC, artist_name LIMIT ?1",
+        )?;
+        stats.top_artists = stmt
+            .query_map(params![TOP_ARTISTS], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(stats)
+    }
+
+    /// Drop the stored lyrics (and their match score) of every track matching
+    /// the options' filter, keeping all other metadata.
+    ///
+    /// Returns the number of tracks changed and the bytes of lyric text removed.
+    pub fn purge_lyrics(&self, options: &ListOptions) -> Result<(usize, u64)> {
+        retry_on_busy(|| self.clear_lyrics(options))
+    }
+
+    fn clear_lyrics(&self, options: &ListOptions) -> Result<(usize, u64)> {
+        let (condition, filter_params) = options.condition(1);
+        let condition = format!("lyrics IS NOT NULL AND {}", condition);
+
+        let tx = self.conn.unchecked_transaction()?;
+        let bytes: u64 = tx.query_row(
+            &format!(
+                "SELECT COALESCE(SUM(length(CAST(lyrics AS BLOB))
+                                     + COALESCE(length(CAST(synced_lyrics AS BLOB)), 0)), 0)
+                 FROM tracks WHERE {}",
+                condition
+            ),
+            params_from_iter(filter_params.iter()),
+            |row| row.get(0),
+        )?;
+        let purged = tx.execute(
+            &format!(
+                "UPDATE tracks SET lyrics = NULL, lyrics_confidence = NULL, synced_lyrics = NULL
+                 WHERE {}",
+                condition
+            ),
+            params_from_iter(filter_params.iter()),
+        )?;
+        tx.commit()?;
+
+        Ok((purged, bytes))
+    }
+
+    /// Count a play of a cached track, returning its new play count (`0` if
+    /// the track isn't cached).
+    pub fn record_play(&self, track_id: &str) -> Result<i64> {
+        retry_on_busy(|| {
+            let count = self
+                .conn
+                .query_row(
+                    "UPDATE tracks SET play_count = play_count + 1
+                     WHERE track_id = ?1 RETURNING play_count",
+                    params![track_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(count.unwrap_or(0))
+        })
+    }
+
+    /// Star or unstar a cached track. Returns whether the track exists.
+    pub fn set_favorite(&self, track_id: &str, favorite: bool) -> Result<bool> {
+        let updated = retry_on_busy(|| {
+            self.conn
+                .execute(
+                    "UPDATE tracks SET favorite = ?2 WHERE track_id = ?1",
+                    params![track_id, favorite],
+                )
+                .map_err(anyhow::Error::from)
+        })?;
+        Ok(updated > 0)
+    }
+
+    /// Return every starred track matching the options' filter, ordered by
+    /// the options' sort (most recently cached first otherwise).
+    pub fn get_favorites(&self, options: &ListOptions) -> Result<Vec<TrackInfo>> {
+        let (condition, filter_params) = options.condition(1);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tracks WHERE favorite AND {} ORDER BY {}",
+            TRACK_COLUMNS,
+            condition,
+            options.order_by("cached_at DESC")
+        ))?;
+
+        let tracks = stmt
+            .query_map(params_from_iter(filter_params), row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tracks)
+    }
+
+    /// Return the `limit` most played tracks matching the options' filter,
+    /// ordered by the options' sort (most played first otherwise).
+    pub fn get_most_played(&self, limit: usize, options: &ListOptions) -> Result<Vec<TrackInfo>> {
+        let (condition, filter_params) = options.condition(2);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {0} FROM (
+                 SELECT {0} FROM tracks
+                 WHERE play_count > 0 AND {1}
+                 ORDER BY play_count DESC, cached_at DESC LIMIT ?1
+             )
+             ORDER BY {2}",
+            TRACK_COLUMNS,
+            condition,
+            options.order_by("play_count DESC, cached_at DESC")
+        ))?;
+
+        let mut values = vec![Value::Integer(limit as i64)];
+        values.extend(filter_params);
+        let tracks = stmt
+            .query_map(params_from_iter(values), row_to_track_info)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tracks)
+    }
+
+    /// Group cached tracks whose titles and artists match once case, accents,
+    /// and punctuation are ignored, leaving out songs cached only once.
+    pub fn find_duplicates(&self) -> Result<Vec<DuplicateGroup>> {
+        let mut groups: Vec<Vec<TrackInfo>> = Vec::new();
+        let mut by_key = HashMap::new();
+        for track in self.get_all_tracks()? {
+            let key = song_key(&track);
+            // A title with no letters or digits says nothing about the song.
+            if key.0.is_empty() {
+                continue;
+            }
+            let index = *by_key.entry(key).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[index].push(track);
+        }
+
+        let has_lyrics = |t: &TrackInfo| t.lyrics.as_deref().is_some_and(|l| !l.trim().is_empty());
+        Ok(groups
+            .into_iter()
+            .filter(|group| group.len() > 1)
+            .map(|mut group| {
+                group.sort_by(|a, b| {
+                    has_lyrics(b)
+                        .cmp(&has_lyrics(a))
+                        .then(b.play_count.cmp(&a.play_count))
+                        .then(b.cached_at.cmp(&a.cached_at))
+                });
+                let keep = group.remove(0);
+                DuplicateGroup {
+                    keep,
+                    remove: group,
+                }
+            })
+            .collect())
+    }
+
+    /// Merge every group from [`Database::find_duplicates`] in one
+    /// transaction: the kept row takes over the plays (and star) of the
+    /// others, which are deleted. Returns the groups merged, with the kept
+    /// rows' new counts.
+    pub fn merge_duplicates(&self) -> Result<Vec<DuplicateGroup>> {
+        retry_on_busy(|| {
+            let tx = self.conn.unchecked_transaction()?;
+            let mut groups = self.find_duplicates()?;
+            for group in &mut groups {
+                for track in &group.remove {
+                    group.keep.play_count += track.play_count;
+                    group.keep.favorite |= track.favorite;
+                    tx.execute(
+                        "DELETE FROM tracks WHERE track_id = ?1",
+                        params![track.track_id],
+                    )?;
+                }
+                tx.execute(
+                    "UPDATE tracks SET play_count = ?2, favorite = ?3 WHERE track_id = ?1",
+                    params![
+                        group.keep.track_id,
+                        group.keep.play_count,
+                        group.keep.favorite
+                    ],
+                )?;
+            }
+            tx.commit()?;
+            Ok(groups)
+        })
+    }
+
+    /// Remove a track from the cache. Returns whether a row was deleted.
+    pub fn delete_track(&self, track_id: &str) -> Result<bool> {
+        let deleted = retry_on_busy(|| {
+            self.conn
+                .execute("DELETE FROM tracks WHERE track_id = ?1", params![track_id])
+                .map_err(anyhow::Error::from)
+        })?;
+        Ok(deleted > 0)
+    }
+
+    /// Rebuild the database file to release space left by deleted data.
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn
+            .execute("VACUUM", [])
+            .context("Failed to vacuum database")?;
+        // VACUUM may renumber the rowids the full-text index refers to.
+        self.rebuild_lyrics_index()?;
+        // The compacted pages sit in the write-ahead log until a checkpoint
+        // copies them back, so do that now for the file to actually shrink.
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Database {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+        db
+    }
 
     fn sample_track(id: &str, name: &str, artist: &str) -> TrackInfo {
         TrackInfo {
-            track_id: id.to_string(),
-            track_name: name.to_string(),
-            artist_name: artist.to_string(),
             album_name: "Test Album".to_string(),
             release_date: "2024-01-01".to_string(),
             duration_ms: 240000,
@@ -245,9 +1543,48 @@ mod tests {
             lyrics: Some("Test lyrics".to_string()),
             producers: "Test Producer".to_string(),
             writers: "Test Writer".to_string(),
+            lyrics_confidence: Some(1.0),
+            ..TrackInfo::sample(id, name, artist)
         }
     }
 
+    #[test]
+    fn duplicates_differing_in_punctuation_are_grouped() {
+        let db = test_db();
+        let plain = TrackInfo {
+            play_count: 5,
+            ..sample_track("Dont Stop-Fleetwood Mac", "Dont Stop", "Fleetwood Mac")
+        };
+        let quoted = TrackInfo {
+            lyrics: None,
+            play_count: 9,
+            favorite: true,
+            ..sample_track("Don't Stop-Fleetwood Mac", "Don't Stop", "Fleetwood Mac")
+        };
+        let curly = TrackInfo {
+            play_count: 2,
+            ..sample_track("Don’t Stop-fleetwood mac", "Don’t Stop", "fleetwood mac")
+        };
+        db.import_tracks(&[plain.clone(), quoted, curly]).unwrap();
+        db.insert_track_info(&sample_track("id:other", "Dreams", "Fleetwood Mac"))
+            .unwrap();
+
+        let groups = db.find_duplicates().unwrap();
+        assert_eq!(groups.len(), 1);
+        // Lyrics win over plays; among rows with lyrics, the most played.
+        assert_eq!(groups[0].keep.track_id, plain.track_id);
+        assert_eq!(groups[0].remove.len(), 2);
+        assert_eq!(db.count_tracks().unwrap(), 4, "finding must not delete");
+
+        let merged = db.merge_duplicates().unwrap();
+        assert_eq!(merged.len(), 1);
+        assert_eq!(db.count_tracks().unwrap(), 2);
+        let kept = db.get_track_info(&plain.track_id).unwrap().unwrap();
+        assert_eq!(kept.play_count, 16);
+        assert!(kept.favorite);
+        assert!(db.find_duplicates().unwrap().is_empty());
+    }
+
     #[test]
     fn count_tracks_empty_db() {
         let db = test_db();
@@ -266,6 +1603,7 @@ mod tests {
         assert_eq!(info.track_name, "Test Song");
         assert_eq!(info.artist_name, "Test Artist");
         assert_eq!(info.lyrics, Some("Test lyrics".to_string()));
+        assert!(info.cached_at.is_some_and(|at| at.len() == 19));
     }
 
     #[test]
@@ -287,6 +1625,55 @@ mod tests {
         assert_eq!(db.count_tracks().unwrap(), 3);
     }
 
+    #[test]
+    fn stats_summarize_the_cache() {
+        let db = test_db();
+        assert_eq!(db.stats().unwrap(), LibraryStats::default());
+
+        let mut old = sample_track("id:1", "Song One", "Artist A");
+        old.release_date = "1971".to_string();
+        old.popularity = 25;
+        let mut undated = sample_track("id:2", "Song Two", "Artist A");
+        undated.release_date = String::new();
+        undated.album_name = String::new();
+        let mut other = sample_track("id:3", "Song Three", "Artist B");
+        other.album_name = "Other Album".to_string();
+        for track in [&old, &undated, &other] {
+            db.insert_track_info(track).unwrap();
+        }
+
+        let stats = db.stats().unwrap();
+        assert_eq!(stats.tracks, 3);
+        assert_eq!(stats.artists, 2);
+        assert_eq!(stats.albums, 2);
+        assert_eq!(stats.average_popularity, Some(175.0 / 3.0));
+        assert_eq!(stats.total_duration_ms, 720000);
+        assert_eq!(
+            stats.top_artists,
+            vec![("Artist A".to_string(), 2), ("Artist B".to_string(), 1)]
+        );
+        assert_eq!(stats.oldest_release.as_deref(), Some("1971"));
+        assert_eq!(stats.newest_release.as_deref(), Some("2024-01-01"));
+    }
+
+    #[test]
+    fn distinct_genres_splits_and_dedupes() {
+        let db = test_db();
+        for (id, genres) in [
+            ("id:1", "rock, indie"),
+            ("id:2", "indie,art pop"),
+            ("id:3", ""),
+        ] {
+            let track = TrackInfo {
+                genres: genres.to_string(),
+                ..sample_track(id, id, "Artist")
+            };
+            db.insert_track_info(&track).unwrap();
+        }
+
+        assert_eq!(db.distinct_genres().unwrap(), ["art pop", "indie", "rock"]);
+    }
+
     #[test]
     fn search_finds_by_artist() {
         let db = test_db();
@@ -301,6 +1688,116 @@ mod tests {
         assert_eq!(results.len(), 2);
     }
 
+    #[test]
+    fn search_ignores_case_and_accents() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("id:1", "Halo", "Beyoncé"))
+            .unwrap();
+        db.insert_track_info(&sample_track("id:2", "Jóga", "BJÖRK"))
+            .unwrap();
+        db.insert_track_info(&sample_track("id:3", "Creep", "Radiohead"))
+            .unwrap();
+
+        for query in ["beyonce", "BEYONCÉ", "Beyonce"] {
+            let results = db.search_tracks(query).unwrap();
+            assert_eq!(results.len(), 1, "query {:?}", query);
+            assert_eq!(results[0].track_id, "id:1");
+        }
+        for query in ["björk", "bjork", "JOGA"] {
+            let results = db.search_tracks(query).unwrap();
+            assert_eq!(results.len(), 1, "query {:?}", query);
+            assert_eq!(results[0].track_id, "id:2");
+        }
+    }
+
+    #[test]
+    fn tracks_by_artist_matches_the_whole_name() {
+        let db = test_db();
+        let album = |id: &str, name: &str, artist: &str, album: &str, released: &str| TrackInfo {
+            album_name: album.to_string(),
+            release_date: released.to_string(),
+            ..sample_track(id, name, artist)
+        };
+        db.insert_track_info(&album(
+            "id:1",
+            "Reckoner",
+            "Radiohead",
+            "In Rainbows",
+            "2007-10-10",
+        ))
+        .unwrap();
+        db.insert_track_info(&album(
+            "id:2",
+            "Creep",
+            "Radiohead",
+            "Pablo Honey",
+            "1993-02-22",
+        ))
+        .unwrap();
+        db.insert_track_info(&album(
+            "id:3",
+            "Airbag",
+            "radiohead",
+            "OK Computer",
+            "1997-05-21",
+        ))
+        .unwrap();
+        db.insert_track_info(&album(
+            "id:4",
+            "Radiohead",
+            "Someone Else",
+            "Covers",
+            "2000-01-01",
+        ))
+        .unwrap();
+        db.insert_track_info(&album(
+            "id:5",
+            "Harrowdown Hill",
+            "Thom Yorke, Radiohead",
+            "X",
+            "2006",
+        ))
+        .unwrap();
+
+        let ids: Vec<_> = db
+            .tracks_by_artist(" RADIOHEAD ")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.track_id)
+            .collect();
+        assert_eq!(ids, ["id:2", "id:3", "id:1"]);
+    }
+
+    #[test]
+    fn tracks_by_album_follow_album_order() {
+        let db = test_db();
+        for (id, name, album, position) in [
+            ("id:1", "Lucky", "OK Computer", Some((1, 11))),
+            ("id:2", "airbag", "ok computer", Some((1, 1))),
+            ("id:3", "Creep", "Pablo Honey", Some((1, 2))),
+            ("id:4", "OK Computer Medley", "Tributes", None),
+            ("id:5", "Lift", "OK Computer", Some((2, 1))),
+            ("id:6", "Exit Music", "OK Computer", None),
+            ("id:7", "Bonus", "OK Computer", None),
+        ] {
+            db.insert_track_info(&TrackInfo {
+                album_name: album.to_string(),
+                disc_number: position.map(|(disc, _)| disc),
+                track_number: position.map(|(_, track)| track),
+                ..sample_track(id, name, "Radiohead")
+            })
+            .unwrap();
+        }
+
+        let names: Vec<_> = db
+            .tracks_by_album("OK COMPUTER")
+            .unwrap()
+            .into_iter()
+            .map(|t| t.track_name)
+            .collect();
+        assert_eq!(names, ["airbag", "Lucky", "Lift", "Bonus", "Exit Music"]);
+    }
+
     #[test]
     fn search_finds_by_track_name() {
         let db = test_db();
@@ -325,10 +1822,150 @@ mod tests {
             ))
             .unwrap();
         }
-        let recent = db.get_recent_tracks(3).unwrap();
+        let recent = db.get_recent_tracks(3, &ListOptions::default()).unwrap();
         assert_eq!(recent.len(), 3);
     }
 
+    #[test]
+    fn search_sorted_by_popularity_desc() {
+        let db = test_db();
+        for (id, popularity) in [("id:1", 40), ("id:2", 90), ("id:3", 65)] {
+            let track = TrackInfo {
+                popularity,
+                album_name: format!("Album {}", id),
+                ..sample_track(id, "Love Song", "Artist")
+            };
+            db.insert_track_info(&track).unwrap();
+        }
+
+        let options = ListOptions {
+            sort: Some(TrackSort {
+                key: SortKey::Popularity,
+                descending: true,
+            }),
+            ..Default::default()
+        };
+        let results = db.search_tracks_with("love", &options).unwrap();
+        let ids: Vec<_> = results.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(ids, ["id:2", "id:3", "id:1"]);
+    }
+
+    #[test]
+    fn limited_search_keeps_the_best_sorted_matches() {
+        let db = test_db();
+        for (id, popularity) in [("id:1", 40), ("id:2", 90), ("id:3", 65)] {
+            let track = TrackInfo {
+                popularity,
+                album_name: format!("Album {}", id),
+                ..sample_track(id, "Love Song", "Artist")
+            };
+            db.insert_track_info(&track).unwrap();
+        }
+
+        let options = ListOptions {
+            sort: Some(TrackSort {
+                key: SortKey::Popularity,
+                descending: true,
+            }),
+            ..Default::default()
+        };
+        let results = db.search_tracks_limited("love", Some(2), &options).unwrap();
+        let ids: Vec<_> = results.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(ids, ["id:2", "id:3"]);
+
+        let all = db.search_tracks_limited("love", None, &options).unwrap();
+        assert_eq!(all.len(), 3);
+    }
+
+    #[test]
+    fn year_sort_puts_missing_dates_last() {
+        let db = test_db();
+        for (id, date) in [("id:1", ""), ("id:2", "1997-05-21"), ("id:3", "1993-02-22")] {
+            let track = TrackInfo {
+                release_date: date.to_string(),
+                album_name: format!("Album {}", id),
+                ..sample_track(id, "Song", "Radiohead")
+            };
+            db.insert_track_info(&track).unwrap();
+        }
+
+        let options = ListOptions {
+            sort: Some(TrackSort {
+                key: SortKey::Year,
+                descending: false,
+            }),
+            ..Default::default()
+        };
+        let results = db.get_recent_tracks(10, &options).unwrap();
+        let ids: Vec<_> = results.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(ids, ["id:3", "id:2", "id:1"]);
+    }
+
+    #[test]
+    fn where_filter_applies_to_search_and_listing() {
+        let db = test_db();
+        for (id, popularity, date) in [
+            ("id:1", 80, "2015-01-01"),
+            ("id:2", 90, "1999-01-01"),
+            ("id:3", 20, "2020-01-01"),
+        ] {
+            let track = TrackInfo {
+                popularity,
+                release_date: date.to_string(),
+                album_name: format!("Album {}", id),
+                ..sample_track(id, "Song", "Artist")
+            };
+            db.insert_track_info(&track).unwrap();
+        }
+
+        let filter = Filter::parse("popularity > 70 AND year >= 2010").unwrap();
+        let options = ListOptions {
+            filter: Some(&filter),
+            ..Default::default()
+        };
+        let listed = db.list_tracks(&options).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].track_id, "id:1");
+
+        let searched = db.search_tracks_with("song", &options).unwrap();
+        assert_eq!(searched.len(), 1);
+        assert_eq!(searched[0].track_id, "id:1");
+    }
+
+    #[test]
+    fn clean_only_leaves_out_explicit_tracks() {
+        let db = test_db();
+        let explicit = TrackInfo {
+            explicit: true,
+            ..sample_track("id:1", "Song", "Artist")
+        };
+        db.insert_track_info(&explicit).unwrap();
+        db.insert_track_info(&sample_track("id:2", "Other", "Artist"))
+            .unwrap();
+
+        // A re-fetch without the Web API doesn't clear the flag.
+        db.insert_track_info(&sample_track("id:1", "Song", "Artist"))
+            .unwrap();
+        assert!(db.get_track_info("id:1").unwrap().unwrap().explicit);
+
+        let filter = Filter::parse("plays = 0").unwrap();
+        let options = ListOptions {
+            filter: Some(&filter),
+            clean_only: true,
+            ..Default::default()
+        };
+        let listed = db.list_tracks(&options).unwrap();
+        let ids: Vec<&str> = listed.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(ids, ["id:2"]);
+
+        let filter = Filter::parse("explicit = 1").unwrap();
+        let options = ListOptions {
+            filter: Some(&filter),
+            ..Default::default()
+        };
+        assert_eq!(db.list_tracks(&options).unwrap()[0].track_id, "id:1");
+    }
+
     #[test]
     fn insert_replaces_existing_track() {
         let db = test_db();
@@ -346,6 +1983,100 @@ mod tests {
         assert_eq!(retrieved.track_name, "Updated");
     }
 
+    #[test]
+    fn reinsert_without_lyrics_keeps_cached_lyrics() {
+        let db = test_db();
+        db.insert_track_info(&TrackInfo {
+            synced_lyrics: Some("[00:01.00]Test lyrics".to_string()),
+            ..sample_track("id:1", "Song", "Artist")
+        })
+        .unwrap();
+
+        for missing in [None, Some("  \n".to_string())] {
+            let refetched = TrackInfo {
+                lyrics: missing,
+                lyrics_confidence: None,
+                popularity: 90,
+                ..sample_track("id:1", "Song", "Artist")
+            };
+            db.insert_track_info(&refetched).unwrap();
+            let kept = db.get_track_info("id:1").unwrap().unwrap();
+            assert_eq!(kept.lyrics.as_deref(), Some("Test lyrics"));
+            assert_eq!(kept.lyrics_confidence, Some(1.0));
+            assert_eq!(kept.synced_lyrics.as_deref(), Some("[00:01.00]Test lyrics"));
+            assert_eq!(kept.popularity, 90, "the rest of the row is refreshed");
+        }
+
+        let replaced = TrackInfo {
+            lyrics: Some("New lyrics".to_string()),
+            ..sample_track("id:1", "Song", "Artist")
+        };
+        db.insert_track_info(&replaced).unwrap();
+        let retrieved = db.get_track_info("id:1").unwrap().unwrap();
+        assert_eq!(retrieved.lyrics.as_deref(), Some("New lyrics"));
+        assert_eq!(
+            retrieved.synced_lyrics, None,
+            "unsynced lyrics replace synced ones"
+        );
+    }
+
+    #[test]
+    fn genius_url_survives_refetches_that_miss_it() {
+        let db = test_db();
+        db.insert_track_info(&TrackInfo {
+            genius_url: Some("https://genius.com/Artist-song-lyrics".to_string()),
+            ..sample_track("id:1", "Song", "Artist")
+        })
+        .unwrap();
+
+        // Captured again under another ID, without a Genius match this time.
+        db.insert_track_info(&sample_track("id:2", "song ", "Artist"))
+            .unwrap();
+        let kept = db.get_track_info("id:2").unwrap().unwrap();
+        assert_eq!(
+            kept.genius_url.as_deref(),
+            Some("https://genius.com/Artist-song-lyrics")
+        );
+
+        db.insert_track_info(&TrackInfo {
+            genius_url: Some("https://genius.com/Artist-song-remix-lyrics".to_string()),
+            ..sample_track("id:2", "Song", "Artist")
+        })
+        .unwrap();
+        let replaced = db.get_track_info("id:2").unwrap().unwrap();
+        assert_eq!(
+            replaced.genius_url.as_deref(),
+            Some("https://genius.com/Artist-song-remix-lyrics")
+        );
+    }
+
+    #[test]
+    fn strip_lyrics_headers_cleans_legacy_rows() {
+        let db = test_db();
+        let legacy = TrackInfo {
+            lyrics: Some("🎵 Creep\n👤 Radiohead\n\nWhen you were here before".to_string()),
+            ..sample_track("id:1", "Creep", "Radiohead")
+        };
+        db.insert_track_info(&legacy).unwrap();
+        db.strip_lyrics_headers().unwrap();
+
+        let info = db.get_track_info("id:1").unwrap().unwrap();
+        assert_eq!(info.lyrics, Some("When you were here before".to_string()));
+    }
+
+    #[test]
+    fn legacy_header_strip_leaves_other_lyrics_alone() {
+        assert_eq!(strip_legacy_lyrics_header("Test lyrics"), None);
+        assert_eq!(
+            strip_legacy_lyrics_header("🎵 Intro\nno artist line\n\nverse"),
+            None
+        );
+        assert_eq!(
+            strip_legacy_lyrics_header("🎵 Song\n👤 Artist\nverse starts here"),
+            None
+        );
+    }
+
     #[test]
     fn schema_migration_is_idempotent() {
         let db = test_db();
@@ -353,4 +2084,509 @@ mod tests {
         db.init().unwrap();
         db.init().unwrap();
     }
+
+    #[test]
+    fn interrupted_migration_rolls_back_and_reruns() {
+        let db = test_db();
+        let version = db.schema_version().unwrap();
+        let add_column = || {
+            db.conn
+                .execute("ALTER TABLE tracks ADD COLUMN scratch TEXT", [])?;
+            Ok(())
+        };
+
+        let failed = db.migrate(version, version + 1, || {
+            add_column()?;
+            bail!("interrupted")
+        });
+        assert!(failed.is_err());
+        assert_eq!(db.schema_version().unwrap(), version);
+
+        // The column went with the rollback, so the step can run again.
+        db.migrate(version, version + 1, add_column).unwrap();
+        assert_eq!(db.schema_version().unwrap(), version + 1);
+    }
+
+    fn temp_db_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("playbot-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn merge_adds_new_tracks_and_honors_conflict_policy() {
+        let other_path = temp_db_path("merge");
+        let other = Database::new(&other_path).unwrap();
+        other.init().unwrap();
+        other
+            .insert_track_info(&sample_track("spotify:track:a", "Theirs", "Artist"))
+            .unwrap();
+        other.record_play("spotify:track:a").unwrap();
+        other
+            .insert_track_info(&sample_track("spotify:track:b", "New", "Artist"))
+            .unwrap();
+        other.record_play("spotify:track:b").unwrap();
+        drop(other);
+
+        let db = test_db();
+        db.insert_track_info(&sample_track("spotify:track:a", "Ours", "Artist"))
+            .unwrap();
+        db.record_play("spotify:track:a").unwrap();
+        db.record_play("spotify:track:a").unwrap();
+        db.set_favorite("spotify:track:a", true).unwrap();
+
+        let stats = db.merge_from(&other_path, OnConflict::Skip).unwrap();
+        assert_eq!(
+            stats,
+            MergeStats {
+                added: 1,
+                updated: 0,
+                skipped: 1
+            }
+        );
+        let kept = db.get_track_info("spotify:track:a").unwrap().unwrap();
+        assert_eq!(kept.track_name, "Ours");
+        assert_eq!(kept.play_count, 3, "skipped rows still sum plays");
+        assert!(kept.favorite);
+
+        let stats = db.merge_from(&other_path, OnConflict::Replace).unwrap();
+        assert_eq!(
+            stats,
+            MergeStats {
+                added: 0,
+                updated: 2,
+                skipped: 0
+            }
+        );
+        let replaced = db.get_track_info("spotify:track:a").unwrap().unwrap();
+        assert_eq!(replaced.track_name, "Theirs");
+        assert_eq!(replaced.play_count, 4, "merging again adds the plays again");
+        assert!(replaced.favorite, "a local star survives the merge");
+        let added = db.get_track_info("spotify:track:b").unwrap().unwrap();
+        assert_eq!(added.play_count, 2);
+        assert_eq!(db.count_tracks().unwrap(), 2);
+
+        std::fs::remove_file(&other_path).unwrap();
+    }
+
+    #[test]
+    fn merge_folds_near_duplicates() {
+        let other_path = temp_db_path("merge-fold");
+        let other = Database::new(&other_path).unwrap();
+        other.init().unwrap();
+        let mut theirs = sample_track("title-artist", "song ", "artist");
+        theirs.lyrics = None;
+        other.insert_track_info(&theirs).unwrap();
+        other.record_play("title-artist").unwrap();
+        drop(other);
+
+        let db = test_db();
+        db.insert_track_info(&sample_track("spotify:track:x", "Song", "Artist"))
+            .unwrap();
+        db.record_play("spotify:track:x").unwrap();
+
+        let stats = db.merge_from(&other_path, OnConflict::Replace).unwrap();
+        assert_eq!(stats.updated, 1);
+        assert_eq!(db.count_tracks().unwrap(), 1);
+        let merged = db.get_track_info("title-artist").unwrap().unwrap();
+        assert_eq!(merged.play_count, 2);
+        assert_eq!(
+            merged.lyrics.as_deref(),
+            Some("Test lyrics"),
+            "local lyrics fill a row that has none"
+        );
+
+        std::fs::remove_file(&other_path).unwrap();
+    }
+
+    #[test]
+    fn merge_rejects_missing_database() {
+        let db = test_db();
+        assert!(db
+            .merge_from("/nonexistent/playbot.db", OnConflict::Skip)
+            .is_err());
+    }
+
+    #[test]
+    fn insert_replaces_case_and_whitespace_variants() {
+        let db = test_db();
+        let first = db
+            .insert_track_info(&sample_track("title-artist", "Song ", "artist"))
+            .unwrap();
+        let second = db
+            .insert_track_info(&sample_track("spotify:track:x", "song", "Artist"))
+            .unwrap();
+        assert_eq!((first, second), (Written::Inserted, Written::Merged));
+
+        assert_eq!(db.count_tracks().unwrap(), 1);
+        assert!(db.get_track_info("title-artist").unwrap().is_none());
+        let kept = db.get_track_info("spotify:track:x").unwrap().unwrap();
+        assert_eq!(kept.track_name, "song");
+    }
+
+    #[test]
+    fn import_counts_what_each_write_did() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("id:1", "Song", "Artist"))
+            .unwrap();
+        db.insert_track_info(&sample_track("title-artist", "Other ", "artist"))
+            .unwrap();
+
+        let tracks = [
+            TrackInfo {
+                play_count: 4,
+                ..sample_track("id:1", "Song", "Artist")
+            },
+            sample_track("spotify:track:x", "other", "Artist"),
+            sample_track("id:3", "New", "Artist"),
+        ];
+        let stats = db.import_tracks(&tracks).unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                inserted: 1,
+                replaced: 1,
+                merged: 1
+            }
+        );
+        assert_eq!(db.count_tracks().unwrap(), 3);
+
+        // Importing the same export again replaces every row without
+        // inflating the play counts.
+        let again = db.import_tracks(&tracks).unwrap();
+        assert_eq!(again.replaced, 3);
+        assert_eq!(db.get_track_info("id:1").unwrap().unwrap().play_count, 4);
+    }
+
+    #[test]
+    fn insert_keeps_same_title_on_different_albums() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("spotify:track:a", "Intro", "Artist"))
+            .unwrap();
+        let mut other = sample_track("spotify:track:b", "Intro", "Artist");
+        other.album_name = "Second Album".to_string();
+        db.insert_track_info(&other).unwrap();
+
+        assert_eq!(db.count_tracks().unwrap(), 2);
+    }
+
+    #[test]
+    fn purge_lyrics_respects_filter_and_keeps_metadata() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("spotify:track:a", "Keep", "Artist"))
+            .unwrap();
+        db.insert_track_info(&sample_track("spotify:track:b", "Purge", "Other"))
+            .unwrap();
+
+        let filter = Filter::parse("artist = Other").unwrap();
+        let options = ListOptions {
+            filter: Some(&filter),
+            ..Default::default()
+        };
+        let (purged, bytes) = db.purge_lyrics(&options).unwrap();
+        assert_eq!((purged, bytes), (1, "Test lyrics".len() as u64));
+
+        let purged_track = db.get_track_info("spotify:track:b").unwrap().unwrap();
+        assert_eq!(purged_track.lyrics, None);
+        assert_eq!(purged_track.album_name, "Test Album");
+        let kept = db.get_track_info("spotify:track:a").unwrap().unwrap();
+        assert_eq!(kept.lyrics.as_deref(), Some("Test lyrics"));
+
+        assert_eq!(db.purge_lyrics(&options).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn play_counts_survive_reinserts_and_dedupe() {
+        let db = test_db();
+        let track = sample_track("title-artist", "Song", "Artist");
+        db.insert_track_info(&track).unwrap();
+        assert_eq!(db.record_play("title-artist").unwrap(), 1);
+        assert_eq!(db.record_play("missing").unwrap(), 0);
+
+        // Re-fetching the same track keeps its count...
+        db.insert_track_info(&track).unwrap();
+        assert_eq!(db.record_play("title-artist").unwrap(), 2);
+
+        // ...and so does replacing it with its Spotify URI.
+        db.insert_track_info(&sample_track("spotify:track:x", "song ", "Artist"))
+            .unwrap();
+        assert_eq!(db.record_play("spotify:track:x").unwrap(), 3);
+
+        db.insert_track_info(&sample_track("spotify:track:y", "Other", "Artist"))
+            .unwrap();
+        let top = db.get_most_played(10, &ListOptions::default()).unwrap();
+        let ids: Vec<&str> = top.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(ids, ["spotify:track:x"], "unplayed tracks are left out");
+    }
+
+    #[test]
+    fn favorites_are_listed_and_survive_refetches() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("title-artist", "Song", "Artist"))
+            .unwrap();
+        db.insert_track_info(&sample_track("spotify:track:y", "Other", "Artist"))
+            .unwrap();
+        assert!(db.set_favorite("title-artist", true).unwrap());
+        assert!(!db.set_favorite("missing", true).unwrap());
+
+        // Re-fetched under its Spotify URI, the track stays starred.
+        db.insert_track_info(&sample_track("spotify:track:x", "Song", "Artist"))
+            .unwrap();
+        let favorites = db.get_favorites(&ListOptions::default()).unwrap();
+        let ids: Vec<&str> = favorites.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(ids, ["spotify:track:x"]);
+
+        db.set_favorite("spotify:track:x", false).unwrap();
+        assert!(db
+            .get_favorites(&ListOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn search_lyrics_follows_every_write() {
+        let db = test_db();
+        let ids = |tracks: Vec<TrackInfo>| -> Vec<String> {
+            tracks.into_iter().map(|t| t.track_id).collect()
+        };
+        let mut track = sample_track("spotify:track:a", "Song", "Artist");
+        track.lyrics = Some("Hello darkness, my old friend".to_string());
+        db.insert_track_info(&track).unwrap();
+        db.insert_track_info(&sample_track("spotify:track:b", "Other", "Artist"))
+            .unwrap();
+
+        assert_eq!(
+            ids(db.search_lyrics("darkness my OLD").unwrap()),
+            ["spotify:track:a"]
+        );
+        assert!(db.search_lyrics("old darkness").unwrap().is_empty());
+        assert!(db.search_lyrics("\"unbalanced").unwrap().is_empty());
+        assert!(db.search_lyrics("  ").unwrap().is_empty());
+
+        // Re-caching replaces the row, so the old words must stop matching.
+        track.lyrics = Some("I've come to talk with you again".to_string());
+        db.insert_track_info(&track).unwrap();
+        assert!(db.search_lyrics("darkness").unwrap().is_empty());
+        assert_eq!(
+            ids(db.search_lyrics("talk with you").unwrap()),
+            ["spotify:track:a"]
+        );
+
+        db.purge_lyrics(&ListOptions::default()).unwrap();
+        assert!(db.search_lyrics("talk with you").unwrap().is_empty());
+        assert_eq!(ids(db.search_lyrics("song").unwrap()), ["spotify:track:a"]);
+
+        db.delete_track("spotify:track:a").unwrap();
+        assert!(db.search_lyrics("song").unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_lyrics_falls_back_without_the_index() {
+        let db = test_db();
+        db.conn
+            .execute_batch(
+                "DROP TRIGGER tracks_fts_insert;
+                 DROP TRIGGER tracks_fts_delete;
+                 DROP TRIGGER tracks_fts_update;
+                 DROP TABLE tracks_fts;",
+            )
+            .unwrap();
+        let mut track = sample_track("spotify:track:a", "Song", "Artist");
+        track.lyrics = Some("Hello darkness, my old friend".to_string());
+        db.insert_track_info(&track).unwrap();
+
+        assert_eq!(db.search_lyrics("MY OLD friend").unwrap().len(), 1);
+        assert!(db.search_lyrics("darkness my").unwrap().is_empty());
+    }
+
+    #[test]
+    fn delete_track_reports_whether_a_row_went() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("spotify:track:a", "Song", "Artist"))
+            .unwrap();
+        assert!(db.delete_track("spotify:track:a").unwrap());
+        assert!(!db.delete_track("spotify:track:a").unwrap());
+        assert_eq!(db.count_tracks().unwrap(), 0);
+    }
+
+    #[test]
+    fn expired_rows_are_a_cache_miss() {
+        let db = test_db();
+        db.insert_track_info(&sample_track("spotify:track:a", "Song", "Artist"))
+            .unwrap();
+        assert!(db
+            .get_fresh_track_info("spotify:track:a", 1)
+            .unwrap()
+            .is_some());
+
+        db.conn
+            .execute(
+                "UPDATE tracks SET cached_at = datetime('now', '-2 hours')",
+                [],
+            )
+            .unwrap();
+        assert!(db
+            .get_fresh_track_info("spotify:track:a", 1)
+            .unwrap()
+            .is_none());
+        assert!(db
+            .get_fresh_track_info("spotify:track:a", 3)
+            .unwrap()
+            .is_some());
+        assert!(db
+            .get_fresh_track_info("spotify:track:a", 0)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn reindex_collapses_legacy_duplicates_and_strips_headers() {
+        let db = test_db();
+        // Rows written before inserts deduplicated, bypassing insert_track_info.
+        for (id, name, cached_at, plays) in [
+            ("old-id", "Song ", "2020-01-01 00:00:00", 2),
+            ("spotify:track:new", "song", "2024-01-01 00:00:00", 3),
+        ] {
+            db.conn
+                .execute(
+                    "INSERT INTO tracks (track_id, track_name, artist_name, album_name, release_date,
+                         duration_ms, popularity, genres, lyrics, producers, writers, cached_at,
+                         play_count)
+                     VALUES (?1, ?2, 'Artist', 'Album', '', 0, 0, '',
+                             '🎵 Song\n👤 Artist\n\nla la', '', '', ?3, ?4)",
+                    params![id, name, cached_at, plays],
+                )
+                .unwrap();
+        }
+
+        let mut steps = Vec::new();
+        let stats = db.reindex(|step| steps.push(step.to_string())).unwrap();
+        assert_eq!(
+            stats,
+            ReindexStats {
+                headers_stripped: 2,
+                duplicates_removed: 1
+            }
+        );
+        assert_eq!(steps.len(), 3);
+
+        let kept = db.get_track_info("spotify:track:new").unwrap().unwrap();
+        assert_eq!(kept.lyrics.as_deref(), Some("la la"));
+        assert_eq!(kept.play_count, 5);
+        assert_eq!(db.count_tracks().unwrap(), 1);
+    }
+
+    #[test]
+    fn reindex_fills_the_survivor_from_removed_duplicates() {
+        let db = test_db();
+        // Only the older capture has lyrics, art, and credits.
+        for (id, name, cached_at, lyrics, art, writers, explicit) in [
+            (
+                "old-id",
+                "Song ",
+                "2020-01-01 00:00:00",
+                Some("la la"),
+                Some("https://i.scdn.co/image/old"),
+                "Writer",
+                true,
+            ),
+            (
+                "spotify:track:new",
+                "song",
+                "2024-01-01 00:00:00",
+                None,
+                None,
+                "",
+                false,
+            ),
+        ] {
+            db.conn
+                .execute(
+                    "INSERT INTO tracks (track_id, track_name, artist_name, album_name, release_date,
+                         duration_ms, popularity, genres, lyrics, producers, writers, cached_at,
+                         lyrics_confidence, synced_lyrics, album_art_url, explicit)
+                     VALUES (?1, ?2, 'Artist', 'Album', '', 0, 0, '', ?3, '', ?4, ?5,
+                             ?6, ?7, ?8, ?9)",
+                    params![
+                        id,
+                        name,
+                        lyrics,
+                        writers,
+                        cached_at,
+                        lyrics.map(|_| 0.9),
+                        lyrics.map(|_| "[00:01.00]la la"),
+                        art,
+                        explicit
+                    ],
+                )
+                .unwrap();
+        }
+
+        db.reindex(|_| {}).unwrap();
+        assert_eq!(db.count_tracks().unwrap(), 1);
+        let kept = db.get_track_info("spotify:track:new").unwrap().unwrap();
+        assert_eq!(kept.lyrics.as_deref(), Some("la la"));
+        assert_eq!(kept.lyrics_confidence, Some(0.9));
+        assert_eq!(kept.synced_lyrics.as_deref(), Some("[00:01.00]la la"));
+        assert_eq!(
+            kept.album_art_url.as_deref(),
+            Some("https://i.scdn.co/image/old")
+        );
+        assert_eq!(kept.writers, "Writer");
+        assert!(kept.explicit);
+    }
+
+    #[test]
+    fn locked_database_write_fails_with_clear_message() {
+        let path = temp_db_path("locked");
+        let db = Database::new(&path).unwrap();
+        db.init().unwrap();
+        db.conn.busy_timeout(Duration::from_millis(10)).unwrap();
+
+        let other = Connection::open(&path).unwrap();
+        other.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        let err = db
+            .insert_track_info(&sample_track("spotify:track:a", "Song", "Artist"))
+            .unwrap_err();
+        assert!(is_busy(&err));
+        assert!(format!("{:#}", err).contains("locked by another playbot process"));
+
+        other.execute_batch("COMMIT").unwrap();
+        db.insert_track_info(&sample_track("spotify:track:a", "Song", "Artist"))
+            .unwrap();
+        assert_eq!(db.count_tracks().unwrap(), 1);
+
+        drop((db, other));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn second_handle_reads_while_first_writes() {
+        let path = temp_db_path("wal");
+        let writer = Database::new(&path).unwrap();
+        writer.init().unwrap();
+        let reader = Database::new(&path).unwrap();
+        reader.init().unwrap();
+        let journal_mode: String = reader
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+
+        // A read transaction stays open across the other handle's write...
+        let tx = reader.conn.unchecked_transaction().unwrap();
+        assert_eq!(reader.count_tracks().unwrap(), 0);
+        writer
+            .insert_track_info(&sample_track("spotify:track:a", "Song", "Artist"))
+            .unwrap();
+        assert_eq!(reader.count_tracks().unwrap(), 0, "reads see a snapshot");
+        tx.commit().unwrap();
+
+        // ...and sees it once the transaction is over.
+        assert_eq!(reader.count_tracks().unwrap(), 1);
+        assert!(reader.get_track_info("spotify:track:a").unwrap().is_some());
+
+        drop((writer, reader));
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,412 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::db::TrackInfo;
+use crate::output::{out, outln};
+
+/// JSON shape of a track. Mirrors [`TrackInfo`], except that the
+/// comma-joined list columns are split back into arrays.
+#[derive(Debug, Serialize)]
+pub struct TrackRecord<'a> {
+    pub track_id: &'a str,
+    pub track_name: &'a str,
+    pub artist_name: &'a str,
+    pub album_name: &'a str,
+    pub release_date: &'a str,
+    pub duration_ms: i64,
+    pub popularity: i32,
+    pub genres: Vec<&'a str>,
+    pub producers: Vec<&'a str>,
+    pub writers: Vec<&'a str>,
+    pub lyrics: Option<&'a str>,
+    pub lyrics_confidence: Option<f64>,
+    pub cached_at: Option<&'a str>,
+    pub play_count: i64,
+    pub favorite: bool,
+    pub album_art_url: Option<&'a str>,
+    pub synced_lyrics: Option<&'a str>,
+    pub track_number: Option<i64>,
+    pub disc_number: Option<i64>,
+    pub explicit: bool,
+    pub genius_url: Option<&'a str>,
+}
+
+impl<'a> From<&'a TrackInfo> for TrackRecord<'a> {
+    fn from(track: &'a TrackInfo) -> Self {
+        Self {
+            track_id: &track.track_id,
+            track_name: &track.track_name,
+            artist_name: &track.artist_name,
+            album_name: &track.album_name,
+            release_date: &track.release_date,
+            duration_ms: track.duration_ms,
+            popularity: track.popularity,
+            genres: split_list(&track.genres),
+            producers: split_list(&track.producers),
+            writers: split_list(&track.writers),
+            lyrics: track.lyrics.as_deref(),
+            lyrics_confidence: track.lyrics_confidence,
+            cached_at: track.cached_at.as_deref(),
+            play_count: track.play_count,
+            favorite: track.favorite,
+            album_art_url: track.album_art_url.as_deref(),
+            synced_lyrics: track.synced_lyrics.as_deref(),
+            track_number: track.track_number,
+            disc_number: track.disc_number,
+            explicit: track.explicit,
+            genius_url: track.genius_url.as_deref(),
+        }
+    }
+}
+
+/// A track read back from a JSON export. Only the ID, title, and artist are
+/// required; everything else falls back to the value an empty fetch stores.
+#[derive(Debug, Deserialize)]
+struct ImportRecord {
+    track_id: String,
+    track_name: String,
+    artist_name: String,
+    #[serde(default)]
+    album_name: String,
+    #[serde(default)]
+    release_date: String,
+    #[serde(default)]
+    duration_ms: i64,
+    #[serde(default)]
+    popularity: i32,
+    #[serde(default)]
+    genres: Vec<String>,
+    #[serde(default)]
+    producers: Vec<String>,
+    #[serde(default)]
+    writers: Vec<String>,
+    #[serde(default)]
+    lyrics: Option<String>,
+    #[serde(default)]
+    lyrics_confidence: Option<f64>,
+    #[serde(default)]
+    cached_at: Option<String>,
+    #[serde(default)]
+    play_count: i64,
+    #[serde(default)]
+    favorite: bool,
+    #[serde(default)]
+    album_art_url: Option<String>,
+    #[serde(default)]
+    synced_lyrics: Option<String>,
+    #[serde(default)]
+    track_number: Option<i64>,
+    #[serde(default)]
+    disc_number: Option<i64>,
+    #[serde(default)]
+    explicit: bool,
+    #[serde(default)]
+    genius_url: Option<String>,
+}
+
+impl From<ImportRecord> for TrackInfo {
+    fn from(record: ImportRecord) -> Self {
+        Self {
+            track_id: record.track_id,
+            track_name: record.track_name,
+            artist_name: record.artist_name,
+            album_name: record.album_name,
+            release_date: record.release_date,
+            duration_ms: record.duration_ms,
+            popularity: record.popularity,
+            genres: record.genres.join(", "),
+            lyrics: record.lyrics,
+            producers: record.producers.join(", "),
+            writers: record.writers.join(", "),
+            lyrics_confidence: record.lyrics_confidence,
+            cached_at: record.cached_at,
+            play_count: record.play_count,
+            favorite: record.favorite,
+            album_art_url: record.album_art_url,
+            synced_lyrics: record.synced_lyrics,
+            track_number: record.track_number,
+            disc_number: record.disc_number,
+            explicit: record.explicit,
+            genius_url: record.genius_url,
+        }
+    }
+}
+
+/// Read tracks from a JSON array in the shape [`write_json`] produces,
+/// joining the list fields back into their comma-delimited column form.
+///
+/// A file that isn't an array, or an entry that isn't a track, fails with
+/// the position of the offending entry.
+pub fn read_json(reader: impl Read) -> Result<Vec<TrackInfo>> {
+    let value: serde_json::Value = serde_json::from_reader(reader).context("Invalid JSON")?;
+    let serde_json::Value::Array(entries) = value else {
+        bail!("Expected a JSON array of tracks, as written by --export-json");
+    };
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let record: ImportRecord = serde_json::from_value(entry)
+                .with_context(|| format!("Entry {} is not a valid track", i + 1))?;
+            if record.track_id.trim().is_empty() {
+                bail!("Entry {} has an empty track_id", i + 1);
+            }
+            Ok(record.into())
+        })
+        .collect()
+}
+
+/// Split a comma-joined column into its trimmed, non-empty entries.
+fn split_list(joined: &str) -> Vec<&str> {
+    joined
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Print one track as a pretty JSON object to the command output.
+pub fn print_track(track: &TrackInfo) -> Result<()> {
+    outln!(
+        "{}",
+        serde_json::to_string_pretty(&TrackRecord::from(track))?
+    );
+    Ok(())
+}
+
+/// Print tracks as a pretty JSON array to the command output.
+pub fn print_tracks(tracks: &[TrackInfo]) -> Result<()> {
+    let mut json = Vec::new();
+    write_json(tracks, true, &mut json)?;
+    out!("{}", String::from_utf8_lossy(&json));
+    Ok(())
+}
+
+/// Fields left out of an export without lyrics: the lyrics are copyrighted,
+/// and the rest only describe them.
+const LYRICS_FIELDS: [&str; 3] = ["lyrics", "synced_lyrics", "lyrics_confidence"];
+
+/// Write tracks as a pretty JSON array followed by a newline. Without
+/// `with_lyrics`, the [`LYRICS_FIELDS`] are left out, for sharing.
+pub fn write_json(tracks: &[TrackInfo], with_lyrics: bool, mut out: impl Write) -> Result<()> {
+    let mut records = Vec::with_capacity(tracks.len());
+    for track in tracks {
+        let mut record = serde_json::to_value(TrackRecord::from(track))?;
+        if let Some(fields) = record.as_object_mut().filter(|_| !with_lyrics) {
+            for field in LYRICS_FIELDS {
+                fields.remove(field);
+            }
+        }
+        records.push(record);
+    }
+    serde_json::to_writer_pretty(&mut out, &records)?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// CSV header, in the order [`csv_row`] writes the fields. Lyrics come last
+/// since they are by far the longest.
+const CSV_HEADER: [&str; 16] = [
+    "track_id",
+    "track_name",
+    "artist_name",
+    "album_name",
+    "release_date",
+    "duration_ms",
+    "popularity",
+    "genres",
+    "producers",
+    "writers",
+    "play_count",
+    "favorite",
+    "cached_at",
+    "lyrics_confidence",
+    "album_art_url",
+    "lyrics",
+];
+
+/// Write tracks as RFC 4180 CSV with a header row. List columns keep their
+/// comma-joined form, quoted like any other field containing a comma.
+/// Without `with_lyrics`, the [`LYRICS_FIELDS`] columns are left out.
+pub fn write_csv(tracks: &[TrackInfo], with_lyrics: bool, mut out: impl Write) -> Result<()> {
+    let keep = |column: &usize| with_lyrics || !LYRICS_FIELDS.contains(&CSV_HEADER[*column]);
+    let columns: Vec<usize> = (0..CSV_HEADER.len()).filter(keep).collect();
+    write_csv_line(&mut out, columns.iter().map(|&i| CSV_HEADER[i].to_string()))?;
+    for track in tracks {
+        let mut row = csv_row(track);
+        write_csv_line(
+            &mut out,
+            columns.iter().map(|&i| std::mem::take(&mut row[i])),
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn csv_row(track: &TrackInfo) -> [String; 16] {
+    [
+        track.track_id.clone(),
+        track.track_name.clone(),
+        track.artist_name.clone(),
+        track.album_name.clone(),
+        track.release_date.clone(),
+        track.duration_ms.to_string(),
+        track.popularity.to_string(),
+        track.genres.clone(),
+        track.producers.clone(),
+        track.writers.clone(),
+        track.play_count.to_string(),
+        track.favorite.to_string(),
+        track.cached_at.clone().unwrap_or_default(),
+        track
+            .lyrics_confidence
+            .map(|c| c.to_string())
+            .unwrap_or_default(),
+        track.album_art_url.clone().unwrap_or_default(),
+        track.lyrics.clone().unwrap_or_default(),
+    ]
+}
+
+fn write_csv_line(out: &mut impl Write, fields: impl Iterator<Item = String>) -> Result<()> {
+    let line: Vec<String> = fields.map(|field| csv_field(&field)).collect();
+    write!(out, "{}\r\n", line.join(","))?;
+    Ok(())
+}
+
+/// Quote a field if it contains a delimiter, quote, or line break, doubling
+/// any quotes inside it.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_columns_become_arrays() {
+        let track = TrackInfo {
+            album_name: "Album".to_string(),
+            release_date: "2024-01-01".to_string(),
+            duration_ms: 1000,
+            popularity: 50,
+            genres: "rock, indie".to_string(),
+            writers: "A, B ,".to_string(),
+            lyrics_confidence: Some(0.9),
+            ..TrackInfo::sample("spotify:track:abc", "Song", "Artist")
+        };
+
+        let json = serde_json::to_value(TrackRecord::from(&track)).unwrap();
+        assert_eq!(json["genres"], serde_json::json!(["rock", "indie"]));
+        assert_eq!(json["producers"], serde_json::json!([]));
+        assert_eq!(json["writers"], serde_json::json!(["A", "B"]));
+        assert_eq!(json["lyrics"], serde_json::Value::Null);
+        assert_eq!(json["track_id"], "spotify:track:abc");
+    }
+
+    #[test]
+    fn json_export_reads_back() {
+        let track = TrackInfo {
+            album_name: "Album".to_string(),
+            release_date: "2024-01-01".to_string(),
+            duration_ms: 1000,
+            popularity: 50,
+            genres: "rock, indie".to_string(),
+            lyrics: Some("la la".to_string()),
+            writers: "A, B".to_string(),
+            lyrics_confidence: Some(0.9),
+            cached_at: Some("2024-01-02 13:45:00".to_string()),
+            play_count: 4,
+            favorite: true,
+            ..TrackInfo::sample("spotify:track:abc", "Song", "Artist")
+        };
+
+        let mut out = Vec::new();
+        write_json(std::slice::from_ref(&track), true, &mut out).unwrap();
+        let read = read_json(out.as_slice()).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].genres, "rock, indie");
+        assert_eq!(read[0].producers, "");
+        assert_eq!(read[0].writers, "A, B");
+        assert_eq!(read[0].lyrics.as_deref(), Some("la la"));
+        assert_eq!(read[0].play_count, 4);
+        assert!(read[0].favorite);
+
+        let minimal = r#"[{"track_id": "x", "track_name": "T", "artist_name": "A"}]"#;
+        assert_eq!(read_json(minimal.as_bytes()).unwrap()[0].album_name, "");
+    }
+
+    #[test]
+    fn json_import_rejects_wrong_shapes() {
+        let not_array = read_json(r#"{"track_id": "x"}"#.as_bytes()).unwrap_err();
+        assert!(not_array.to_string().contains("array"));
+
+        let bad_entry = r#"[{"track_id": "x", "track_name": "T", "artist_name": "A"},
+                            {"track_id": "y", "track_name": 5}]"#;
+        let err = read_json(bad_entry.as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("Entry 2"));
+
+        assert!(read_json("not json".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn csv_quotes_lists_quotes_and_multiline_lyrics() {
+        let track = TrackInfo {
+            album_name: "Album".to_string(),
+            duration_ms: 1000,
+            popularity: 50,
+            genres: "rock, indie".to_string(),
+            lyrics: Some("line one\nline two".to_string()),
+            cached_at: Some("2024-01-02 13:45:00".to_string()),
+            play_count: 3,
+            favorite: true,
+            ..TrackInfo::sample("spotify:track:abc", "Say \"Hi\"", "Artist")
+        };
+
+        let mut out = Vec::new();
+        write_csv(std::slice::from_ref(&track), true, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let (header, row) = csv.split_once("\r\n").unwrap();
+        assert!(header.starts_with("track_id,track_name,"));
+        assert!(header.ends_with(",lyrics"));
+        assert_eq!(
+            row,
+            "spotify:track:abc,\"Say \"\"Hi\"\"\",Artist,Album,,1000,50,\"rock, indie\",,,3,true,\
+             2024-01-02 13:45:00,,,\"line one\nline two\"\r\n"
+        );
+
+        let mut out = Vec::new();
+        write_csv(&[track], false, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let (header, row) = csv.split_once("\r\n").unwrap();
+        assert!(header.ends_with(",cached_at,album_art_url"), "{}", header);
+        assert!(!row.contains("line one"), "{}", row);
+    }
+
+    #[test]
+    fn json_export_without_lyrics_leaves_them_out() {
+        let track = TrackInfo {
+            lyrics: Some("la la".to_string()),
+            synced_lyrics: Some("[00:01.00]la la".to_string()),
+            lyrics_confidence: Some(0.9),
+            play_count: 4,
+            ..TrackInfo::sample("spotify:track:abc", "Song", "Artist")
+        };
+
+        let mut out = Vec::new();
+        write_json(&[track], false, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let record = json[0].as_object().unwrap();
+        for field in LYRICS_FIELDS {
+            assert!(!record.contains_key(field), "{}", field);
+        }
+        assert_eq!(record["play_count"], 4);
+        assert_eq!(read_json(out.as_slice()).unwrap()[0].lyrics, None);
+    }
+}
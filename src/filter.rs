@@ -0,0 +1,430 @@
+use anyhow::{anyhow, bail, Result};
+use rusqlite::types::Value;
+
+/// A parsed `--where` expression, e.g. `popularity > 70 AND year >= 2010`.
+///
+/// The grammar is deliberately small:
+///
+/// ```text
+/// expr       := and_expr ("OR" and_expr)*
+/// and_expr   := unary ("AND" unary)*
+/// unary      := "NOT" unary | "(" expr ")" | comparison
+/// comparison := column op value
+/// op         := "=" | "!=" | "<" | "<=" | ">" | ">=" | "~"
+/// ```
+///
+/// `~` is a case-insensitive "contains" match. Values are numbers or strings
+/// (quoted with `'` or `"`, or a single bare word). Values are always bound as
+/// SQL parameters, never interpolated into the query text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Column, Op, Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Title,
+    Artist,
+    Album,
+    Genres,
+    ReleaseDate,
+    Popularity,
+    Year,
+    Duration,
+    Plays,
+    Explicit,
+}
+
+impl Column {
+    const NAMES: &'static str =
+        "title, artist, album, genres, release_date, popularity, year, duration, plays, explicit";
+
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "title" | "name" | "track" => Self::Title,
+            "artist" => Self::Artist,
+            "album" => Self::Album,
+            "genre" | "genres" => Self::Genres,
+            "release_date" | "released" => Self::ReleaseDate,
+            "popularity" => Self::Popularity,
+            "year" => Self::Year,
+            "duration" => Self::Duration,
+            "plays" | "play_count" => Self::Plays,
+            "explicit" => Self::Explicit,
+            _ => return None,
+        })
+    }
+
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Title => "track_name",
+            Self::Artist => "artist_name",
+            Self::Album => "album_name",
+            Self::Genres => "genres",
+            Self::ReleaseDate => "release_date",
+            Self::Popularity => "popularity",
+            Self::Year => "CAST(NULLIF(substr(release_date, 1, 4), '') AS INTEGER)",
+            // Exposed in seconds, which is what people think in.
+            Self::Duration => "(duration_ms / 1000)",
+            Self::Plays => "play_count",
+            Self::Explicit => "explicit",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Self::Popularity | Self::Year | Self::Duration | Self::Plays | Self::Explicit
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn sql(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Contains => "LIKE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Number(f64),
+    Str(String),
+    Op(Op),
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '\'' | '"' => {
+                let quote = c;
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == quote => break,
+                        Some(c) => value.push(c),
+                        None => bail!("Unterminated string in --where expression"),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                chars.next();
+                let followed_by_eq = chars.peek() == Some(&'=');
+                let op = match (c, followed_by_eq) {
+                    ('=', _) => Op::Eq,
+                    ('!', true) => Op::Ne,
+                    ('<', true) => Op::Le,
+                    ('<', false) => Op::Lt,
+                    ('>', true) => Op::Ge,
+                    ('>', false) => Op::Gt,
+                    ('~', _) => Op::Contains,
+                    _ => bail!("Unexpected '!' in --where expression (did you mean '!=')"),
+                };
+                if followed_by_eq && c != '~' {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            c if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || c == '.' {
+                        number.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let value = number
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid number '{}' in --where expression", number))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '-' {
+                        word.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+            other => bail!("Unexpected character '{}' in --where expression", other),
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut left = self.and_expr()?;
+        while self.eat_keyword("or") {
+            let right = self.and_expr()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr> {
+        let mut left = self.unary()?;
+        while self.eat_keyword("and") {
+            let right = self.unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let inner = self.expr()?;
+            if self.next() != Some(Token::RParen) {
+                bail!("Missing ')' in --where expression");
+            }
+            return Ok(inner);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr> {
+        let column = match self.next() {
+            Some(Token::Word(name)) => Column::parse(&name).ok_or_else(|| {
+                anyhow!(
+                    "Unknown column '{}' in --where expression (valid: {})",
+                    name,
+                    Column::NAMES
+                )
+            })?,
+            other => bail!(
+                "Expected a column name in --where expression, found {:?}",
+                other
+            ),
+        };
+
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            other => bail!(
+                "Expected an operator after '{}' in --where expression, found {:?}",
+                column.sql(),
+                other
+            ),
+        };
+
+        let value = match (self.next(), column.is_numeric()) {
+            (Some(Token::Number(n)), true) if op != Op::Contains => {
+                if n.fract() == 0.0 {
+                    Value::Integer(n as i64)
+                } else {
+                    Value::Real(n)
+                }
+            }
+            (Some(Token::Str(s) | Token::Word(s)), false) => Value::Text(s),
+            (Some(Token::Number(n)), false) => Value::Text(n.to_string()),
+            (Some(_), true) => bail!(
+                "Column '{:?}' needs a numeric comparison in --where expression",
+                column
+            ),
+            (other, _) => bail!("Expected a value in --where expression, found {:?}", other),
+        };
+
+        Ok(Expr::Compare(column, op, value))
+    }
+}
+
+impl Filter {
+    /// Parse a `--where` expression.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            bail!("Empty --where expression");
+        }
+        let expr = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {:?} in --where expression", token);
+        }
+        Ok(Self { expr })
+    }
+
+    /// Render the filter as an SQL boolean expression whose placeholders start
+    /// at `?{first_param}`, returning the values to bind in order.
+    pub fn to_sql(&self, first_param: usize) -> (String, Vec<Value>) {
+        let mut params = Vec::new();
+        let sql = render(&self.expr, first_param, &mut params);
+        (sql, params)
+    }
+}
+
+fn render(expr: &Expr, first_param: usize, params: &mut Vec<Value>) -> String {
+    match expr {
+        Expr::And(l, r) => format!(
+            "({} AND {})",
+            render(l, first_param, params),
+            render(r, first_param, params)
+        ),
+        Expr::Or(l, r) => format!(
+            "({} OR {})",
+            render(l, first_param, params),
+            render(r, first_param, params)
+        ),
+        Expr::Not(inner) => format!("(NOT {})", render(inner, first_param, params)),
+        Expr::Compare(column, op, value) => {
+            // `~` matches the value literally, so its own `%` and `_` are
+            // escaped rather than acting as wildcards.
+            let value = match (op, value) {
+                (Op::Contains, Value::Text(s)) => Value::Text(format!(
+                    "%{}%",
+                    s.replace('\\', "\\\\")
+                        .replace('%', "\\%")
+                        .replace('_', "\\_")
+                )),
+                _ => value.clone(),
+            };
+            params.push(value);
+            let placeholder = first_param + params.len() - 1;
+            let suffix = if *op == Op::Contains {
+                " ESCAPE '\\'"
+            } else if column.is_numeric() {
+                ""
+            } else {
+                " COLLATE NOCASE"
+            };
+            format!("{} {} ?{}{}", column.sql(), op.sql(), placeholder, suffix)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_of_numeric_comparisons() {
+        let filter = Filter::parse("popularity > 70 AND year >= 2010").unwrap();
+        let (sql, params) = filter.to_sql(1);
+        assert_eq!(
+            sql,
+            "(popularity > ?1 AND CAST(NULLIF(substr(release_date, 1, 4), '') AS INTEGER) >= ?2)"
+        );
+        assert_eq!(params, vec![Value::Integer(70), Value::Integer(2010)]);
+    }
+
+    #[test]
+    fn placeholders_start_at_requested_index() {
+        let filter = Filter::parse("artist = 'Radiohead'").unwrap();
+        let (sql, params) = filter.to_sql(3);
+        assert_eq!(sql, "artist_name = ?3 COLLATE NOCASE");
+        assert_eq!(params, vec![Value::Text("Radiohead".to_string())]);
+    }
+
+    #[test]
+    fn or_binds_looser_than_and() {
+        let filter = Filter::parse("genre ~ rock or popularity < 10 and not year = 1999").unwrap();
+        let (sql, _) = filter.to_sql(1);
+        assert!(sql.starts_with("(genres LIKE ?1 ESCAPE '\\' OR (popularity < ?2 AND (NOT"));
+    }
+
+    #[test]
+    fn contains_matches_wildcards_literally() {
+        let filter = Filter::parse("title ~ '100%_a\\b'").unwrap();
+        let (sql, params) = filter.to_sql(1);
+        assert_eq!(sql, "track_name LIKE ?1 ESCAPE '\\'");
+        assert_eq!(params, vec![Value::Text("%100\\%\\_a\\\\b%".to_string())]);
+    }
+
+    #[test]
+    fn values_are_never_interpolated() {
+        let filter = Filter::parse("title = \"x' OR 1=1 --\"").unwrap();
+        let (sql, params) = filter.to_sql(1);
+        assert_eq!(sql, "track_name = ?1 COLLATE NOCASE");
+        assert_eq!(params, vec![Value::Text("x' OR 1=1 --".to_string())]);
+    }
+
+    #[test]
+    fn rejects_unknown_columns_and_bad_values() {
+        assert!(Filter::parse("lyrics ~ love").is_err());
+        assert!(Filter::parse("popularity > high").is_err());
+        assert!(Filter::parse("year >= 2010 AND").is_err());
+        assert!(Filter::parse("(year >= 2010").is_err());
+        assert!(Filter::parse("").is_err());
+    }
+}
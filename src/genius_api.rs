@@ -0,0 +1,258 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::sync::Once;
+
+use crate::config::{Config, GeniusApiConfig};
+use crate::db::TrackInfo;
+use crate::lyrics;
+use crate::spotify::ARTIST_SEPARATOR;
+
+const API_URL: &str = "https://api.genius.com";
+
+/// Guards the one warning about a `[genius]` section without a token.
+static NO_TOKEN_WARNING: Once = Once::new();
+
+/// Client for the official Genius API, used for the song credits (producers
+/// and writers) that the lyrics lookup can't see.
+///
+/// Authenticates with a client access token from an API client registered at
+/// <https://genius.com/api-clients>; no user login is needed.
+pub struct GeniusApiClient {
+    http: reqwest::Client,
+    access_token: String,
+}
+
+impl GeniusApiClient {
+    /// The client for the configured token, or `None` when there is no
+    /// `[genius]` section or its token is missing or blank. Every
+    /// token-based lookup goes through here, so a section without a usable
+    /// token is warned about once per run and the lookups are skipped;
+    /// lyrics searches don't need the token and carry on.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let section = config.genius.as_ref()?;
+        let Some(access_token) = usable_token(section) else {
+            NO_TOKEN_WARNING.call_once(|| {
+                eprintln!(
+                    "⚠️  [genius] has no access_token; song credits from the Genius API are disabled"
+                )
+            });
+            return None;
+        };
+        Some(Self {
+            http: reqwest::Client::new(),
+            access_token: access_token.to_string(),
+        })
+    }
+
+    /// Fill in `track`'s producers, writers, and Genius page (and its release
+    /// date, if it has none) from the Genius song that best matches its title and
+    /// primary artist.
+    ///
+    /// The track is left alone if the best hit scores below `min_confidence`,
+    /// as lyric matches are scored.
+    pub async fn enrich(&self, track: &mut TrackInfo, min_confidence: f64) -> Result<()> {
+        let primary = track
+            .artist_name
+            .split(ARTIST_SEPARATOR)
+            .next()
+            .unwrap_or_default();
+        let query = format!("{} {}", track.track_name, primary);
+        let results = self.get("/search", &[("q", &query)]).await?;
+        let Some((id, confidence)) = best_hit(&results, &track.track_name, primary) else {
+            bail!(
+                "No Genius match for '{}' by '{}'",
+                track.track_name,
+                track.artist_name
+            );
+        };
+        if confidence < min_confidence {
+            bail!(
+                "Best Genius match for '{}' by '{}' is only a {:.0}% match",
+                track.track_name,
+                track.artist_name,
+                confidence * 100.0
+            );
+        }
+
+        let song = self
+            .get(&format!("/songs/{}", id), &[("text_format", "plain")])
+            .await?;
+        apply_song(track, &song["song"]);
+        Ok(())
+    }
+
+    /// GET an API path and return the `response` object Genius wraps every
+    /// result in.
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<Value> {
+        crate::ratelimit::acquire().await;
+        let response = self
+            .http
+            .get(format!("{}{}", API_URL, path))
+            .query(query)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .with_context(|| format!("Genius API request {} failed", path))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            bail!("Genius rejected the access token; check access_token in [genius]");
+        }
+        if !status.is_success() {
+            bail!("Genius API returned {} for {}", status, path);
+        }
+        let mut body: Value = response.json().await?;
+        Ok(body["response"].take())
+    }
+}
+
+/// The section's access token, unless it is missing or blank.
+fn usable_token(config: &GeniusApiConfig) -> Option<&str> {
+    config
+        .access_token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// The ID of the song among `/search` results that best matches the title
+/// and artist, with how well it matches (0.0–1.0).
+fn best_hit(results: &Value, track_name: &str, artist_name: &str) -> Option<(u64, f64)> {
+    results["hits"]
+        .as_array()?
+        .iter()
+        .filter(|hit| hit["type"] == "song")
+        .filter_map(|hit| {
+            let song = &hit["result"];
+            let confidence = lyrics::match_confidence(
+                track_name,
+                artist_name,
+                song["title"].as_str().unwrap_or_default(),
+                song["primary_artist"]["name"].as_str().unwrap_or_default(),
+            );
+            Some((song["id"].as_u64()?, confidence))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Copy the credits and page URL of a `/songs/:id` song object onto
+/// `track`. Fields the song leaves empty keep their local values.
+fn apply_song(track: &mut TrackInfo, song: &Value) {
+    let names = |key: &str| -> String {
+        song[key]
+            .as_array()
+            .map(|artists| {
+                artists
+                    .iter()
+                    .filter_map(|artist| artist["name"].as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default()
+    };
+    let producers = names("producer_artists");
+    if !producers.is_empty() {
+        track.producers = producers;
+    }
+    let writers = names("writer_artists");
+    if !writers.is_empty() {
+        track.writers = writers;
+    }
+    match song["release_date"].as_str() {
+        Some(date) if track.release_date.is_empty() && !date.is_empty() => {
+            track.release_date = date.to_string()
+        }
+        _ => {}
+    }
+    if let Some(url) = song["url"].as_str() {
+        track.genius_url = Some(url.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn song_response() -> Value {
+        json!({
+            "song": {
+                "id": 378195,
+                "title": "Paranoid Android",
+                "url": "https://genius.com/Radiohead-paranoid-android-lyrics",
+                "release_date": "1997-05-26",
+                "primary_artist": {"id": 604, "name": "Radiohead"},
+                "producer_artists": [
+                    {"id": 1, "name": "Nigel Godrich"},
+                    {"id": 604, "name": "Radiohead"}
+                ],
+                "writer_artists": [
+                    {"id": 2, "name": "Thom Yorke"},
+                    {"id": 3, "name": "Jonny Greenwood"}
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn song_credits_fill_in_the_track() {
+        let mut track = TrackInfo::sample("spotify:track:abc", "Paranoid Android", "Radiohead");
+        apply_song(&mut track, &song_response()["song"]);
+        assert_eq!(track.producers, "Nigel Godrich, Radiohead");
+        assert_eq!(track.writers, "Thom Yorke, Jonny Greenwood");
+        assert_eq!(track.release_date, "1997-05-26");
+        assert_eq!(
+            track.genius_url.as_deref(),
+            Some("https://genius.com/Radiohead-paranoid-android-lyrics")
+        );
+
+        // A release date from the player or Web API is kept, and a song with
+        // no credits leaves the track's alone.
+        let mut track = TrackInfo {
+            release_date: "1997-05-21".to_string(),
+            writers: "Thom Yorke".to_string(),
+            ..TrackInfo::sample("spotify:track:abc", "Paranoid Android", "Radiohead")
+        };
+        apply_song(
+            &mut track,
+            &json!({"release_date": null, "writer_artists": []}),
+        );
+        assert_eq!(track.release_date, "1997-05-21");
+        assert_eq!(track.writers, "Thom Yorke");
+    }
+
+    #[test]
+    fn search_picks_the_closest_song() {
+        let results = json!({
+            "hits": [
+                {"type": "song", "result": {
+                    "id": 1, "title": "Paranoid Android (Live)",
+                    "primary_artist": {"name": "Radiohead Tribute"}
+                }},
+                {"type": "song", "result": {
+                    "id": 378195, "title": "Paranoid Android",
+                    "primary_artist": {"name": "Radiohead"}
+                }}
+            ]
+        });
+        let (id, confidence) = best_hit(&results, "Paranoid Android", "Radiohead").unwrap();
+        assert_eq!(id, 378195);
+        assert!(confidence > 0.9, "{}", confidence);
+
+        assert_eq!(best_hit(&json!({"hits": []}), "Song", "Artist"), None);
+    }
+
+    #[test]
+    fn blank_or_absent_tokens_disable_the_client() {
+        let client = |toml: &str| GeniusApiClient::from_config(&toml::from_str(toml).unwrap());
+        assert!(client("[genius]\naccess_token = \"abc\"").is_some());
+        assert!(client("[genius]\naccess_token = \"  \"").is_none());
+        assert!(client("[genius]").is_none());
+        assert!(client("").is_none());
+        assert_eq!(
+            usable_token(&GeniusApiConfig {
+                access_token: Some(" abc\n".to_string())
+            }),
+            Some("abc")
+        );
+    }
+}
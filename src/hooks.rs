@@ -0,0 +1,33 @@
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::db::TrackInfo;
+use crate::template;
+
+/// How long a hook may run before it is stopped.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run the `hooks.on_track_change` command for `track` in the background,
+/// reporting a failure, nonzero exit, or timeout on stderr. The command's own
+/// output goes to stderr too, so it never mixes with the track output.
+pub fn on_track_change(command: &str, track: &TrackInfo) {
+    let command = template::render_shell(command, track);
+    tokio::spawn(async move {
+        let status = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .stdin(Stdio::null())
+            .stdout(std::io::stderr())
+            .kill_on_drop(true)
+            .status();
+        match tokio::time::timeout(HOOK_TIMEOUT, status).await {
+            Ok(Ok(status)) if status.success() => {}
+            Ok(Ok(status)) => eprintln!("⚠️  on_track_change hook failed ({})", status),
+            Ok(Err(e)) => eprintln!("⚠️  Failed to run on_track_change hook: {}", e),
+            Err(_) => eprintln!(
+                "⚠️  on_track_change hook still running after {}s; stopped it",
+                HOOK_TIMEOUT.as_secs()
+            ),
+        }
+    });
+}
@@ -0,0 +1,114 @@
+/// One line of time-synced lyrics.
+#[derive(Debug, PartialEq)]
+pub struct LrcLine {
+    /// When the line starts, in milliseconds from the start of the song.
+    pub time_ms: i64,
+    pub text: String,
+}
+
+/// Parse LRC lyrics, where each line starts with one or more `[mm:ss.xx]`
+/// timestamps, into their lines in time order.
+///
+/// A line with several timestamps (a repeated chorus) appears once per
+/// timestamp. Tag lines such as `[ar:Artist]` and lines without a timestamp
+/// are skipped.
+pub fn parse(lrc: &str) -> Vec<LrcLine> {
+    let mut lines = Vec::new();
+    for line in lrc.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some((time_ms, after)) = rest
+            .strip_prefix('[')
+            .and_then(|r| r.split_once(']'))
+            .and_then(|(stamp, after)| Some((parse_timestamp(stamp)?, after)))
+        {
+            times.push(time_ms);
+            rest = after;
+        }
+        let text = rest.trim();
+        lines.extend(times.into_iter().map(|time_ms| LrcLine {
+            time_ms,
+            text: text.to_string(),
+        }));
+    }
+    lines.sort_by_key(|line| line.time_ms);
+    lines
+}
+
+/// `mm:ss`, `mm:ss.x`, `mm:ss.xx`, or `mm:ss.xxx`, in milliseconds.
+fn parse_timestamp(stamp: &str) -> Option<i64> {
+    let (minutes, seconds) = stamp.split_once(':')?;
+    let minutes: u32 = minutes.parse().ok()?;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let seconds: u32 = seconds.parse().ok()?;
+    if seconds >= 60 || fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let fraction_ms = match fraction.len() {
+        0 => 0,
+        digits => fraction.parse::<i64>().ok()? * 10_i64.pow(3 - digits as u32),
+    };
+    Some(i64::from(minutes) * 60_000 + i64::from(seconds) * 1000 + fraction_ms)
+}
+
+/// The lyrics without their timestamps, one line per timed line.
+pub fn to_plain(lrc: &str) -> String {
+    parse(lrc)
+        .into_iter()
+        .map(|line| line.text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Index of the line being sung at `position_ms`: the last one that has
+/// started. `None` before the first line.
+pub fn current_line(lines: &[LrcLine], position_ms: i64) -> Option<usize> {
+    lines
+        .partition_point(|line| line.time_ms <= position_ms)
+        .checked_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps_tags_and_repeated_lines() {
+        let lrc = "[ar:Radiohead]\n[ti:Creep]\n\
+                   [00:12.34]When you were here before\n\
+                   [00:20.5][01:40.250]  Chorus line  \n\
+                   no timestamp here\n\
+                   [00:30]\n";
+        let lines = parse(lrc);
+        let times: Vec<_> = lines.iter().map(|l| l.time_ms).collect();
+        assert_eq!(times, [12_340, 20_500, 30_000, 100_250]);
+        assert_eq!(lines[0].text, "When you were here before");
+        assert_eq!(lines[1].text, "Chorus line");
+        assert_eq!(lines[2].text, "");
+        assert_eq!(lines[3].text, "Chorus line");
+    }
+
+    #[test]
+    fn malformed_stamps_are_not_timestamps() {
+        assert_eq!(parse_timestamp("01:75.00"), None);
+        assert_eq!(parse_timestamp("-1:05.00"), None);
+        assert_eq!(parse_timestamp("01:05.1234"), None);
+        assert_eq!(parse_timestamp("length"), None);
+        assert_eq!(parse_timestamp("02:05.07"), Some(125_070));
+    }
+
+    #[test]
+    fn plain_text_drops_timestamps() {
+        let lrc = "[00:01.00]First\n[00:03.00]Second";
+        assert_eq!(to_plain(lrc), "First\nSecond");
+    }
+
+    #[test]
+    fn current_line_follows_position() {
+        let lines = parse("[00:01.00]a\n[00:03.00]b\n[00:05.00]c");
+        assert_eq!(current_line(&lines, 500), None);
+        assert_eq!(current_line(&lines, 1000), Some(0));
+        assert_eq!(current_line(&lines, 4999), Some(1));
+        assert_eq!(current_line(&lines, 60_000), Some(2));
+    }
+}
@@ -1,37 +1,194 @@
-use anyhow::{Context, Result};
-use lyric_finder::{Client, LyricResult};
+use anyhow::{anyhow, Context, Result};
+use lyric_finder::Client;
+use regex::Regex;
+use serde_json::Value;
+use std::future::Future;
+use std::time::Duration;
 
-/// Client for fetching song lyrics automatically, without any API key.
-pub struct LyricsClient {
+use crate::config::{LyricsConfig, LyricsSource};
+use crate::db::TrackInfo;
+use crate::lrc;
+
+/// Page furniture that the lyrics source mixes into the lyric text, removed
+/// before the lyrics are returned.
+const DEFAULT_CLEANUP_PATTERNS: &[&str] = &[
+    // Trailing "123Embed" widget counter
+    r"\d*Embed\s*\z",
+    // Related-songs banner, often glued onto a lyric line
+    r"You might also like",
+    // Concert ticket adverts
+    r"(?m)^See .*? LiveGet tickets as low as \$\d+.*$",
+];
+
+/// Wait before the first retry of a failed lookup; doubled for each one after.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// LRCLIB's search endpoint, answering with a JSON array of matching songs.
+const LRCLIB_SEARCH_URL: &str = "https://lrclib.net/api/search";
+
+/// LRCLIB asks clients to identify themselves.
+const USER_AGENT: &str = concat!(
+    "playbot/",
+    env!("CARGO_PKG_VERSION"),
+    " (",
+    env!("CARGO_PKG_REPOSITORY"),
+    ")"
+);
+
+/// A source of song lyrics.
+pub trait LyricsProvider {
+    /// Fetch lyrics for a song by title and artist name.
+    ///
+    /// Returns the bare lyric text, or a "not found" message if no lyrics are
+    /// available. Never returns an error for missing lyrics. Track and artist
+    /// headings are left to the display layer.
+    async fn get_lyrics(&self, song_title: &str, artist_name: &str) -> Result<Lyrics>;
+}
+
+/// The provider chosen by `[lyrics] provider`.
+pub enum Provider {
+    Genius(GeniusClient),
+    LrcLib(LrcLibClient),
+}
+
+impl Provider {
+    /// The configured provider, or `None` when lookups are turned off.
+    pub fn from_config(config: &LyricsConfig) -> Option<Self> {
+        match config.provider {
+            LyricsSource::Genius => Some(Self::Genius(GeniusClient::new(config))),
+            LyricsSource::Lrclib => Some(Self::LrcLib(LrcLibClient::new(config))),
+            LyricsSource::None => None,
+        }
+    }
+}
+
+impl LyricsProvider for Provider {
+    async fn get_lyrics(&self, song_title: &str, artist_name: &str) -> Result<Lyrics> {
+        match self {
+            Self::Genius(client) => client.get_lyrics(song_title, artist_name).await,
+            Self::LrcLib(client) => client.get_lyrics(song_title, artist_name).await,
+        }
+    }
+}
+
+/// Lyrics from Genius, found through `lyric_finder` without any API key.
+pub struct GeniusClient {
     client: Client,
+    cleanup: Vec<Regex>,
+    retry: Retry,
+}
+
+/// Lyrics from LRCLIB (lrclib.net), an open database that often has songs,
+/// particularly non-English ones, that Genius search misses. No API key is
+/// needed.
+pub struct LrcLibClient {
+    http: reqwest::Client,
+    cleanup: Vec<Regex>,
+    retry: Retry,
+}
+
+/// Lyrics for a single song.
+pub struct Lyrics {
+    /// Cleaned lyric text, or a "not found" message.
+    pub text: String,
+    /// The unprocessed lyric text as returned by the lookup, before cleanup.
+    /// `None` when no lyrics were found.
+    pub raw: Option<String>,
+    /// How closely the matched song's title and artist agree with the ones
+    /// requested, from 0.0 (unrelated) to 1.0 (identical). `None` when no
+    /// lyrics were found.
+    pub match_confidence: Option<f64>,
+    /// The song the lookup actually matched, as "Title by Artists". `None`
+    /// when no lyrics were found.
+    pub matched_song: Option<String>,
+    /// The lyrics in LRC format, with the time each line is sung, when the
+    /// provider has them. Only LRCLIB does.
+    pub synced: Option<String>,
+    /// The matched song's page on Genius, when Genius provided the lyrics.
+    pub genius_url: Option<String>,
 }
 
-impl LyricsClient {
-    /// Create a new lyrics client.
-    pub fn new() -> Self {
-        let client = Client::new();
-        Self { client }
+impl Lyrics {
+    fn not_found(song_title: &str, artist_name: &str) -> Self {
+        Self {
+            text: format!("No lyrics found for '{}' by '{}'", song_title, artist_name),
+            raw: None,
+            match_confidence: None,
+            matched_song: None,
+            synced: None,
+            genius_url: None,
+        }
     }
+}
 
-    /// Fetch lyrics for a song by title and artist name.
+/// A song found on Genius, with the lyrics scraped from its page.
+struct GeniusSong {
+    track: String,
+    artists: String,
+    url: String,
+    lyric: String,
+}
+
+impl GeniusClient {
+    /// Create a Genius client that strips the built-in cleanup patterns plus
+    /// the configured `cleanup_patterns` from fetched lyrics, and retries
+    /// failed lookups as configured.
     ///
-    /// Returns the lyrics as a formatted string, or a "not found" message if
-    /// no lyrics are available. Never returns an error for missing lyrics.
-    pub async fn get_lyrics(&self, song_title: &str, artist_name: &str) -> Result<String> {
-        let search_query = format!("{} {}", song_title, artist_name);
+    /// Patterns that fail to compile are reported on stderr and skipped.
+    pub fn new(config: &LyricsConfig) -> Self {
+        Self {
+            client: Client::new(),
+            cleanup: compile_cleanup_patterns(&config.cleanup_patterns),
+            retry: Retry::new(config),
+        }
+    }
 
-        let result = self
+    /// Search Genius and fetch the lyrics of the first result not credited to
+    /// Genius itself (its own annotation and list pages), as `lyric_finder`'s
+    /// `get_lyric` does, but keeping the page URL it came from.
+    async fn find_song(&self, query: &str) -> Result<Option<GeniusSong>> {
+        let Some(result) = self
             .client
-            .get_lyric(&search_query)
-            .await
-            .context("Failed to fetch lyrics")?;
+            .search_songs(query)
+            .await?
+            .into_iter()
+            .find(|result| !result.artist_names.contains("Genius"))
+        else {
+            return Ok(None);
+        };
+        let lyric = self.client.retrieve_lyric(&result.url).await?;
+        Ok(Some(GeniusSong {
+            track: result.title,
+            artists: result.artist_names,
+            url: result.url,
+            lyric: space_sections(&lyric),
+        }))
+    }
+}
+
+/// Put one blank line before every `[Section]` heading of scraped lyrics,
+/// which come with anywhere from none to two.
+fn space_sections(lyric: &str) -> String {
+    lyric.replace("\n\n[", "\n[").replace("\n[", "\n\n[")
+}
+
+impl LyricsProvider for GeniusClient {
+    async fn get_lyrics(&self, song_title: &str, artist_name: &str) -> Result<Lyrics> {
+        let search_query = format!("{} {}", song_title, artist_name);
+        let result = self
+            .retry
+            .run(song_title, artist_name, || self.find_song(&search_query))
+            .await?;
 
         match result {
-            LyricResult::Some {
+            Some(GeniusSong {
                 track,
                 artists,
+                url,
                 lyric,
-            } => {
+            }) => {
+                let match_confidence = match_confidence(song_title, artist_name, &track, &artists);
+
                 // Clean up the lyrics by removing metadata artifacts
                 let cleaned_lyric = lyric
                     .trim()
@@ -43,13 +200,615 @@ impl LyricsClient {
                     .trim_start_matches(&track)
                     .trim_start_matches(" Lyrics")
                     .trim();
+                let cleaned_lyric = apply_cleanup(&self.cleanup, cleaned_lyric);
 
-                Ok(format!("🎵 {}\n👤 {}\n\n{}", track, artists, cleaned_lyric))
+                Ok(Lyrics {
+                    text: cleaned_lyric,
+                    raw: Some(lyric),
+                    match_confidence: Some(match_confidence),
+                    matched_song: Some(format!("{} by {}", track, artists)),
+                    synced: None,
+                    genius_url: Some(url),
+                })
             }
-            LyricResult::None => Ok(format!(
-                "No lyrics found for '{}' by '{}'",
-                song_title, artist_name
-            )),
+            None => Ok(Lyrics::not_found(song_title, artist_name)),
         }
     }
 }
+
+impl LrcLibClient {
+    /// Create an LRCLIB client that applies the cleanup patterns and retries
+    /// like [`GeniusClient::new`].
+    pub fn new(config: &LyricsConfig) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .build()
+                .unwrap_or_default(),
+            cleanup: compile_cleanup_patterns(&config.cleanup_patterns),
+            retry: Retry::new(config),
+        }
+    }
+
+    async fn search(&self, query: &str) -> Result<Value> {
+        let response = self
+            .http
+            .get(LRCLIB_SEARCH_URL)
+            .query(&[("q", query)])
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+impl LyricsProvider for LrcLibClient {
+    async fn get_lyrics(&self, song_title: &str, artist_name: &str) -> Result<Lyrics> {
+        let query = format!("{} {}", strip_title_suffix(song_title), artist_name);
+        let results = self
+            .retry
+            .run(song_title, artist_name, || self.search(&query))
+            .await?;
+
+        Ok(match best_lrclib_match(&results, song_title, artist_name) {
+            Some(found) => {
+                // Songs with only synced lyrics get their text from those.
+                let plain = found
+                    .plain
+                    .map(str::to_string)
+                    .or_else(|| found.synced.map(lrc::to_plain))
+                    .unwrap_or_default();
+                Lyrics {
+                    text: apply_cleanup(&self.cleanup, &plain),
+                    raw: found.plain.or(found.synced).map(str::to_string),
+                    match_confidence: Some(found.confidence),
+                    matched_song: Some(format!("{} by {}", found.track, found.artists)),
+                    synced: found.synced.map(str::to_string),
+                    genius_url: None,
+                }
+            }
+            None => Lyrics::not_found(song_title, artist_name),
+        })
+    }
+}
+
+/// One LRCLIB search result with lyrics, scored against the requested song.
+struct LrcLibMatch<'a> {
+    track: &'a str,
+    artists: &'a str,
+    plain: Option<&'a str>,
+    synced: Option<&'a str>,
+    confidence: f64,
+}
+
+/// The search result whose title and artist agree best with the requested
+/// ones, among those with plain or synced lyrics (instrumentals have none).
+fn best_lrclib_match<'a>(
+    results: &'a Value,
+    song_title: &str,
+    artist_name: &str,
+) -> Option<LrcLibMatch<'a>> {
+    results
+        .as_array()?
+        .iter()
+        .filter_map(|result| {
+            let lyrics = |key: &str| {
+                result[key]
+                    .as_str()
+                    .filter(|lyrics| !lyrics.trim().is_empty())
+            };
+            let (plain, synced) = (lyrics("plainLyrics"), lyrics("syncedLyrics"));
+            if plain.is_none() && synced.is_none() {
+                return None;
+            }
+            let track = result["trackName"].as_str().unwrap_or_default();
+            let artists = result["artistName"].as_str().unwrap_or_default();
+            Some(LrcLibMatch {
+                track,
+                artists,
+                plain,
+                synced,
+                confidence: match_confidence(song_title, artist_name, track, artists),
+            })
+        })
+        .max_by(|a, b| a.confidence.total_cmp(&b.confidence))
+}
+
+/// Retry and timeout settings from `[lyrics]`, shared by every provider.
+struct Retry {
+    max_retries: u32,
+    timeout: Duration,
+}
+
+impl Retry {
+    fn new(config: &LyricsConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            timeout: Duration::from_secs(config.timeout_secs.max(1)),
+        }
+    }
+
+    /// Run `lookup` for a song, abandoning each attempt after the timeout.
+    ///
+    /// Timeouts, network errors, and server errors are retried up to
+    /// `max_retries` times with exponential backoff; other failures, and the
+    /// last one once retries run out, are returned.
+    async fn run<T, F, Fut>(&self, song_title: &str, artist_name: &str, lookup: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            crate::ratelimit::acquire().await;
+            let result = match tokio::time::timeout(self.timeout, lookup()).await {
+                Ok(result) => result,
+                Err(_) => Err(anyhow!(LookupTimedOut(self.timeout))),
+            };
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Failed to fetch lyrics for '{}' by '{}' after {} attempt(s)",
+                            song_title,
+                            artist_name,
+                            attempt + 1
+                        )
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// A lookup that got no answer in time.
+#[derive(Debug)]
+struct LookupTimedOut(Duration);
+
+impl std::fmt::Display for LookupTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Lyrics lookup timed out after {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for LookupTimedOut {}
+
+/// Whether a failed lookup is worth repeating: timeouts, connection problems,
+/// and 5xx responses are; 4xx responses would only fail the same way again.
+/// `lyric_finder` reports HTTP errors only as text, so the status is read
+/// from the message (reqwest's own errors read the same way).
+fn is_retryable(err: &anyhow::Error) -> bool {
+    if err.downcast_ref::<LookupTimedOut>().is_some() {
+        return true;
+    }
+    !err.chain()
+        .any(|cause| cause.to_string().contains("HTTP status client error"))
+}
+
+/// The track's page on Genius as its lookup stored it, or for tracks cached
+/// without one, the search page for it.
+pub fn genius_page_url(track: &TrackInfo) -> String {
+    track
+        .genius_url
+        .clone()
+        .unwrap_or_else(|| genius_search_url(&track.track_name, &track.artist_name))
+}
+
+/// Genius search page for a song, where its lyrics page and annotations are
+/// usually the top result.
+pub fn genius_search_url(song_title: &str, artist_name: &str) -> String {
+    let query = format!("{} {}", strip_title_suffix(song_title), artist_name);
+    let encoded: String = query
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b' ' => "+".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("https://genius.com/search?q={}", encoded)
+}
+
+/// Compile the default cleanup patterns followed by `extra`, warning about
+/// (and skipping) any user pattern that is not a valid regex.
+fn compile_cleanup_patterns(extra: &[String]) -> Vec<Regex> {
+    let defaults = DEFAULT_CLEANUP_PATTERNS
+        .iter()
+        .map(|p| Regex::new(p).expect("built-in cleanup pattern is valid"));
+    let user = extra.iter().filter_map(|p| match Regex::new(p) {
+        Ok(re) => Some(re),
+        Err(e) => {
+            eprintln!("⚠️  Ignoring invalid lyrics cleanup pattern {:?}: {}", p, e);
+            None
+        }
+    });
+    defaults.chain(user).collect()
+}
+
+/// Remove every match of `patterns` from `text`, in order, and trim the result.
+fn apply_cleanup(patterns: &[Regex], text: &str) -> String {
+    let mut cleaned = text.to_string();
+    for re in patterns {
+        cleaned = re.replace_all(&cleaned, "").into_owned();
+    }
+    cleaned.trim().to_string()
+}
+
+/// Marker appended to lyrics cut short by [`truncate_lyrics`].
+const TRUNCATION_MARKER: &str = "\n[… lyrics truncated …]";
+
+/// Fragments that show up in scraped page furniture but not in song lyrics.
+const BOILERPLATE_MARKERS: &[&str] = &[
+    "http://",
+    "https://",
+    "www.",
+    "Embed",
+    "You might also like",
+    "Contributors",
+    "Translations",
+    "Read More",
+    "Get tickets",
+];
+
+/// Whether fetched text looks like a page scrape rather than lyrics: more
+/// than half of its lines contain link or page-chrome fragments.
+///
+/// Short texts are never flagged, since a couple of odd lines is not enough
+/// evidence to throw away a match.
+pub fn looks_like_boilerplate(text: &str) -> bool {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    if lines.len() < 4 {
+        return false;
+    }
+    let boilerplate = lines
+        .iter()
+        .filter(|line| BOILERPLATE_MARKERS.iter().any(|m| line.contains(m)))
+        .count();
+    boilerplate * 2 > lines.len()
+}
+
+/// Lyrics for a track as they go into its cache columns.
+#[derive(Debug, Default)]
+pub struct StoredLyrics {
+    pub text: Option<String>,
+    pub confidence: Option<f64>,
+    pub synced: Option<String>,
+    /// The Genius page of the match, kept only with its text.
+    pub genius_url: Option<String>,
+    /// What vetting dropped or changed, to tell the user.
+    pub warnings: Vec<String>,
+}
+
+/// Decide what of a lookup's result is worth caching.
+///
+/// A match scoring below `config.confirm_below` is put to `confirm`, which
+/// returns `None` when there is nobody to ask; an unconfirmed match is then
+/// kept if it clears `min_match_confidence`, with a warning. Page boilerplate
+/// is dropped, text over `max_bytes` truncated, and synced lyrics kept only
+/// with the whole text.
+pub fn vet_lyrics(
+    found: Lyrics,
+    config: &LyricsConfig,
+    max_bytes: usize,
+    confirm: impl FnOnce(&Lyrics, f64) -> Result<Option<bool>>,
+) -> Result<StoredLyrics> {
+    let mut warnings = Vec::new();
+    if found.raw.is_none() {
+        // Nothing to store; a track refreshed this way keeps its old lyrics.
+        warnings.push(format!("🔇 {}", found.text));
+        return Ok(StoredLyrics {
+            warnings,
+            ..StoredLyrics::default()
+        });
+    }
+
+    let answer = match found.match_confidence {
+        Some(confidence) if confidence < config.confirm_below => confirm(&found, confidence)?,
+        _ => None,
+    };
+    let rejected = match (answer, found.match_confidence) {
+        (Some(accepted), _) => !accepted,
+        (None, Some(confidence)) if confidence < config.min_match_confidence => {
+            warnings.push(format!(
+                "⚠️  Lyrics match looks wrong (confidence {:.0}%); not storing lyrics",
+                confidence * 100.0
+            ));
+            true
+        }
+        (None, Some(confidence)) if confidence < config.confirm_below => {
+            warnings.push(format!(
+                "⚠️  Lyrics match is uncertain (confidence {:.0}%); storing it anyway",
+                confidence * 100.0
+            ));
+            false
+        }
+        (None, _) => false,
+    };
+
+    let text = match (!rejected).then_some(found.text) {
+        Some(text) if looks_like_boilerplate(&text) => {
+            warnings.push(
+                "⚠️  Fetched lyrics look like page boilerplate; not storing lyrics".to_string(),
+            );
+            None
+        }
+        Some(text) if text.len() > max_bytes => {
+            warnings.push(format!(
+                "⚠️  Lyrics are {} bytes, over the {} byte limit; truncating",
+                text.len(),
+                max_bytes
+            ));
+            Some(truncate_lyrics(&text, max_bytes))
+        }
+        text => text,
+    };
+    // Synced lyrics only go with stored text, and not with a truncated one.
+    let synced = found
+        .synced
+        .filter(|synced| text.is_some() && synced.len() <= max_bytes);
+
+    let genius_url = found.genius_url.filter(|_| text.is_some());
+
+    Ok(StoredLyrics {
+        text,
+        confidence: found.match_confidence,
+        synced,
+        genius_url,
+        warnings,
+    })
+}
+
+/// Cut `text` to at most `max_bytes` (plus a marker), breaking at the last
+/// full line that fits. Returns `text` unchanged if it is already short enough.
+pub fn truncate_lyrics(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = text[..end].rfind('\n').unwrap_or(end);
+    format!("{}{}", &text[..end], TRUNCATION_MARKER)
+}
+
+/// Score how well a lyric match corresponds to the requested song, averaging
+/// title and artist similarity.
+pub fn match_confidence(
+    requested_title: &str,
+    requested_artist: &str,
+    matched_title: &str,
+    matched_artists: &str,
+) -> f64 {
+    let title = similarity(
+        &strip_title_suffix(requested_title),
+        &strip_title_suffix(matched_title),
+    );
+    let artist = similarity(requested_artist, matched_artists);
+    (title + artist) / 2.0
+}
+
+/// Drop version annotations such as " - Remastered 2011" or " (feat. X)" that
+/// Spotify adds to titles but lyric sites usually omit.
+fn strip_title_suffix(title: &str) -> String {
+    let end = [" - ", " (", " ["]
+        .iter()
+        .filter_map(|sep| title.find(sep))
+        .filter(|&i| i > 0)
+        .min()
+        .unwrap_or(title.len());
+    title[..end].to_string()
+}
+
+/// Dice coefficient over the lowercase alphanumeric words of both strings.
+fn similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> std::collections::HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(&b).count();
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        assert!(is_retryable(&anyhow!(LookupTimedOut(Duration::from_secs(
+            10
+        )))));
+        assert!(is_retryable(&anyhow!(
+            "HTTP status server error (503 Service Unavailable) for url (https://genius.com/)"
+        )));
+        assert!(is_retryable(
+            &anyhow!("connection reset by peer").context("error sending request")
+        ));
+        assert!(!is_retryable(&anyhow!(
+            "HTTP status client error (404 Not Found) for url (https://genius.com/)"
+        )));
+    }
+
+    #[test]
+    fn truncation_breaks_on_line_and_marks_it() {
+        let text = "first line\nsecond line\nthird line";
+        let truncated = truncate_lyrics(text, 15);
+        assert_eq!(truncated, format!("first line{}", TRUNCATION_MARKER));
+        assert_eq!(truncate_lyrics(text, 1000), text);
+    }
+
+    #[test]
+    fn truncation_respects_char_boundaries() {
+        let truncated = truncate_lyrics("ééééé", 3);
+        assert_eq!(truncated, format!("é{}", TRUNCATION_MARKER));
+    }
+
+    #[test]
+    fn boilerplate_detection_is_conservative() {
+        let lyrics = "[Verse 1]\nSee the stars tonight\nHolding on\nLetting go";
+        assert!(!looks_like_boilerplate(lyrics));
+
+        let scrape = "Read More\nhttps://genius.com/a\nYou might also like\n12 Embed\nla la";
+        assert!(looks_like_boilerplate(scrape));
+    }
+
+    #[test]
+    fn default_cleanup_strips_genius_furniture() {
+        let patterns = compile_cleanup_patterns(&[]);
+        let raw = "Line one\nYou might also likeLine two\nLast line42Embed";
+        assert_eq!(
+            apply_cleanup(&patterns, raw),
+            "Line one\nLine two\nLast line"
+        );
+    }
+
+    #[test]
+    fn user_cleanup_patterns_layer_on_defaults_and_skip_invalid() {
+        let extra = vec!["(?m)^\\[Chorus\\]\n".to_string(), "([".to_string()];
+        let patterns = compile_cleanup_patterns(&extra);
+        assert_eq!(patterns.len(), DEFAULT_CLEANUP_PATTERNS.len() + 1);
+        assert_eq!(apply_cleanup(&patterns, "[Chorus]\nla la\n3Embed"), "la la");
+    }
+
+    #[test]
+    fn genius_search_url_is_encoded() {
+        assert_eq!(
+            genius_search_url("Don't Stop Me Now - Remastered 2011", "Queen"),
+            "https://genius.com/search?q=Don%27t+Stop+Me+Now+Queen"
+        );
+        assert_eq!(
+            genius_search_url("Jóga", "Björk"),
+            "https://genius.com/search?q=J%C3%B3ga+Bj%C3%B6rk"
+        );
+    }
+
+    #[test]
+    fn stored_genius_page_beats_the_search_page() {
+        let track = TrackInfo::sample("id:1", "Hello", "Adele");
+        assert_eq!(
+            genius_page_url(&track),
+            "https://genius.com/search?q=Hello+Adele"
+        );
+        let track = TrackInfo {
+            genius_url: Some("https://genius.com/Adele-hello-lyrics".to_string()),
+            ..track
+        };
+        assert_eq!(
+            genius_page_url(&track),
+            "https://genius.com/Adele-hello-lyrics"
+        );
+    }
+
+    #[test]
+    fn lrclib_picks_the_closest_result_with_lyrics() {
+        let results = serde_json::json!([
+            {"trackName": "Creep", "artistName": "Stone Temple Pilots", "plainLyrics": "wrong band"},
+            {"trackName": "Creep", "artistName": "Radiohead", "plainLyrics": null,
+             "instrumental": true},
+            {"trackName": "Creep (Acoustic)", "artistName": "Radiohead", "plainLyrics": "right",
+             "syncedLyrics": "[00:01.00]right"},
+        ]);
+        let found = best_lrclib_match(&results, "Creep", "Radiohead").unwrap();
+        assert_eq!(found.plain, Some("right"));
+        assert_eq!(found.synced, Some("[00:01.00]right"));
+        assert_eq!(found.confidence, 1.0);
+
+        let synced_only = serde_json::json!([
+            {"trackName": "Creep", "artistName": "Radiohead", "syncedLyrics": "[00:01.00]x"},
+        ]);
+        let found = best_lrclib_match(&synced_only, "Creep", "Radiohead").unwrap();
+        assert_eq!((found.plain, found.synced), (None, Some("[00:01.00]x")));
+
+        assert!(best_lrclib_match(&serde_json::json!([]), "Creep", "Radiohead").is_none());
+    }
+
+    #[test]
+    fn exact_match_is_fully_confident() {
+        let score = match_confidence("Creep", "Radiohead", "Creep", "Radiohead");
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn version_suffix_and_case_are_ignored() {
+        let score = match_confidence(
+            "Karma Police - Remastered 2017",
+            "Radiohead",
+            "Karma police",
+            "radiohead",
+        );
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn different_artist_lowers_confidence() {
+        let score = match_confidence("Creep", "Radiohead", "Creep", "Stone Temple Pilots");
+        assert!(score <= 0.5, "score was {}", score);
+    }
+
+    #[test]
+    fn unrelated_song_scores_zero() {
+        let score = match_confidence("Creep", "Radiohead", "Hello", "Adele");
+        assert_eq!(score, 0.0);
+    }
+
+    fn found(confidence: f64) -> Lyrics {
+        Lyrics {
+            text: "Hello from the other side".to_string(),
+            raw: Some("Hello from the other side".to_string()),
+            match_confidence: Some(confidence),
+            matched_song: Some("Hello by Adele".to_string()),
+            synced: Some("[00:01.00]Hello from the other side".to_string()),
+            genius_url: Some("https://genius.com/Adele-hello-lyrics".to_string()),
+        }
+    }
+
+    #[test]
+    fn vetting_follows_the_confidence_thresholds() {
+        let config = LyricsConfig::default();
+        let ask_nobody = |_: &Lyrics, _: f64| Ok(None);
+
+        let sure = vet_lyrics(found(0.95), &config, 1000, ask_nobody).unwrap();
+        assert!(sure.text.is_some() && sure.synced.is_some());
+        assert!(sure.genius_url.is_some());
+        assert!(sure.warnings.is_empty());
+
+        let uncertain = vet_lyrics(found(0.6), &config, 1000, ask_nobody).unwrap();
+        assert!(uncertain.text.is_some());
+        assert_eq!(uncertain.warnings.len(), 1);
+
+        let wrong = vet_lyrics(found(0.3), &config, 1000, ask_nobody).unwrap();
+        assert_eq!((wrong.text, wrong.synced), (None, None));
+        assert_eq!(wrong.genius_url, None);
+        assert_eq!(wrong.confidence, Some(0.3));
+
+        let declined = vet_lyrics(found(0.6), &config, 1000, |_, _| Ok(Some(false))).unwrap();
+        assert!(declined.text.is_none());
+        let accepted = vet_lyrics(found(0.3), &config, 1000, |_, _| Ok(Some(true))).unwrap();
+        assert!(accepted.text.is_some());
+    }
+
+    #[test]
+    fn vetting_truncates_and_drops_synced_lyrics() {
+        let stored = vet_lyrics(found(1.0), &LyricsConfig::default(), 10, |_, _| {
+            panic!("a sure match isn't confirmed")
+        })
+        .unwrap();
+        assert!(stored.text.unwrap().ends_with(TRUNCATION_MARKER));
+        assert!(stored.synced.is_none());
+        assert_eq!(stored.warnings.len(), 1);
+    }
+}
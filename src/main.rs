@@ -1,17 +1,35 @@
+mod album_art;
+mod browser;
+mod clipboard;
 mod config;
 mod db;
+mod export;
+mod filter;
+mod genius_api;
+mod hooks;
+mod lrc;
 mod lyrics;
+mod output;
+mod ratelimit;
+mod scrobble;
 mod spotify;
+mod spotify_web;
+mod template;
 mod tui;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
+use std::collections::BTreeSet;
+use std::io::{BufRead, IsTerminal, Write};
+
+use lyrics::{LyricsProvider, StoredLyrics};
+use output::outln;
 
 #[derive(Parser, Debug)]
 #[command(name = "playbot")]
 #[command(about = "Get detailed information about the currently playing Spotify song", long_about = None)]
 struct Cli {
-    /// Path to the configuration file
+    /// Path to the configuration file (overrides $PLAYBOT_CONFIG)
     #[arg(short, long)]
     config: Option<String>,
 
@@ -19,9 +37,17 @@ struct Cli {
     #[arg(short, long)]
     refresh: bool,
 
-    /// Show recently queried songs
+    /// Show the N most recently queried songs (10 if N is left out)
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    recent: Option<usize>,
+
+    /// Show the most played songs
+    #[arg(long)]
+    top: bool,
+
+    /// Show starred songs
     #[arg(long)]
-    recent: bool,
+    favorites: bool,
 
     /// Browse database with interactive TUI
     #[arg(short, long)]
@@ -31,32 +57,310 @@ struct Cli {
     #[arg(short, long)]
     search: Option<String>,
 
+    /// Find songs by a phrase from their lyrics (or title, artist, album)
+    #[arg(long, value_name = "PHRASE")]
+    lyrics_search: Option<String>,
+
+    /// List every cached track by this artist (exact name, any case), grouped
+    /// by album
+    #[arg(long, value_name = "NAME")]
+    artist: Option<String>,
+
+    /// List every cached track from this album (exact name, any case)
+    #[arg(long, value_name = "NAME")]
+    album: Option<String>,
+
+    /// Show at most N --search results
+    #[arg(long, value_name = "N", requires = "search")]
+    limit: Option<usize>,
+
+    /// Sort --search, --recent, and --where listings by this field
+    #[arg(long, value_enum, value_name = "KEY")]
+    sort: Option<db::SortKey>,
+
+    /// Reverse the --sort order (descending)
+    #[arg(long, requires = "sort")]
+    desc: bool,
+
+    /// Filter listings with an expression, e.g. "popularity > 70 AND year >= 2010"
+    #[arg(long = "where", value_name = "EXPR", value_parser = filter::Filter::parse)]
+    filter: Option<filter::Filter>,
+
+    /// Leave tracks the Spotify Web API marks explicit out of listings
+    #[arg(long)]
+    clean_only: bool,
+
     /// Count total tracks in database
     #[arg(short = 'n', long)]
     count: bool,
+
+    /// Summarize the cache: artists, albums, popularity, listening time, and top artists
+    #[arg(long)]
+    stats: bool,
+
+    /// Print full details for a cached track by its Spotify ID
+    #[arg(long, value_name = "TRACK_ID")]
+    info: Option<String>,
+
+    /// Fetch, cache, and show a track from a Spotify share link
+    /// (https://open.spotify.com/track/... or spotify:track:...), whether or
+    /// not it is playing. Needs the [spotify] config section
+    #[arg(long, value_name = "LINK", value_parser = spotify_web::parse_track_link)]
+    lookup: Option<String>,
+
+    /// Never read from or write to the database; every run is a fresh fetch
+    #[arg(long, conflicts_with_all = ["browse", "search", "lyrics_search", "artist", "album", "recent", "top", "favorites", "favorite", "filter", "count", "stats", "info", "lookup", "open_genius", "art", "merge_db", "export_csv", "export_json", "export_public", "import_json", "purge_lyrics", "reindex", "dedupe", "delete", "repl"])]
+    no_cache: bool,
+
+    /// Keep running and show each new track as the player changes songs
+    #[arg(long)]
+    watch: bool,
+
+    /// How --watch shows a new track: the full summary, or a one-line
+    /// "previous → current" notice with the key details
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        default_value_t,
+        requires = "watch",
+        conflicts_with_all = ["json", "format"]
+    )]
+    watch_format: WatchFormat,
+
+    /// Ring the terminal bell when --watch sees the song change (only when
+    /// stderr is a terminal)
+    #[arg(long, requires = "watch")]
+    bell: bool,
+
+    /// Print the unprocessed lyrics to stderr on a fresh fetch (for debugging cleanup)
+    #[arg(long)]
+    raw_lyrics: bool,
+
+    /// Skip the lyrics lookup on a fresh fetch (with --refresh, cached lyrics are
+    /// kept); with --export-csv or --export-json, leave lyrics out for sharing
+    #[arg(long, conflicts_with = "raw_lyrics")]
+    no_lyrics: bool,
+
+    /// Keep a file updated with the current "Title - Artist" (e.g. for an OBS text source)
+    #[arg(long, value_name = "PATH")]
+    write_file: Option<String>,
+
+    /// Open the Genius page for a cached track (by Spotify ID) in the browser
+    #[arg(long, value_name = "TRACK_ID")]
+    open_genius: Option<String>,
+
+    /// Merge tracks from another playbot database into this one
+    #[arg(long, value_name = "PATH")]
+    merge_db: Option<String>,
+
+    /// How --merge-db treats tracks present in both databases
+    #[arg(
+        long,
+        value_enum,
+        value_name = "POLICY",
+        default_value_t,
+        requires = "merge_db"
+    )]
+    on_conflict: db::OnConflict,
+
+    /// Remove stored lyrics (all tracks, or those matching --where), keeping metadata
+    #[arg(long)]
+    purge_lyrics: bool,
+
+    /// After --purge-lyrics, compact the database file to reclaim the space
+    #[arg(long, requires = "purge_lyrics")]
+    vacuum: bool,
+
+    /// Write every cached track (or those matching --where) to a CSV file
+    #[arg(long, value_name = "PATH")]
+    export_csv: Option<String>,
+
+    /// Write every cached track (or those matching --where) to a JSON file
+    #[arg(long, value_name = "PATH")]
+    export_json: Option<String>,
+
+    /// Write a JSON export without lyrics, for sharing (--export-json --no-lyrics)
+    #[arg(long, value_name = "PATH")]
+    export_public: Option<String>,
+
+    /// Load tracks from a JSON export, replacing cached copies with the same ID
+    #[arg(long, value_name = "PATH")]
+    import_json: Option<String>,
+
+    /// Print the playing track's album art URL (and open it when run in a terminal)
+    #[arg(long)]
+    art: bool,
+
+    /// Remove a cached track matching QUERY (asks which one when several match)
+    #[arg(long, value_name = "QUERY")]
+    delete: Option<String>,
+
+    /// Star or unstar a cached track matching QUERY (asks which one when several match)
+    #[arg(long, value_name = "QUERY")]
+    favorite: Option<String>,
+
+    /// Recompute derived data (cleaned lyrics, duplicate detection, indexes) after an upgrade
+    #[arg(long)]
+    reindex: bool,
+
+    /// Merge tracks cached more than once under IDs differing only in punctuation or case
+    #[arg(long)]
+    dedupe: bool,
+
+    /// With --dedupe, list the merges without changing the database
+    #[arg(long, requires = "dedupe")]
+    dry_run: bool,
+
+    /// MPRIS player to read on Linux, overriding `player.name` (e.g. vlc, ncspot)
+    #[arg(long, value_name = "NAME")]
+    player: Option<String>,
+
+    /// List the MPRIS players currently running (Linux), for use with --player
+    #[arg(long)]
+    list_players: bool,
+
+    /// Resume playback
+    #[arg(long, group = "transport")]
+    play: bool,
+
+    /// Pause playback
+    #[arg(long, group = "transport")]
+    pause: bool,
+
+    /// Skip to the next track
+    #[arg(long, group = "transport")]
+    next: bool,
+
+    /// Go back to the previous track
+    #[arg(long, group = "transport")]
+    prev: bool,
+
+    /// Print tracks as JSON instead of formatted text (now playing, --info, and listings)
+    #[arg(long)]
+    json: bool,
+
+    /// Print the playing track for a status bar: a waybar custom-module JSON
+    /// object, or one line with --plain. Only asks the player; exits 1 with no
+    /// output when nothing is playing
+    #[arg(long, conflicts_with_all = ["json", "format"])]
+    status: bool,
+
+    /// With --status, print plain text instead of JSON
+    #[arg(long, requires = "status")]
+    plain: bool,
+
+    /// Print the track as one line from a template, e.g. "{artist} - {title}".
+    /// Placeholders: {title} {artist} {album} {duration} {position}
+    /// {popularity} {released} {genres} {plays} {id}
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "json", value_parser = template::parse)]
+    format: Option<String>,
+
+    /// Write the output to this file instead of stdout, without color codes
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Start an interactive prompt for ad-hoc queries (reads commands from stdin)
+    #[arg(long)]
+    repl: bool,
+}
+
+/// How `--watch` shows each new track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+enum WatchFormat {
+    /// The same summary as a single run
+    #[default]
+    Full,
+    /// A "previous → current" line and the new track's key details
+    Compact,
+}
+
+impl Cli {
+    fn list_options(&self) -> db::ListOptions<'_> {
+        db::ListOptions {
+            sort: self.sort.map(|key| db::TrackSort {
+                key,
+                descending: self.desc,
+            }),
+            filter: self.filter.as_ref(),
+            clean_only: self.clean_only,
+        }
+    }
+
+    /// The playback command requested, if any.
+    fn transport(&self) -> Option<spotify::Transport> {
+        [
+            (self.play, spotify::Transport::Play),
+            (self.pause, spotify::Transport::Pause),
+            (self.next, spotify::Transport::Next),
+            (self.prev, spotify::Transport::Previous),
+        ]
+        .into_iter()
+        .find_map(|(requested, command)| requested.then_some(command))
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let (config, db) = initialize(&cli)?;
+    if let Some(path) = &cli.output {
+        output::to_file(path)?;
+    }
+    let result = run(cli).await;
+    // The command's own error matters more than a failed write after it.
+    result.and(output::finish())
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    if cli.list_players {
+        return handle_list_players();
+    }
+    let mut config = load_config(&cli)?;
+    if let Some(player) = &cli.player {
+        config.player.name = player.clone();
+    }
+    // Transport commands are one-shot: no cache, no lyrics.
+    if let Some(command) = cli.transport() {
+        return handle_transport(&config, command);
+    }
+    // So is status-bar output, which runs every few seconds.
+    if cli.status {
+        return handle_status(&config, cli.plain).await;
+    }
+    ratelimit::init(config.network.requests_per_minute);
+    if cli.no_cache {
+        if cli.watch {
+            return handle_watch(&cli, &config, None).await;
+        }
+        return handle_now_playing(&cli, &config, None).await;
+    }
+    let db = open_database(&config)?;
     dispatch(cli, config, db).await
 }
 
-fn initialize(cli: &Cli) -> Result<(config::Config, db::Database)> {
+/// Existing `~/.pb/` installs need no migration: while that directory exists
+/// it stays the config and data directory, so configs pointing into it keep
+/// working.
+fn load_config(cli: &Cli) -> Result<config::Config> {
     config::Config::ensure_app_dir()?;
     let config_path = resolve_config_path(cli)?;
-    let config = config::Config::load(&config_path)?;
-    migrate_database(&config)?;
+    config::Config::load(&config_path)
+}
+
+fn open_database(config: &config::Config) -> Result<db::Database> {
+    migrate_database(config)?;
     let db = db::Database::new(&config.database.path)?;
     db.init()?;
-    Ok((config, db))
+    Ok(db)
 }
 
 fn resolve_config_path(cli: &Cli) -> Result<String> {
     if let Some(path) = &cli.config {
         return Ok(path.clone());
     }
+    if let Some(path) = config::env_path(config::CONFIG_ENV) {
+        return Ok(path);
+    }
 
     let default_path = config::Config::get_default_config_path()?;
     let old_config = std::path::PathBuf::from("config.toml");
@@ -98,18 +402,106 @@ fn migrate_database(config: &config::Config) -> Result<()> {
 
 async fn dispatch(cli: Cli, config: config::Config, db: db::Database) -> Result<()> {
     if cli.browse {
-        return tui::run(db);
+        return tui::run(db, &config);
     }
     if cli.count {
         return handle_count(&db);
     }
+    if cli.stats {
+        return handle_stats(&db);
+    }
+    if let Some(track_id) = &cli.open_genius {
+        return handle_open_genius(&db, track_id);
+    }
+    if cli.art {
+        return handle_art(&db, &config).await;
+    }
+    if let Some(path) = &cli.merge_db {
+        return handle_merge(&db, path, cli.on_conflict);
+    }
+    if let Some(track_id) = &cli.info {
+        return handle_info(&db, track_id, &cli, &config);
+    }
+    if let Some(track_id) = &cli.lookup {
+        return handle_lookup(&cli, &config, &db, track_id).await;
+    }
     if let Some(query) = &cli.search {
-        return handle_search(&db, query).await;
+        return handle_search(
+            &db,
+            query,
+            cli.limit,
+            &cli.list_options(),
+            &config,
+            cli.json,
+        )
+        .await;
+    }
+    if let Some(phrase) = &cli.lyrics_search {
+        return handle_lyrics_search(&db, phrase, cli.json);
     }
-    if cli.recent {
-        return handle_recent(&db);
+    if let Some(name) = &cli.artist {
+        return handle_artist(&db, name, cli.json);
+    }
+    if let Some(name) = &cli.album {
+        return handle_album(&db, name, cli.json);
+    }
+    if let Some(limit) = cli.recent {
+        return handle_recent(&db, limit, &cli.list_options(), cli.json);
+    }
+    if cli.top {
+        return handle_top(&db, &cli.list_options(), cli.json);
+    }
+    if cli.favorites {
+        return handle_favorites(&db, &cli.list_options(), cli.json);
+    }
+    if cli.reindex {
+        return handle_reindex(&db);
+    }
+    if cli.dedupe {
+        return handle_dedupe(&db, cli.dry_run);
+    }
+    if let Some(path) = &cli.export_csv {
+        return handle_export_csv(&db, path, &cli.list_options(), !cli.no_lyrics);
+    }
+    if let Some(path) = &cli.export_json {
+        return handle_export_json(&db, path, &cli.list_options(), !cli.no_lyrics);
+    }
+    if let Some(path) = &cli.export_public {
+        return handle_export_json(&db, path, &cli.list_options(), false);
+    }
+    if let Some(path) = &cli.import_json {
+        return handle_import_json(&db, path);
+    }
+    if let Some(query) = &cli.delete {
+        return handle_delete(&db, query);
+    }
+    if let Some(query) = &cli.favorite {
+        return handle_favorite(&db, query);
+    }
+    if cli.purge_lyrics {
+        return handle_purge_lyrics(&db, &config, &cli.list_options(), cli.vacuum);
+    }
+    if cli.filter.is_some() || cli.clean_only {
+        return handle_list(&db, &cli.list_options(), cli.json);
+    }
+    if cli.repl {
+        return handle_repl(&cli, &config, &db).await;
+    }
+
+    if cli.watch {
+        return handle_watch(&cli, &config, Some(&db)).await;
+    }
+
+    // Flags that only make sense for the now-playing view imply it.
+    let now_playing_flags =
+        cli.refresh || cli.raw_lyrics || cli.no_lyrics || cli.write_file.is_some();
+    match config.default_action {
+        config::DefaultAction::Browse if !now_playing_flags => tui::run(db, &config),
+        config::DefaultAction::Recent if !now_playing_flags => {
+            handle_recent(&db, DEFAULT_RECENT, &cli.list_options(), cli.json)
+        }
+        _ => handle_now_playing(&cli, &config, Some(&db)).await,
     }
-    handle_now_playing(cli, config, db).await
 }
 
 fn handle_count(db: &db::Database) -> Result<()> {
@@ -126,22 +518,384 @@ fn handle_count(db: &db::Database) -> Result<()> {
         _ => "LEGENDARY STATUS! Your music collection is epic!",
     };
 
-    println!("🎵 Total tracks in database: {}", count);
-    println!("🎉 {}", celebration);
+    outln!("🎵 Total tracks in database: {}", count);
+    outln!("🎉 {}", celebration);
+
+    Ok(())
+}
+
+fn handle_stats(db: &db::Database) -> Result<()> {
+    let stats = db.stats()?;
+    if stats.tracks == 0 {
+        outln!("📊 No tracks cached yet.");
+        return Ok(());
+    }
+
+    let minutes = stats.total_duration_ms / 60000;
+    outln!("📊 Library stats");
+    outln!("  Tracks:          {}", stats.tracks);
+    outln!("  Artists:         {}", stats.artists);
+    outln!("  Albums:          {}", stats.albums);
+    if let Some(popularity) = stats.average_popularity {
+        outln!("  Avg popularity:  {:.1}/100", popularity);
+    }
+    outln!("  Listening time:  {}h {:02}m", minutes / 60, minutes % 60);
+    if let (Some(oldest), Some(newest)) = (&stats.oldest_release, &stats.newest_release) {
+        outln!("  Releases:        {} – {}", oldest, newest);
+    }
+    outln!("  Top artists:");
+    for (i, (artist, tracks)) in stats.top_artists.iter().enumerate() {
+        let noun = if *tracks == 1 { "track" } else { "tracks" };
+        outln!("    {}. {} ({} {})", i + 1, artist, tracks, noun);
+    }
+    Ok(())
+}
+
+fn handle_open_genius(db: &db::Database, track_id: &str) -> Result<()> {
+    let track = db
+        .get_track_info(track_id)?
+        .with_context(|| format!("No cached track with ID '{}'", track_id))?;
+    let url = lyrics::genius_page_url(&track);
+    outln!("🌐 Opening {}", url);
+    browser::open(&url)
+}
+
+/// Print the cover image URL of the playing track, from the player or else
+/// the cache, and open it when stdout is a terminal.
+async fn handle_art(db: &db::Database, config: &config::Config) -> Result<()> {
+    let track = spotify::SpotifyClient::new(&config.player)?
+        .get_current_track()
+        .await?;
+    let url = match track.album_art_url {
+        Some(url) => url,
+        None => db
+            .get_track_info(&track.track_id)?
+            .and_then(|cached| cached.album_art_url)
+            .with_context(|| {
+                format!(
+                    "No album art known for '{}' by '{}'",
+                    track.track_name, track.artist_name
+                )
+            })?,
+    };
+    outln!("{}", url);
+    if std::io::stdout().is_terminal() {
+        browser::open(&url)?;
+    }
+    Ok(())
+}
+
+fn handle_list_players() -> Result<()> {
+    let players = spotify::list_players()?;
+    if players.is_empty() {
+        outln!("No MPRIS players are running.");
+        return Ok(());
+    }
+    outln!("🎧 Running players (use with --player or player.name):");
+    for player in players {
+        outln!("   {}", player);
+    }
+    Ok(())
+}
+
+fn handle_transport(config: &config::Config, command: spotify::Transport) -> Result<()> {
+    spotify::SpotifyClient::new(&config.player)?.send_command(command)?;
+    let done = match command {
+        spotify::Transport::Play => "▶️  Playing",
+        spotify::Transport::Pause => "⏸️  Paused",
+        spotify::Transport::Next => "⏭️  Skipped to the next track",
+        spotify::Transport::Previous => "⏮️  Back to the previous track",
+    };
+    outln!("{}", done);
+    Ok(())
+}
+
+async fn handle_status(config: &config::Config, plain: bool) -> Result<()> {
+    let playback = match spotify::SpotifyClient::new(&config.player) {
+        Ok(client) => client.get_playback().await.ok(),
+        Err(_) => None,
+    };
+    // Status bars hide the module on a failed run with no output.
+    let Some(playback) = playback.filter(|p| p.status != spotify::PlaybackStatus::Stopped) else {
+        std::process::exit(1);
+    };
+
+    let track = &playback.track;
+    let text = format!("{} – {}", track.artist_name, track.track_name);
+    if plain {
+        outln!("{}", text);
+        return Ok(());
+    }
+
+    let mut tooltip = vec![track.track_name.clone(), track.artist_name.clone()];
+    if !track.album_name.is_empty() {
+        tooltip.push(track.album_name.clone());
+    }
+    tooltip.push(match playback.position_ms {
+        Some(position_ms) => format!(
+            "{} / {}",
+            format_minutes(position_ms),
+            format_minutes(track.duration_ms)
+        ),
+        None => format_minutes(track.duration_ms),
+    });
+    let class = match playback.status {
+        spotify::PlaybackStatus::Paused => "paused",
+        _ => "playing",
+    };
+    outln!(
+        "{}",
+        serde_json::json!({
+            "text": text,
+            "tooltip": tooltip.join("\n"),
+            "class": class,
+            "alt": class,
+        })
+    );
+    Ok(())
+}
+
+fn handle_merge(db: &db::Database, path: &str, on_conflict: db::OnConflict) -> Result<()> {
+    let stats = db.merge_from(path, on_conflict)?;
+    outln!(
+        "🔀 Merged {}: {} added, {} updated, {} skipped",
+        path,
+        stats.added,
+        stats.updated,
+        stats.skipped
+    );
+    Ok(())
+}
 
+fn handle_reindex(db: &db::Database) -> Result<()> {
+    let stats = db.reindex(|step| outln!("🔧 {}...", step))?;
+    outln!(
+        "✅ Reindex complete: {} lyric heading(s) stripped, {} duplicate(s) merged",
+        stats.headers_stripped,
+        stats.duplicates_removed
+    );
     Ok(())
 }
 
-async fn handle_search(db: &db::Database, query: &str) -> Result<()> {
-    let results = db.search_tracks(query)?;
+fn handle_dedupe(db: &db::Database, dry_run: bool) -> Result<()> {
+    let groups = if dry_run {
+        db.find_duplicates()?
+    } else {
+        db.merge_duplicates()?
+    };
+    if groups.is_empty() {
+        outln!("No duplicate tracks found");
+        return Ok(());
+    }
+
+    for group in &groups {
+        outln!(
+            "'{}' by '{}': keeping {}",
+            group.keep.track_name,
+            group.keep.artist_name,
+            group.keep.track_id
+        );
+        for track in &group.remove {
+            outln!(
+                "  - {} ('{}' by '{}', {} play(s){})",
+                track.track_id,
+                track.track_name,
+                track.artist_name,
+                track.play_count,
+                if track.lyrics.is_some() {
+                    ""
+                } else {
+                    ", no lyrics"
+                }
+            );
+        }
+    }
+
+    let removed: usize = groups.iter().map(|group| group.remove.len()).sum();
+    if dry_run {
+        outln!(
+            "\n🔍 Would remove {} duplicate(s) of {} song(s); run without --dry-run to merge them",
+            removed,
+            groups.len()
+        );
+    } else {
+        outln!(
+            "\n✅ Removed {} duplicate(s) of {} song(s), keeping their plays and stars",
+            removed,
+            groups.len()
+        );
+    }
+    Ok(())
+}
+
+fn handle_export_csv(
+    db: &db::Database,
+    path: &str,
+    options: &db::ListOptions<'_>,
+    with_lyrics: bool,
+) -> Result<()> {
+    let tracks = db.list_tracks(options)?;
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path))?;
+    export::write_csv(&tracks, with_lyrics, std::io::BufWriter::new(file))
+        .with_context(|| format!("Failed to write {}", path))?;
+    outln!("📤 Exported {} track(s) to {}", tracks.len(), path);
+    Ok(())
+}
+
+fn handle_export_json(
+    db: &db::Database,
+    path: &str,
+    options: &db::ListOptions<'_>,
+    with_lyrics: bool,
+) -> Result<()> {
+    let tracks = db.list_tracks(options)?;
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path))?;
+    export::write_json(&tracks, with_lyrics, std::io::BufWriter::new(file))
+        .with_context(|| format!("Failed to write {}", path))?;
+    outln!("📤 Exported {} track(s) to {}", tracks.len(), path);
+    Ok(())
+}
+
+fn handle_import_json(db: &db::Database, path: &str) -> Result<()> {
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path))?;
+    let tracks = export::read_json(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to import {}", path))?;
+
+    let stats = db.import_tracks(&tracks)?;
+    outln!(
+        "📥 Imported {}: {} inserted, {} replaced, {} merged",
+        path,
+        stats.inserted,
+        stats.replaced,
+        stats.merged
+    );
+    Ok(())
+}
+
+/// Find the cached track `query` refers to. A single match is returned
+/// directly; several are listed and the user picks one by number (`verb`
+/// completes the prompt, e.g. "Delete"). `None` if nothing matched or the
+/// pick was cancelled.
+fn pick_track(db: &db::Database, query: &str, verb: &str) -> Result<Option<db::TrackInfo>> {
+    let mut matches = db.search_tracks(query)?;
+    match matches.len() {
+        0 => {
+            outln!("No cached tracks match '{}'.", query);
+            return Ok(None);
+        }
+        1 => return Ok(matches.pop()),
+        _ => {}
+    }
+
+    println!("🔍 {} tracks match '{}':\n", matches.len(), query);
+    for (i, track) in matches.iter().enumerate() {
+        println!(
+            "{}. {}{} by {} ({})",
+            i + 1,
+            track.track_name,
+            explicit_mark(track),
+            track.artist_name,
+            track.album_name
+        );
+    }
+    print!(
+        "\n{} which one? [1-{}, Enter to cancel] ",
+        verb,
+        matches.len()
+    );
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    match answer.trim().parse::<usize>() {
+        Ok(n) if (1..=matches.len()).contains(&n) => Ok(Some(matches.swap_remove(n - 1))),
+        _ => {
+            println!("Cancelled.");
+            Ok(None)
+        }
+    }
+}
+
+fn handle_delete(db: &db::Database, query: &str) -> Result<()> {
+    let Some(track) = pick_track(db, query, "Delete")? else {
+        return Ok(());
+    };
+    if db.delete_track(&track.track_id)? {
+        outln!(
+            "🗑️  Deleted '{}' by '{}' ({})",
+            track.track_name,
+            track.artist_name,
+            track.track_id
+        );
+    }
+    Ok(())
+}
+
+fn handle_favorite(db: &db::Database, query: &str) -> Result<()> {
+    let Some(track) = pick_track(db, query, "Star or unstar")? else {
+        return Ok(());
+    };
+    let favorite = !track.favorite;
+    db.set_favorite(&track.track_id, favorite)?;
+    if favorite {
+        outln!(
+            "★ Starred '{}' by '{}'",
+            track.track_name,
+            track.artist_name
+        );
+    } else {
+        outln!(
+            "☆ Unstarred '{}' by '{}'",
+            track.track_name,
+            track.artist_name
+        );
+    }
+    Ok(())
+}
+
+fn handle_purge_lyrics(
+    db: &db::Database,
+    config: &config::Config,
+    options: &db::ListOptions<'_>,
+    vacuum: bool,
+) -> Result<()> {
+    let (purged, bytes) = db.purge_lyrics(options)?;
+    outln!(
+        "🧹 Removed lyrics from {} track(s) ({} KB of text)",
+        purged,
+        bytes / 1024
+    );
+
+    if vacuum {
+        let file_size = || std::fs::metadata(&config.database.path).map(|m| m.len());
+        let before = file_size()?;
+        db.vacuum()?;
+        let freed = before.saturating_sub(file_size()?);
+        outln!("📦 Database compacted, {} KB freed on disk", freed / 1024);
+    }
+    Ok(())
+}
+
+async fn handle_search(
+    db: &db::Database,
+    query: &str,
+    limit: Option<usize>,
+    options: &db::ListOptions<'_>,
+    config: &config::Config,
+    json: bool,
+) -> Result<()> {
+    let results = db.search_tracks_limited(query, limit, options)?;
+    if json {
+        return export::print_tracks(&results);
+    }
 
     if results.is_empty() {
-        println!("No results found for '{}'", query);
+        outln!("No results found for '{}'", query);
         return Ok(());
     }
 
     // Try to get currently playing track (if Spotify is running) to highlight it
-    let current_track_id = match spotify::SpotifyClient::new() {
+    let current_track_id = match spotify::SpotifyClient::new(&config.player) {
         Ok(client) => match client.get_current_track().await {
             Ok(track) => Some(track.track_id),
             Err(_) => None,
@@ -149,119 +903,1077 @@ async fn handle_search(db: &db::Database, query: &str) -> Result<()> {
         Err(_) => None,
     };
 
-    println!("Found {} result(s) for '{}':\n", results.len(), query);
+    outln!("Found {} result(s) for '{}':\n", results.len(), query);
     for (i, track) in results.iter().enumerate() {
         let is_playing = current_track_id.as_ref() == Some(&track.track_id);
 
         if is_playing {
-            println!(
-                "\x1b[1;92m{}. 🎵 {} by {} ⚡ NOW PLAYING ⚡\x1b[0m",
+            let line = format!(
+                "{}. 🎵 {}{} by {} ⚡ NOW PLAYING ⚡",
                 i + 1,
                 track.track_name,
+                explicit_mark(track),
                 track.artist_name
             );
+            if color_enabled() {
+                outln!("{}", config.display.now_playing_style.paint(&line));
+            } else {
+                outln!("{}", line);
+            }
         } else {
-            println!("{}. {} by {}", i + 1, track.track_name, track.artist_name);
+            outln!(
+                "{}. {}{} by {}",
+                i + 1,
+                track.track_name,
+                explicit_mark(track),
+                track.artist_name
+            );
         }
-        println!("   Album: {}", track.album_name);
+        outln!("   Album: {}", track.album_name);
         if !track.release_date.is_empty() {
-            println!("   Released: {}", track.release_date);
+            outln!("   Released: {}", track.release_date);
         }
-        println!();
+        outln!();
     }
 
     Ok(())
 }
 
-fn handle_recent(db: &db::Database) -> Result<()> {
-    let recent_tracks = db.get_recent_tracks(10)?;
+fn handle_lyrics_search(db: &db::Database, phrase: &str, json: bool) -> Result<()> {
+    let results = db.search_lyrics(phrase)?;
+    if json {
+        return export::print_tracks(&results);
+    }
+
+    if results.is_empty() {
+        outln!("No lyrics found matching '{}'", phrase);
+        return Ok(());
+    }
+
+    outln!("Found {} song(s) matching '{}':\n", results.len(), phrase);
+    for (i, track) in results.iter().enumerate() {
+        outln!(
+            "{}. {}{} by {}",
+            i + 1,
+            track.track_name,
+            explicit_mark(track),
+            track.artist_name
+        );
+        if let Some(line) = track
+            .lyrics
+            .as_deref()
+            .and_then(|l| matching_line(l, phrase))
+        {
+            outln!("   \"{}\"", line);
+        }
+        outln!();
+    }
+
+    Ok(())
+}
+
+fn handle_artist(db: &db::Database, name: &str, json: bool) -> Result<()> {
+    let tracks = db.tracks_by_artist(name)?;
+    if json {
+        return export::print_tracks(&tracks);
+    }
+
+    if tracks.is_empty() {
+        outln!("No cached tracks by '{}'", name);
+        return Ok(());
+    }
+
+    outln!("🎤 {} ({} track(s)):", tracks[0].artist_name, tracks.len());
+    for album in tracks.chunk_by(|a, b| a.album_name == b.album_name) {
+        outln!();
+        match album[0].release_date.as_str() {
+            "" => outln!("💿 {}", album[0].album_name),
+            released => outln!("💿 {} ({})", album[0].album_name, released),
+        }
+        for track in album {
+            outln!("   • {}", track.track_name);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_album(db: &db::Database, name: &str, json: bool) -> Result<()> {
+    let tracks = db.tracks_by_album(name)?;
+    if json {
+        return export::print_tracks(&tracks);
+    }
+
+    if tracks.is_empty() {
+        outln!("No cached tracks from '{}'", name);
+        return Ok(());
+    }
+
+    let artists: BTreeSet<&str> = tracks.iter().map(|t| t.artist_name.as_str()).collect();
+    outln!("💿 {}", tracks[0].album_name);
+    outln!(
+        "   By: {}",
+        artists.into_iter().collect::<Vec<_>>().join(", ")
+    );
+    if let Some(released) = tracks
+        .iter()
+        .map(|t| t.release_date.as_str())
+        .find(|date| !date.is_empty())
+    {
+        outln!("   Released: {}", released);
+    }
+    outln!("   {} track(s) cached:\n", tracks.len());
+    // Multi-disc albums number their tracks per disc.
+    let discs = tracks.iter().filter_map(|t| t.disc_number).max() > Some(1);
+    for track in &tracks {
+        match (track.track_number, track.disc_number) {
+            (Some(number), Some(disc)) if discs => {
+                outln!("{}-{:02}. {}", disc, number, track.track_name)
+            }
+            (Some(number), _) => outln!("{:>2}. {}", number, track.track_name),
+            (None, _) => outln!(" • {}", track.track_name),
+        }
+    }
+
+    Ok(())
+}
+
+/// First lyric line containing `phrase`, ignoring case, to show why a
+/// track matched.
+fn matching_line<'a>(lyrics: &'a str, phrase: &str) -> Option<&'a str> {
+    let phrase = phrase.trim().to_lowercase();
+    lyrics
+        .lines()
+        .map(str::trim)
+        .find(|line| line.to_lowercase().contains(&phrase))
+}
+
+/// Tracks listed by a bare `--recent`, the REPL's `recent`, and
+/// `default_action = "recent"`.
+const DEFAULT_RECENT: usize = 10;
+
+/// Most tracks `--recent N` lists; larger counts are cut down to it.
+const MAX_RECENT: usize = 1000;
+
+fn handle_recent(
+    db: &db::Database,
+    limit: usize,
+    options: &db::ListOptions,
+    json: bool,
+) -> Result<()> {
+    let recent_tracks = db.get_recent_tracks(limit.clamp(1, MAX_RECENT), options)?;
+    if json {
+        return export::print_tracks(&recent_tracks);
+    }
 
     if recent_tracks.is_empty() {
-        println!("No recently queried songs found in the database.");
+        outln!("No recently queried songs found in the database.");
         return Ok(());
     }
 
-    println!("📚 Recently Queried Songs:\n");
+    outln!("📚 Recently Queried Songs:\n");
     for (i, track) in recent_tracks.iter().enumerate() {
-        println!("{}. {} by {}", i + 1, track.track_name, track.artist_name);
-        println!("   Album: {}", track.album_name);
+        outln!(
+            "{}. {}{} by {}",
+            i + 1,
+            track.track_name,
+            explicit_mark(track),
+            track.artist_name
+        );
+        outln!("   Album: {}", track.album_name);
         if !track.release_date.is_empty() {
-            println!("   Released: {}", track.release_date);
+            outln!("   Released: {}", track.release_date);
         }
-        println!();
+        outln!();
     }
 
     Ok(())
 }
 
-async fn handle_now_playing(cli: Cli, config: config::Config, db: db::Database) -> Result<()> {
-    let spotify_client = spotify::SpotifyClient::new()?;
-    let track_info = spotify_client.get_current_track().await?;
+fn handle_top(db: &db::Database, options: &db::ListOptions, json: bool) -> Result<()> {
+    let top_tracks = db.get_most_played(10, options)?;
+    if json {
+        return export::print_tracks(&top_tracks);
+    }
 
-    println!(
-        "🎵 Now Playing: {} by {}",
-        track_info.track_name, track_info.artist_name
-    );
+    if top_tracks.is_empty() {
+        outln!("No plays recorded yet.");
+        return Ok(());
+    }
 
+    outln!("🏆 Most Played Songs:\n");
+    for (i, track) in top_tracks.iter().enumerate() {
+        outln!(
+            "{}. {}{} by {}",
+            i + 1,
+            track.track_name,
+            explicit_mark(track),
+            track.artist_name
+        );
+        outln!("   Album: {}", track.album_name);
+        outln!("   Plays: {}", track.play_count);
+        outln!();
+    }
+
+    Ok(())
+}
+
+fn handle_favorites(db: &db::Database, options: &db::ListOptions, json: bool) -> Result<()> {
+    let favorites = db.get_favorites(options)?;
+    if json {
+        return export::print_tracks(&favorites);
+    }
+
+    if favorites.is_empty() {
+        outln!("No starred songs yet (star one with --favorite <query>).");
+        return Ok(());
+    }
+
+    outln!("★ Starred Songs:\n");
+    for (i, track) in favorites.iter().enumerate() {
+        outln!(
+            "{}. {}{} by {}",
+            i + 1,
+            track.track_name,
+            explicit_mark(track),
+            track.artist_name
+        );
+        outln!("   Album: {}", track.album_name);
+        if !track.release_date.is_empty() {
+            outln!("   Released: {}", track.release_date);
+        }
+        outln!();
+    }
+
+    Ok(())
+}
+
+fn handle_list(db: &db::Database, options: &db::ListOptions, json: bool) -> Result<()> {
+    let tracks = db.list_tracks(options)?;
+    if json {
+        return export::print_tracks(&tracks);
+    }
+
+    if tracks.is_empty() {
+        outln!("No tracks match the filter.");
+        return Ok(());
+    }
+
+    outln!("Found {} matching track(s):\n", tracks.len());
+    for (i, track) in tracks.iter().enumerate() {
+        outln!(
+            "{}. {}{} by {}",
+            i + 1,
+            track.track_name,
+            explicit_mark(track),
+            track.artist_name
+        );
+        outln!("   Album: {}", track.album_name);
+        if !track.release_date.is_empty() {
+            outln!("   Released: {}", track.release_date);
+        }
+        outln!();
+    }
+
+    Ok(())
+}
+
+/// A single command entered at the `--repl` prompt.
+#[derive(Debug, PartialEq)]
+enum ReplCommand<'a> {
+    Search(&'a str),
+    Recent,
+    Top,
+    Info(&'a str),
+    Stats,
+    Now,
+    Help,
+    Quit,
+    Empty,
+    Unknown(&'a str),
+}
+
+fn parse_repl_command(line: &str) -> ReplCommand<'_> {
+    let line = line.trim();
+    let (command, arg) = match line.split_once(char::is_whitespace) {
+        Some((command, arg)) => (command, arg.trim()),
+        None => (line, ""),
+    };
+
+    match (command, arg) {
+        ("", _) => ReplCommand::Empty,
+        ("search", query) if !query.is_empty() => ReplCommand::Search(query),
+        ("recent", "") => ReplCommand::Recent,
+        ("top", "") => ReplCommand::Top,
+        ("info", id) if !id.is_empty() => ReplCommand::Info(id),
+        ("stats", "") => ReplCommand::Stats,
+        ("now", "") => ReplCommand::Now,
+        ("help", "") => ReplCommand::Help,
+        ("quit" | "exit", "") => ReplCommand::Quit,
+        _ => ReplCommand::Unknown(line),
+    }
+}
+
+async fn handle_repl(cli: &Cli, config: &config::Config, db: &db::Database) -> Result<()> {
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        println!("playbot REPL — type `help` for commands, `quit` to exit.");
+    }
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            print!("pb> ");
+            std::io::stdout().flush()?;
+        }
+
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+
+        // A failing command shouldn't end the session; report it and keep reading.
+        let result = match parse_repl_command(&line) {
+            ReplCommand::Search(query) => {
+                handle_search(db, query, None, &cli.list_options(), config, cli.json).await
+            }
+            ReplCommand::Recent => handle_recent(db, DEFAULT_RECENT, &cli.list_options(), cli.json),
+            ReplCommand::Top => handle_top(db, &cli.list_options(), cli.json),
+            ReplCommand::Info(track_id) => handle_info(db, track_id, cli, config),
+            ReplCommand::Stats => handle_stats(db),
+            ReplCommand::Now => handle_now_playing(cli, config, Some(db)).await,
+            ReplCommand::Help => {
+                print_repl_help();
+                Ok(())
+            }
+            ReplCommand::Quit => break,
+            ReplCommand::Empty => Ok(()),
+            ReplCommand::Unknown(input) => {
+                outln!("Unknown command: '{}' (type `help` for commands)", input);
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            eprintln!("Error: {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_repl_help() {
+    outln!("Commands:");
+    outln!("  search <query>   Search cached tracks by title, artist, or album");
+    outln!("  recent           Show recently queried tracks");
+    outln!("  top              Show the most played tracks");
+    outln!("  info <track_id>  Show full details for a cached track");
+    outln!("  stats            Show library statistics");
+    outln!("  now              Show the currently playing track");
+    outln!("  quit             Exit (EOF also exits)");
+}
+
+fn handle_info(
+    db: &db::Database,
+    track_id: &str,
+    cli: &Cli,
+    config: &config::Config,
+) -> Result<()> {
+    let info = db
+        .get_track_info(track_id)?
+        .with_context(|| format!("No cached track with ID '{}'", track_id))?;
+    show_track(&info, None, cli, config)
+}
+
+/// Show the track a share link points to, from the cache or fetched from the
+/// Web API with its lyrics and cached. Unlike the now-playing track, it isn't
+/// counted as a play.
+async fn handle_lookup(
+    cli: &Cli,
+    config: &config::Config,
+    db: &db::Database,
+    track_id: &str,
+) -> Result<()> {
+    let decorate = !cli.json && cli.format.is_none();
     if !cli.refresh {
-        if let Some(cached_info) = db.get_track_info(&track_info.track_id)? {
-            println!("\n📦 (Using cached data)\n");
-            print_track_info(&cached_info);
-            return Ok(());
+        if let Some(cached) = db.get_fresh_track_info(track_id, config.cache.ttl_hours)? {
+            if decorate {
+                outln!("🔗 {} by {}", cached.track_name, cached.artist_name);
+                outln!("\n{}\n", TrackSource::Cache.note());
+            }
+            return show_track(&cached, None, cli, config);
         }
     }
 
-    let lyrics_client = lyrics::LyricsClient::new();
-    let lyric_text = lyrics_client
-        .get_lyrics(&track_info.track_name, &track_info.artist_name)
-        .await?;
+    let web = config
+        .spotify
+        .as_ref()
+        .and_then(spotify_web::SpotifyWebClient::new)
+        .context("--lookup needs Spotify Web API credentials in the [spotify] config section")?;
+    let mut track = web.track(track_id).await?;
+    add_genius_credits(config, &mut track).await;
+    if decorate {
+        outln!("🔗 {} by {}", track.track_name, track.artist_name);
+    }
+    let track = with_lyrics(cli, config, Some(db), track, config::ContentPolicy::Cache).await?;
+    db.insert_track_info(&track)?;
+    // Read back for the play count and star it may already have.
+    let track = db.get_track_info(track_id)?.unwrap_or(track);
+    if decorate {
+        outln!("\n{}\n", TrackSource::Fetched.note());
+    }
+    show_track(&track, None, cli, config)
+}
+
+/// Print a single track as JSON or as the formatted summary, with the
+/// player's position in it when it is the current track.
+fn show_track(
+    info: &db::TrackInfo,
+    position_ms: Option<i64>,
+    cli: &Cli,
+    config: &config::Config,
+) -> Result<()> {
+    if cli.json {
+        return export::print_track(info);
+    }
+    if let Some(template) = &cli.format {
+        outln!("{}", template::render(template, info, position_ms));
+        return Ok(());
+    }
+    print_track_info(info, position_ms, &config.display.symbols);
+    Ok(())
+}
+
+/// Show the currently playing track, using and updating the cache unless `db`
+/// is `None` (`--no-cache`).
+async fn handle_now_playing(
+    cli: &Cli,
+    config: &config::Config,
+    db: Option<&db::Database>,
+) -> Result<()> {
+    let spotify_client = spotify::SpotifyClient::new(&config.player)?;
+    let playback = match spotify_client.get_playback().await {
+        Ok(playback) => playback,
+        Err(e) => {
+            // Nothing is playing, so clear any overlay text left over from the
+            // last track. Failing to is secondary to the player's error.
+            if let Some(path) = &cli.write_file {
+                if let Err(write_error) = write_now_playing_file(path, None) {
+                    eprintln!("⚠️  {:#}", write_error);
+                }
+            }
+            return Err(e);
+        }
+    };
+    show_now_playing(cli, config, db, playback).await
+}
+
+/// Poll the player every `player.watch_interval_secs` and show the track
+/// whenever it changes, until Ctrl-C. Errors for one track are reported
+/// without stopping the loop.
+async fn handle_watch(cli: &Cli, config: &config::Config, db: Option<&db::Database>) -> Result<()> {
+    let spotify_client = spotify::SpotifyClient::new(&config.player)?;
+    let interval = config.player.watch_interval_secs.max(1);
+    eprintln!(
+        "👀 Watching for track changes every {}s (Ctrl-C to stop)",
+        interval
+    );
 
-    let full_info = db::TrackInfo {
-        lyrics: Some(lyric_text),
+    // The bell goes with the status messages on stderr, and only to a
+    // terminal, so it never ends up in a log or --output file.
+    let bell = cli.bell && std::io::stderr().is_terminal();
+    let hook = config.hooks.on_track_change.as_deref();
+    if let Some(hook) = hook {
+        template::parse(hook)
+            .map_err(anyhow::Error::msg)
+            .context("Invalid hooks.on_track_change")?;
+    }
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut last_track_id: Option<String> = None;
+    let mut last_title: Option<String> = None;
+    let mut last_status = spotify::PlaybackStatus::Playing;
+    let mut idle = false;
+    loop {
+        let poll = async {
+            match spotify_client.get_playback().await {
+                Ok(playback) if last_track_id.as_ref() != Some(&playback.track.track_id) => {
+                    idle = false;
+                    last_track_id = Some(playback.track.track_id.clone());
+                    last_status = playback.status;
+                    let previous = last_title.replace(playback.track.track_name.clone());
+                    if bell {
+                        eprint!("\x07");
+                    }
+                    if let Some(hook) = hook {
+                        hooks::on_track_change(hook, &playback.track);
+                    }
+                    let shown = match cli.watch_format {
+                        WatchFormat::Full => {
+                            if !cli.json && cli.format.is_none() {
+                                outln!("\n{}", "─".repeat(40));
+                            }
+                            show_now_playing(cli, config, db, playback).await
+                        }
+                        WatchFormat::Compact => resolve_now_playing(cli, config, db, playback)
+                            .await
+                            .map(|(track, _)| {
+                                outln!("{}", compact_change(previous.as_deref(), &track))
+                            }),
+                    };
+                    if let Err(e) = shown {
+                        eprintln!("⚠️  {:#}", e);
+                    }
+                }
+                // Same track, but paused, resumed, or stopped.
+                Ok(playback) if playback.status != last_status => {
+                    let was_stopped = last_status == spotify::PlaybackStatus::Stopped;
+                    last_status = playback.status;
+                    eprintln!("{}", status_label(playback.status));
+                    if let Some(path) = &cli.write_file {
+                        let result = if playback.status == spotify::PlaybackStatus::Stopped {
+                            write_now_playing_file(path, None)
+                        } else if was_stopped {
+                            write_now_playing_file(path, Some(&playback.track))
+                        } else {
+                            Ok(())
+                        };
+                        if let Err(e) = result {
+                            eprintln!("⚠️  {:#}", e);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if !idle => {
+                    idle = true;
+                    last_track_id = None;
+                    last_title = None;
+                    if let Some(path) = &cli.write_file {
+                        if let Err(e) = write_now_playing_file(path, None) {
+                            eprintln!("⚠️  {:#}", e);
+                        }
+                    }
+                    eprintln!("⏸️  {:#}", e);
+                }
+                Err(_) => {}
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        };
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = poll => {}
+        }
+    }
+
+    eprintln!("\n👋 Stopped watching");
+    Ok(())
+}
+
+/// The line `--watch-format compact` prints for a new track: what changed,
+/// then the artist, album, and length.
+fn compact_change(previous: Option<&str>, track: &db::TrackInfo) -> String {
+    let mut line = match previous {
+        Some(previous) => format!("⏭️  {} → {}", previous, track.track_name),
+        None => format!("🎵 {}", track.track_name),
+    };
+    line.push_str(&format!(" · {}", track.artist_name));
+    if !track.album_name.is_empty() {
+        line.push_str(&format!(" · {}", track.album_name));
+    }
+    if track.duration_ms > 0 {
+        line.push_str(&format!(" · {}", format_minutes(track.duration_ms)));
+    }
+    line
+}
+
+/// Header for the player's current track in each playback state.
+fn status_label(status: spotify::PlaybackStatus) -> &'static str {
+    match status {
+        spotify::PlaybackStatus::Playing => "🎵 Now Playing",
+        spotify::PlaybackStatus::Paused => "⏸️  Paused",
+        spotify::PlaybackStatus::Stopped => "⏹️  Stopped",
+    }
+}
+
+/// Show the player's current track: update the overlay file and play log,
+/// then show it from the cache or fetch and store its lyrics.
+async fn show_now_playing(
+    cli: &Cli,
+    config: &config::Config,
+    db: Option<&db::Database>,
+    playback: spotify::Playback,
+) -> Result<()> {
+    // JSON and --format output are only the track itself, so they stay
+    // parseable.
+    let decorate = !cli.json && cli.format.is_none();
+    if decorate {
+        let kind = spotify::ContentKind::from_track_id(&playback.track.track_id);
+        outln!(
+            "{}: {} by {}{}",
+            status_label(playback.status),
+            playback.track.track_name,
+            playback.track.artist_name,
+            kind.label()
+                .map(|label| format!(" ({})", label))
+                .unwrap_or_default()
+        );
+    }
+
+    let position_ms = playback.position_ms;
+    let via = playback.source.clone();
+    let (track, source) = resolve_now_playing(cli, config, db, playback).await?;
+    if decorate {
+        outln!("\n{} · via {}\n", source.note(), via);
+    }
+    show_track(&track, position_ms, cli, config)
+}
+
+/// Where the track shown for the player came from.
+enum TrackSource {
+    /// Shown as the player reported it; its content kind isn't cached.
+    NotCached,
+    Cache,
+    Fetched,
+}
+
+impl TrackSource {
+    fn note(&self) -> &'static str {
+        match self {
+            Self::NotCached => "⏭️  (Not cached)",
+            Self::Cache => "📦 (Using cached data)",
+            Self::Fetched => "✨ Fresh data fetched!",
+        }
+    }
+}
+
+/// Update the overlay file and play log for the player's current track, then
+/// return it from the cache, or fetched with its lyrics and stored.
+async fn resolve_now_playing(
+    cli: &Cli,
+    config: &config::Config,
+    db: Option<&db::Database>,
+    playback: spotify::Playback,
+) -> Result<(db::TrackInfo, TrackSource)> {
+    let track_info = playback.track;
+    if let Some(path) = &cli.write_file {
+        // A stopped player's track isn't on air, so the overlay is cleared.
+        let on_air = playback.status != spotify::PlaybackStatus::Stopped;
+        write_now_playing_file(path, Some(&track_info).filter(|_| on_air))?;
+    }
+
+    let kind = spotify::ContentKind::from_track_id(&track_info.track_id);
+    let policy = match kind {
+        spotify::ContentKind::Track => config::ContentPolicy::Cache,
+        spotify::ContentKind::LocalFile => config.player.local_files,
+        spotify::ContentKind::Episode => config.player.podcasts,
+    };
+
+    let skip = policy == config::ContentPolicy::Skip;
+    if let (Some(scrobble_log), false) = (&config.scrobble_log, skip) {
+        log_play(&scrobble_log.path, &track_info);
+    }
+
+    if skip {
+        return Ok((track_info, TrackSource::NotCached));
+    }
+
+    if let (Some(db), false) = (db, cli.refresh) {
+        if let Some(mut cached_info) =
+            db.get_fresh_track_info(&track_info.track_id, config.cache.ttl_hours)?
+        {
+            cached_info.play_count = db.record_play(&cached_info.track_id)?;
+            return Ok((cached_info, TrackSource::Cache));
+        }
+    }
+
+    let mut track_info = track_info;
+    if let (Some(web), spotify::ContentKind::Track) = (
+        config
+            .spotify
+            .as_ref()
+            .and_then(spotify_web::SpotifyWebClient::new),
+        kind,
+    ) {
+        // The local data is still worth showing and caching on its own.
+        if let Err(e) = web
+            .enrich(&mut track_info, config.lyrics.min_match_confidence)
+            .await
+        {
+            eprintln!(
+                "⚠️  Spotify Web API lookup failed, using local data: {:#}",
+                e
+            );
+        }
+    }
+    if kind == spotify::ContentKind::Track {
+        add_genius_credits(config, &mut track_info).await;
+    }
+
+    let mut full_info = with_lyrics(cli, config, db, track_info, policy).await?;
+    if let Some(db) = db {
+        db.insert_track_info(&full_info)?;
+        full_info.play_count = db.record_play(&full_info.track_id)?;
+    }
+    Ok((full_info, TrackSource::Fetched))
+}
+
+/// Fill in `track`'s producers and writers from the Genius API, when a
+/// `[genius]` access token is configured (see
+/// [`genius_api::GeniusApiClient::from_config`]). A failed lookup only costs
+/// the credits.
+async fn add_genius_credits(config: &config::Config, track: &mut db::TrackInfo) {
+    let Some(genius) = genius_api::GeniusApiClient::from_config(config) else {
+        return;
+    };
+    if let Err(e) = genius
+        .enrich(track, config.lyrics.min_match_confidence)
+        .await
+    {
+        eprintln!("⚠️  Genius API lookup failed, no song credits: {:#}", e);
+    }
+}
+
+/// Look up lyrics for `track` as its content policy and the flags allow,
+/// falling back to the ones cached for it if there is no lookup or it fails.
+async fn with_lyrics(
+    cli: &Cli,
+    config: &config::Config,
+    db: Option<&db::Database>,
+    track_info: db::TrackInfo,
+    policy: config::ContentPolicy,
+) -> Result<db::TrackInfo> {
+    // Without a lookup, the track keeps the lyrics it had cached, if any.
+    let cached_lyrics = || -> Result<StoredLyrics> {
+        Ok(db
+            .map(|db| db.get_track_info(&track_info.track_id))
+            .transpose()?
+            .flatten()
+            .map_or_else(StoredLyrics::default, |cached| StoredLyrics {
+                text: cached.lyrics,
+                confidence: cached.lyrics_confidence,
+                synced: cached.synced_lyrics,
+                genius_url: cached.genius_url,
+                warnings: Vec::new(),
+            }))
+    };
+    let stored = match policy {
+        config::ContentPolicy::NoLyrics => StoredLyrics::default(),
+        _ if cli.no_lyrics || config.lyrics.provider == config::LyricsSource::None => {
+            cached_lyrics()?
+        }
+        // A failed lookup shouldn't cost the rest of the track info.
+        _ => match fetch_lyrics(cli, config, &track_info).await {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                eprintln!(
+                    "⚠️  {:#}; continuing without new lyrics (--refresh tries again)",
+                    e
+                );
+                cached_lyrics()?
+            }
+        },
+    };
+
+    // The Genius API's match, if it made one, outranks the lyrics search's.
+    Ok(db::TrackInfo {
+        lyrics: stored.text,
+        lyrics_confidence: stored.confidence,
+        synced_lyrics: stored.synced,
+        genius_url: track_info.genius_url.clone().or(stored.genius_url),
         ..track_info
+    })
+}
+
+/// Fetch and vet lyrics for `track`, returning the text worth storing (if
+/// any), the match confidence, and the synced lyrics that go with the text.
+async fn fetch_lyrics(
+    cli: &Cli,
+    config: &config::Config,
+    track: &db::TrackInfo,
+) -> Result<StoredLyrics> {
+    let Some(provider) = lyrics::Provider::from_config(&config.lyrics) else {
+        return Ok(StoredLyrics::default());
     };
+    let found = provider
+        .get_lyrics(&track.track_name, &track.artist_name)
+        .await?;
+
+    if cli.raw_lyrics {
+        match &found.raw {
+            Some(raw) => eprintln!("--- raw lyrics ---\n{}\n--- end raw lyrics ---", raw),
+            None => eprintln!("--- raw lyrics: no match returned ---"),
+        }
+    }
+
+    // The REPL owns stdin and watch mode runs unattended, so neither prompts.
+    let interactive = !cli.repl
+        && !cli.watch
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal();
+    let stored = lyrics::vet_lyrics(
+        found,
+        &config.lyrics,
+        config.cache.max_lyrics_bytes,
+        |found, confidence| {
+            interactive
+                .then(|| confirm_lyrics_match(track, found, confidence))
+                .transpose()
+        },
+    )?;
+    for warning in &stored.warnings {
+        eprintln!("{}", warning);
+    }
+    Ok(stored)
+}
 
-    db.insert_track_info(&full_info)?;
+/// Ask whether an uncertain lyrics match is the right song. Defaults to no.
+fn confirm_lyrics_match(
+    track: &db::TrackInfo,
+    lyrics: &lyrics::Lyrics,
+    confidence: f64,
+) -> Result<bool> {
+    eprintln!(
+        "🤔 Lyrics for '{}' by '{}' matched '{}' (confidence {:.0}%).",
+        track.track_name,
+        track.artist_name,
+        lyrics.matched_song.as_deref().unwrap_or("an unknown song"),
+        confidence * 100.0
+    );
+    eprint!("   Store these lyrics? [y/N] ");
+    std::io::stderr().flush()?;
 
-    println!("\n✨ Fresh data fetched!\n");
-    print_track_info(&full_info);
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    let accepted = matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+    if !accepted {
+        eprintln!("   Not storing lyrics");
+    }
+    Ok(accepted)
+}
+
+/// Whether to emit color escape codes: only to a terminal, so pipes and
+/// pagers get clean text, and never with `NO_COLOR` set
+/// (<https://no-color.org>).
+fn color_enabled() -> bool {
+    std::io::stdout().is_terminal()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+/// Append the play to the scrobble log. Failures are reported but never stop
+/// the track from being shown.
+fn log_play(path: &str, track: &db::TrackInfo) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    if let Err(e) = scrobble::record_play(std::path::Path::new(path), track, timestamp) {
+        eprintln!("⚠️  Could not write scrobble log: {:#}", e);
+    }
+}
+
+/// Atomically replace the contents of `path` with "Title - Artist", or empty it
+/// when nothing is playing.
+///
+/// Writes to a sibling temp file and renames it into place so readers such as
+/// OBS never observe a half-written file.
+fn write_now_playing_file(path: &str, track: Option<&db::TrackInfo>) -> Result<()> {
+    let contents = match track {
+        Some(track) => format!("{} - {}", track.track_name, track.artist_name),
+        None => String::new(),
+    };
 
-    // Suppress unused variable warning when config has no runtime-used fields
-    let _ = config;
+    let mut tmp_path = std::ffi::OsString::from(path);
+    tmp_path.push(".tmp");
+    std::fs::write(&tmp_path, contents)
+        .with_context(|| format!("Failed to write {}", tmp_path.to_string_lossy()))?;
+    std::fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path))?;
 
     Ok(())
 }
 
-fn print_track_info(info: &db::TrackInfo) {
-    println!("📀 Track: {}", info.track_name);
-    println!("👤 Artist: {}", info.artist_name);
-    println!("💿 Album: {}", info.album_name);
+/// Cells in the progress bar drawn next to the playing track's duration.
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// A length of time as `m:ss`.
+fn format_minutes(ms: i64) -> String {
+    format!("{}:{:02}", ms / 60000, (ms % 60000) / 1000)
+}
+
+/// An ASCII bar filled in proportion to how far `position_ms` is through
+/// `duration_ms`, e.g. `[#####---------------]`.
+fn progress_bar(position_ms: i64, duration_ms: i64) -> String {
+    let fraction = (position_ms as f64 / duration_ms as f64).clamp(0.0, 1.0);
+    let filled = (fraction * PROGRESS_BAR_WIDTH as f64).round() as usize;
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        "-".repeat(PROGRESS_BAR_WIDTH - filled)
+    )
+}
+
+/// Marker after the title of a track the Web API marks explicit.
+fn explicit_mark(track: &db::TrackInfo) -> &'static str {
+    if track.explicit {
+        " 🅴"
+    } else {
+        ""
+    }
+}
+
+fn print_track_info(info: &db::TrackInfo, position_ms: Option<i64>, symbols: &config::Symbols) {
+    let label = |symbol: &str, name: &str| {
+        if symbol.is_empty() {
+            name.to_string()
+        } else {
+            format!("{} {}", symbol, name)
+        }
+    };
+
+    let star = if info.favorite { " ★" } else { "" };
+    outln!(
+        "{}: {}{}{}",
+        label(&symbols.track, "Track"),
+        info.track_name,
+        explicit_mark(info),
+        star
+    );
+    outln!("{}: {}", label(&symbols.artist, "Artist"), info.artist_name);
+    outln!("{}: {}", label(&symbols.album, "Album"), info.album_name);
 
     if !info.release_date.is_empty() {
-        println!("📅 Release Date: {}", info.release_date);
+        outln!(
+            "{}: {}",
+            label(&symbols.release_date, "Release Date"),
+            info.release_date
+        );
     }
 
-    println!(
-        "⏱️  Duration: {}:{:02}",
-        info.duration_ms / 60000,
-        (info.duration_ms % 60000) / 1000
+    match position_ms {
+        Some(position) if info.duration_ms > 0 => outln!(
+            "{}: {} / {} {}",
+            label(&symbols.duration, "Duration"),
+            format_minutes(position),
+            format_minutes(info.duration_ms),
+            progress_bar(position, info.duration_ms)
+        ),
+        _ => outln!(
+            "{}: {}",
+            label(&symbols.duration, "Duration"),
+            format_minutes(info.duration_ms)
+        ),
+    }
+    outln!(
+        "{}: {}/100",
+        label(&symbols.popularity, "Popularity"),
+        info.popularity
     );
-    println!("⭐ Popularity: {}/100", info.popularity);
 
     if !info.genres.is_empty() {
-        println!("🎸 Genres: {}", info.genres);
+        outln!("{}: {}", label(&symbols.genres, "Genres"), info.genres);
     }
 
     if !info.producers.is_empty() {
-        println!("🎛️  Producers: {}", info.producers);
+        outln!(
+            "{}: {}",
+            label(&symbols.producers, "Producers"),
+            info.producers
+        );
     }
 
     if !info.writers.is_empty() {
-        println!("✍️  Writers: {}", info.writers);
+        outln!("{}: {}", label(&symbols.writers, "Writers"), info.writers);
+    }
+
+    if let Some(confidence) = info.lyrics_confidence {
+        outln!(
+            "{}: {:.0}%",
+            label(&symbols.lyrics_match, "Lyrics Match"),
+            confidence * 100.0
+        );
+    }
+
+    if info.play_count > 0 {
+        outln!("{}: {}", label(&symbols.plays, "Plays"), info.play_count);
+    }
+
+    if let Some(cached_at) = &info.cached_at {
+        outln!(
+            "{}: {} UTC",
+            label(&symbols.cached, "Cached"),
+            db::format_cached_at(cached_at)
+        );
     }
 
     if let Some(lyrics) = &info.lyrics {
-        println!("\n📝 Lyrics:\n");
-        println!("{}", lyrics);
+        outln!("\n{}:\n", label(&symbols.lyrics, "Lyrics"));
+        outln!("{}", lyrics);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_bar_tracks_position() {
+        assert_eq!(format_minutes(83_000), "1:23");
+        assert_eq!(format_minutes(225_000), "3:45");
+        assert_eq!(progress_bar(0, 225_000), format!("[{}]", "-".repeat(20)));
+        assert_eq!(
+            progress_bar(112_500, 225_000),
+            format!("[{}{}]", "#".repeat(10), "-".repeat(10))
+        );
+        // Players can report a position just past the end.
+        assert_eq!(
+            progress_bar(230_000, 225_000),
+            format!("[{}]", "#".repeat(20))
+        );
+    }
+
+    #[test]
+    fn parse_repl_commands_with_arguments() {
+        assert_eq!(
+            parse_repl_command("search  karma police "),
+            ReplCommand::Search("karma police")
+        );
+        assert_eq!(
+            parse_repl_command("info spotify:track:abc"),
+            ReplCommand::Info("spotify:track:abc")
+        );
+    }
+
+    #[test]
+    fn parse_repl_commands_without_arguments() {
+        assert_eq!(parse_repl_command("recent"), ReplCommand::Recent);
+        assert_eq!(parse_repl_command("top"), ReplCommand::Top);
+        assert_eq!(parse_repl_command("  stats"), ReplCommand::Stats);
+        assert_eq!(parse_repl_command("exit"), ReplCommand::Quit);
+        assert_eq!(parse_repl_command(""), ReplCommand::Empty);
+    }
+
+    #[test]
+    fn parse_repl_rejects_missing_or_extra_arguments() {
+        assert_eq!(parse_repl_command("search"), ReplCommand::Unknown("search"));
+        assert_eq!(
+            parse_repl_command("recent 5"),
+            ReplCommand::Unknown("recent 5")
+        );
+    }
+
+    #[test]
+    fn compact_watch_line_shows_the_change() {
+        let track = db::TrackInfo {
+            album_name: "OK Computer".to_string(),
+            duration_ms: 264_000,
+            ..db::TrackInfo::sample("spotify:track:abc", "Karma Police", "Radiohead")
+        };
+        assert_eq!(
+            compact_change(Some("Creep"), &track),
+            "⏭️  Creep → Karma Police · Radiohead · OK Computer · 4:24"
+        );
+        let bare = db::TrackInfo::sample("x", "Intro", "Someone");
+        assert_eq!(compact_change(None, &bare), "🎵 Intro · Someone");
     }
 }
@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::{Mutex, MutexGuard};
+
+/// Where command output goes: stdout, or the file named by `--output`.
+///
+/// A failed write is remembered rather than panicking like `println!`, later
+/// writes are dropped, and [`finish`] reports it.
+struct Output {
+    file: Option<File>,
+    error: Option<io::Error>,
+}
+
+static OUTPUT: Mutex<Output> = Mutex::new(Output {
+    file: None,
+    error: None,
+});
+
+fn lock() -> MutexGuard<'static, Output> {
+    OUTPUT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Send all further output to `path`, replacing the file if it exists.
+pub fn to_file(path: &str) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path))?;
+    lock().file = Some(file);
+    Ok(())
+}
+
+/// Write formatted text to the output. Color codes are stripped from text
+/// going to a file. Use through [`out!`] and [`outln!`].
+pub fn write(args: fmt::Arguments) {
+    let mut output = lock();
+    if output.error.is_some() {
+        return;
+    }
+    let result = match &mut output.file {
+        Some(file) => file.write_all(strip_ansi(&args.to_string()).as_bytes()),
+        None => io::stdout().lock().write_fmt(args),
+    };
+    if let Err(e) = result {
+        output.error = Some(e);
+    }
+}
+
+/// Flush the output and report the first write that failed, if any.
+pub fn finish() -> Result<()> {
+    let mut output = lock();
+    let flushed = match &mut output.file {
+        Some(file) => file.flush(),
+        None => io::stdout().flush(),
+    };
+    match output.error.take() {
+        Some(e) => Err(e),
+        None => flushed,
+    }
+    .context("Failed to write output")
+}
+
+/// Remove ANSI escape sequences (`ESC [ ... m` and the like) from `text`.
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            stripped.push(c);
+            continue;
+        }
+        // A control sequence runs from `ESC [` to a final byte in `@`–`~`;
+        // any other escape is a single character after ESC.
+        if chars.next() == Some('[') {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    stripped
+}
+
+/// Like `print!`, but to the command output.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::write(format_args!($($arg)*))
+    };
+}
+
+/// Like `println!`, but to the command output.
+macro_rules! outln {
+    () => {
+        $crate::output::write(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::write(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+pub(crate) use {out, outln};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_color_codes_only() {
+        assert_eq!(
+            strip_ansi("\x1b[1;92m1. 🎵 Creep ⚡ NOW PLAYING ⚡\x1b[0m"),
+            "1. 🎵 Creep ⚡ NOW PLAYING ⚡"
+        );
+        assert_eq!(strip_ansi("plain [text]"), "plain [text]");
+        assert_eq!(strip_ansi("a\x1b[4;34mb\x1b[0mc"), "abc");
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Process-wide budget shared by every outgoing request. Unset means unlimited.
+static BUDGET: OnceLock<Mutex<TokenBucket>> = OnceLock::new();
+
+/// Limit outgoing requests to `requests_per_minute` for the rest of the
+/// process. `0` leaves requests unlimited. Only the first call has an effect.
+pub fn init(requests_per_minute: u32) {
+    if requests_per_minute > 0 {
+        let _ = BUDGET.set(Mutex::new(TokenBucket::new(
+            requests_per_minute,
+            Instant::now(),
+        )));
+    }
+}
+
+/// Wait until the request budget allows another request, then spend it.
+pub async fn acquire() {
+    let Some(budget) = BUDGET.get() else {
+        return;
+    };
+    loop {
+        let wait = budget
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .try_take(Instant::now());
+        match wait {
+            None => return,
+            Some(wait) => tokio::time::sleep(wait).await,
+        }
+    }
+}
+
+/// Classic token bucket: holds up to a minute's worth of requests and refills
+/// continuously, so short bursts are fine but the average rate is capped.
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    per_second: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = f64::from(requests_per_minute);
+        Self {
+            capacity,
+            tokens: capacity,
+            per_second: capacity / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Spend a token if one is available, otherwise return how long until one
+    /// will be.
+    fn try_take(&mut self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_a_burst_then_waits_for_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+        assert_eq!(bucket.try_take(start), None);
+        assert_eq!(bucket.try_take(start), None);
+
+        // Two per minute refills one token every 30 seconds.
+        let wait = bucket.try_take(start).unwrap();
+        assert_eq!(wait.as_secs(), 30);
+
+        let later = start + Duration::from_secs(30);
+        assert_eq!(bucket.try_take(later), None);
+        assert!(bucket.try_take(later).is_some());
+    }
+
+    #[test]
+    fn idle_time_never_banks_more_than_capacity() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1, start);
+        let much_later = start + Duration::from_secs(3600);
+        assert_eq!(bucket.try_take(much_later), None);
+        assert!(bucket.try_take(much_later).is_some());
+    }
+}
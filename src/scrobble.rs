@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::db::TrackInfo;
+
+/// Header written at the top of a new log, as expected by tools that import
+/// the Audioscrobbler `.scrobbler.log` format.
+const HEADER: &str = concat!(
+    "#AUDIOSCROBBLER/1.1\n#TZ/UTC\n#CLIENT/playbot ",
+    env!("CARGO_PKG_VERSION"),
+    "\n"
+);
+
+/// How much of the end of the log to read when looking for the last entry.
+const TAIL_BYTES: u64 = 4096;
+
+/// Append a play of `track` at `timestamp` (Unix seconds) to the log at `path`,
+/// creating it with a header if needed.
+///
+/// Returns `false` without writing if the last entry is the same track and
+/// less than one track length ago, so repeated runs during a single play are
+/// only logged once. Each entry goes out in a single append and is synced to
+/// disk, so a crash can at worst lose the entry being written.
+pub fn record_play(path: &Path, track: &TrackInfo, timestamp: u64) -> Result<bool> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)
+        .with_context(|| format!("Failed to open scrobble log: {}", path.display()))?;
+
+    let len = file.metadata()?.len();
+    if let Some(last) = last_entry(&mut file, len)? {
+        let same_track =
+            last.artist == field(&track.artist_name) && last.title == field(&track.track_name);
+        let duration = (track.duration_ms / 1000).max(0) as u64;
+        if same_track && timestamp < last.timestamp + duration {
+            return Ok(false);
+        }
+    }
+
+    let mut entry = if len == 0 {
+        HEADER.to_string()
+    } else {
+        String::new()
+    };
+    entry.push_str(&format_entry(track, timestamp));
+    file.write_all(entry.as_bytes())?;
+    file.sync_data()?;
+    Ok(true)
+}
+
+/// One line of the log: artist, album, title, track number, duration in
+/// seconds, rating (`L` for listened), timestamp, and MusicBrainz ID.
+fn format_entry(track: &TrackInfo, timestamp: u64) -> String {
+    format!(
+        "{}\t{}\t{}\t\t{}\tL\t{}\t\n",
+        field(&track.artist_name),
+        field(&track.album_name),
+        field(&track.track_name),
+        track.duration_ms / 1000,
+        timestamp
+    )
+}
+
+/// Tabs and newlines separate fields and entries, so they can't appear inside one.
+fn field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+struct LoggedPlay {
+    artist: String,
+    title: String,
+    timestamp: u64,
+}
+
+fn last_entry(file: &mut std::fs::File, len: u64) -> Result<Option<LoggedPlay>> {
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    let tail = String::from_utf8_lossy(&tail);
+
+    let Some(line) = tail
+        .lines()
+        .rev()
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+    else {
+        return Ok(None);
+    };
+    let fields: Vec<&str> = line.split('\t').collect();
+    let entry = match fields.as_slice() {
+        [artist, _, title, _, _, _, timestamp, ..] => {
+            timestamp.parse().ok().map(|timestamp| LoggedPlay {
+                artist: artist.to_string(),
+                title: title.to_string(),
+                timestamp,
+            })
+        }
+        _ => None,
+    };
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track() -> TrackInfo {
+        TrackInfo {
+            album_name: "OK Computer".to_string(),
+            release_date: "1997-05-21".to_string(),
+            duration_ms: 264_000,
+            popularity: 80,
+            ..TrackInfo::sample("spotify:track:abc", "Karma Police", "Radiohead")
+        }
+    }
+
+    fn log_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "playbot-scrobble-{}-{}.log",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn entries_use_scrobbler_log_format() {
+        assert_eq!(
+            format_entry(&track(), 1_700_000_000),
+            "Radiohead\tOK Computer\tKarma Police\t\t264\tL\t1700000000\t\n"
+        );
+    }
+
+    #[test]
+    fn header_is_written_once() {
+        let path = log_path("header");
+        assert!(record_play(&path, &track(), 1_000).unwrap());
+        let mut other = track();
+        other.track_name = "Airbag".to_string();
+        assert!(record_play(&path, &other, 1_010).unwrap());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with(HEADER));
+        assert_eq!(contents.matches("#AUDIOSCROBBLER").count(), 1);
+        assert_eq!(contents.lines().count(), 5);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn repeat_detections_within_one_play_are_skipped() {
+        let path = log_path("repeat");
+        assert!(record_play(&path, &track(), 1_000).unwrap());
+        assert!(!record_play(&path, &track(), 1_100).unwrap());
+        assert!(record_play(&path, &track(), 1_000 + 264).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
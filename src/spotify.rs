@@ -1,48 +1,257 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use std::process::Command;
+use std::time::Duration;
 
+use crate::config::{PlayerBackend, PlayerConfig};
 use crate::db::TrackInfo;
 
+/// What kind of item the player is playing, judged from its Spotify URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    /// A catalog music track (`spotify:track:…`).
+    Track,
+    /// A file from the user's own library (`spotify:local:…`).
+    LocalFile,
+    /// A podcast episode (`spotify:episode:…`).
+    Episode,
+}
+
+impl ContentKind {
+    /// Classify a track ID. IDs that aren't Spotify URIs (such as the legacy
+    /// "title-artist" form) are treated as music tracks.
+    pub fn from_track_id(track_id: &str) -> Self {
+        if track_id.starts_with("spotify:local:") {
+            Self::LocalFile
+        } else if track_id.starts_with("spotify:episode:") {
+            Self::Episode
+        } else {
+            Self::Track
+        }
+    }
+
+    /// Short description for output, or `None` for ordinary tracks.
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Self::Track => None,
+            Self::LocalFile => Some("local file"),
+            Self::Episode => Some("podcast episode"),
+        }
+    }
+}
+
+/// Separator between the artists of a collaboration in `artist_name`, on
+/// every platform: MPRIS artist arrays and Web API artist lists are joined
+/// with it, and playerctl already uses it.
+pub const ARTIST_SEPARATOR: &str = ", ";
+
+/// Join a track's artists for `artist_name`.
+pub fn join_artists<S: AsRef<str>>(artists: &[S]) -> String {
+    artists
+        .iter()
+        .map(|artist| artist.as_ref().trim())
+        .filter(|artist| !artist.is_empty())
+        .collect::<Vec<_>>()
+        .join(ARTIST_SEPARATOR)
+}
+
+/// Whether the player is playing the track it reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    /// Stopped, with the last track still loaded.
+    Stopped,
+}
+
+impl PlaybackStatus {
+    /// Parse MPRIS `PlaybackStatus` ("Playing") or AppleScript `player state`
+    /// ("playing") values.
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "playing" => Some(Self::Playing),
+            "paused" => Some(Self::Paused),
+            "stopped" => Some(Self::Stopped),
+            _ => None,
+        }
+    }
+}
+
+/// The player's current track and whether it is playing.
+#[derive(Debug)]
+pub struct Playback {
+    pub track: TrackInfo,
+    pub status: PlaybackStatus,
+    /// How far into the track the player is, when it says. Changes by the
+    /// second, so it is never cached.
+    pub position_ms: Option<i64>,
+    /// What answered, e.g. `playerctl` or `AppleScript`.
+    pub source: String,
+}
+
+/// A playback command for the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Play,
+    Pause,
+    Next,
+    Previous,
+}
+
+impl Transport {
+    /// The MPRIS `Player` method, which is also the `playerctl` command
+    /// in lowercase.
+    #[cfg(target_os = "linux")]
+    fn mpris_method(self) -> &'static str {
+        match self {
+            Self::Play => "Play",
+            Self::Pause => "Pause",
+            Self::Next => "Next",
+            Self::Previous => "Previous",
+        }
+    }
+
+    /// The Spotify AppleScript command.
+    #[cfg(target_os = "macos")]
+    fn applescript(self) -> &'static str {
+        match self {
+            Self::Play => "play",
+            Self::Pause => "pause",
+            Self::Next => "next track",
+            Self::Previous => "previous track",
+        }
+    }
+}
+
 /// Client that reads track information from the local Spotify desktop app.
 ///
-/// On macOS, this uses AppleScript via `osascript`. No API credentials are needed.
-pub struct SpotifyClient;
+/// On macOS, this uses AppleScript via `osascript`; on Linux, it reads the
+/// MPRIS metadata of the configured player (Spotify by default) through
+/// `playerctl` or `dbus-send`. When the desktop app isn't running, a browser
+/// tab playing the Spotify web player is used instead. No API credentials
+/// are needed.
+pub struct SpotifyClient {
+    attempts: u32,
+    retry_delay: Duration,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    player: String,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    backends: Vec<PlayerBackend>,
+}
 
 impl SpotifyClient {
     /// Create a new Spotify client.
     ///
-    /// Returns an error on unsupported platforms.
-    pub fn new() -> Result<Self> {
-        Ok(Self)
+    /// Fails when `player.backends` lists none.
+    pub fn new(config: &PlayerConfig) -> Result<Self> {
+        if config.backends.is_empty() {
+            bail!("player.backends is empty; list at least one of \"playerctl\", \"dbus-send\"");
+        }
+        Ok(Self {
+            attempts: config.detection_attempts.max(1),
+            retry_delay: Duration::from_millis(config.detection_retry_ms),
+            player: config.name.clone(),
+            backends: config.backends.clone(),
+        })
     }
 
     /// Get the currently playing track from the Spotify desktop app.
     ///
-    /// Returns an error if Spotify is not running or no track is playing.
+    /// A paused or stopped track still counts; see [`Self::get_playback`] to
+    /// tell them apart.
     pub async fn get_current_track(&self) -> Result<TrackInfo> {
+        Ok(self.get_playback().await?.track)
+    }
+
+    /// Get the player's current track and whether it is playing, paused, or
+    /// stopped.
+    ///
+    /// Empty or unparsable metadata, which players briefly report around a
+    /// track change, is retried up to the configured number of attempts.
+    /// Returns an error if Spotify is not running or has no track loaded.
+    pub async fn get_playback(&self) -> Result<Playback> {
+        for attempt in 1..=self.attempts {
+            if let Some(playback) = self.read_current_track()? {
+                return Ok(playback);
+            }
+            if attempt < self.attempts {
+                tokio::time::sleep(self.retry_delay).await;
+            }
+        }
+        Err(anyhow!("Failed to parse Spotify track information"))
+    }
+
+    /// Tell the player to play, pause, or change track.
+    pub fn send_command(&self, command: Transport) -> Result<()> {
+        #[cfg(target_os = "macos")]
+        {
+            let script = format!(
+                r#"
+                if application "Spotify" is running then
+                    tell application "Spotify" to {}
+                else
+                    error "Spotify is not running"
+                end if
+            "#,
+                command.applescript()
+            );
+            let output = Command::new("osascript")
+                .arg("-e")
+                .arg(script)
+                .output()
+                .context("Failed to execute osascript")?;
+            if !output.status.success() {
+                let error = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow!("Spotify didn't take the command: {}", error.trim()));
+            }
+            Ok(())
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            self.send_command_linux(command)
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        {
+            let _ = command;
+            Err(anyhow!("Only macOS and Linux are currently supported"))
+        }
+    }
+
+    /// Query the player once. `Ok(None)` means it answered with incomplete
+    /// metadata and is worth asking again.
+    fn read_current_track(&self) -> Result<Option<Playback>> {
         #[cfg(target_os = "macos")]
         {
             self.get_current_track_macos()
         }
 
-        #[cfg(not(target_os = "macos"))]
+        #[cfg(target_os = "linux")]
         {
-            Err(anyhow!("Only macOS is currently supported"))
+            self.get_current_track_linux()
+        }
+
+        #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+        {
+            Err(anyhow!("Only macOS and Linux are currently supported"))
         }
     }
 
     #[cfg(target_os = "macos")]
-    fn get_current_track_macos(&self) -> Result<TrackInfo> {
+    fn get_current_track_macos(&self) -> Result<Option<Playback>> {
         let script = r#"
             if application "Spotify" is running then
                 tell application "Spotify"
-                    if player state is playing then
+                    if player state is not stopped then
+                        set playerState to player state as string
+                        set playerPosition to player position
                         set trackURI to spotify url of current track
                         set trackName to name of current track
                         set artistName to artist of current track
                         set albumName to album of current track
                         set trackDuration to duration of current track
-                        return trackURI & "|" & trackName & "|" & artistName & "|" & albumName & "|" & trackDuration
+                        set artURL to artwork url of current track
+                        return trackURI & "|" & trackName & "|" & artistName & "|" & albumName & "|" & trackDuration & "|" & playerState & "|" & playerPosition & "|" & artURL
                     else
                         error "No track is currently playing"
                     end if
@@ -59,6 +268,10 @@ impl SpotifyClient {
             .context("Failed to execute osascript")?;
 
         if !output.status.success() {
+            // Without the app, Spotify may be playing in a browser tab.
+            if let Some(playback) = read_web_player_macos() {
+                return Ok(Some(playback));
+            }
             let error = String::from_utf8_lossy(&output.stderr);
             return Err(anyhow!(
                 "Spotify is not running or no track is playing. \
@@ -70,8 +283,8 @@ impl SpotifyClient {
         let result = String::from_utf8_lossy(&output.stdout);
         let parts: Vec<&str> = result.trim().split('|').collect();
 
-        if parts.len() < 5 {
-            return Err(anyhow!("Failed to parse Spotify track information"));
+        if parts.len() < 5 || parts[1].is_empty() || parts[2].is_empty() {
+            return Ok(None);
         }
 
         let track_id = parts[0].to_string(); // Spotify URI: spotify:track:xxxxx
@@ -79,8 +292,22 @@ impl SpotifyClient {
         let artist_name = parts[2].to_string();
         let album_name = parts[3].to_string();
         let duration_ms = parts[4].parse::<i64>().unwrap_or(0);
+        let status = parts
+            .get(5)
+            .and_then(|state| PlaybackStatus::parse(state))
+            .unwrap_or(PlaybackStatus::Playing);
+        // Seconds, written with the locale's decimal separator.
+        let position_ms = parts
+            .get(6)
+            .and_then(|seconds| seconds.replace(',', ".").parse::<f64>().ok())
+            .map(|seconds| (seconds * 1000.0) as i64);
+        // Artwork URLs come last, so a stray "|" in one can't shift the rest.
+        let album_art_url = parts
+            .get(7..)
+            .map(|url| url.join("|"))
+            .filter(|url| !url.is_empty());
 
-        Ok(TrackInfo {
+        let track = TrackInfo {
             track_id,
             track_name,
             artist_name,
@@ -92,6 +319,805 @@ impl SpotifyClient {
             lyrics: None,
             producers: String::new(),
             writers: String::new(),
+            lyrics_confidence: None,
+            cached_at: None,
+            play_count: 0,
+            favorite: false,
+            album_art_url,
+            synced_lyrics: None,
+            track_number: None,
+            disc_number: None,
+            explicit: false,
+            genius_url: None,
+        };
+        Ok(Some(Playback {
+            track,
+            status,
+            position_ms,
+            source: "AppleScript".to_string(),
+        }))
+    }
+}
+
+/// Field separator for `playerctl` output. A control character can't show up
+/// in a title the way `|` can.
+#[cfg(target_os = "linux")]
+const PLAYERCTL_SEPARATOR: char = '\u{1f}';
+
+/// Look for a Chrome or Safari tab on the Spotify web player whose title says
+/// what is playing. Tabs give only the title and artist, so the track is
+/// keyed like a player that reports no track ID.
+#[cfg(target_os = "macos")]
+fn read_web_player_macos() -> Option<Playback> {
+    // Each browser is only asked when already running; `tell` would launch it.
+    let script = r#"
+        if application "Google Chrome" is running then
+            tell application "Google Chrome"
+                repeat with w in windows
+                    repeat with t in tabs of w
+                        if URL of t contains "open.spotify.com" and title of t contains " • " then
+                            return "Google Chrome|" & title of t
+                        end if
+                    end repeat
+                end repeat
+            end tell
+        end if
+        if application "Safari" is running then
+            tell application "Safari"
+                repeat with w in windows
+                    repeat with t in tabs of w
+                        if URL of t contains "open.spotify.com" and name of t contains " • " then
+                            return "Safari|" & name of t
+                        end if
+                    end repeat
+                end repeat
+            end tell
+        end if
+        return ""
+    "#;
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let result = String::from_utf8_lossy(&output.stdout);
+    let (browser, title) = result.trim().split_once('|')?;
+    let (track_name, artist_name) = parse_web_player_title(title)?;
+    Some(Playback {
+        track: TrackInfo {
+            track_id: title_slug(&track_name, &artist_name),
+            track_name,
+            artist_name,
+            ..TrackInfo::default()
+        },
+        status: PlaybackStatus::Playing,
+        position_ms: None,
+        source: format!("{} web player", browser),
+    })
+}
+
+/// Split a Spotify web player tab title, `Title • Artist`, which it shows
+/// while a track plays.
+#[cfg(target_os = "macos")]
+fn parse_web_player_title(title: &str) -> Option<(String, String)> {
+    let (track_name, artist_name) = title.rsplit_once(" • ")?;
+    let (track_name, artist_name) = (track_name.trim(), artist_name.trim());
+    if track_name.is_empty() || artist_name.is_empty() {
+        return None;
+    }
+    Some((track_name.to_string(), artist_name.to_string()))
+}
+
+/// Cache key from a track's title and primary artist, for players that give
+/// no track ID.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn title_slug(track_name: &str, artist_name: &str) -> String {
+    let primary = artist_name
+        .split(ARTIST_SEPARATOR)
+        .next()
+        .unwrap_or_default();
+    format!("{}-{}", track_name, primary)
+}
+
+/// The player name `read_web_player` stands in for when it is missing.
+#[cfg(target_os = "linux")]
+const DESKTOP_PLAYER: &str = "spotify";
+
+/// Browsers whose MPRIS players (`firefox.instance_1_23`,
+/// `chromium.instance4567`, ...) may be playing the Spotify web player.
+#[cfg(target_os = "linux")]
+const WEB_BROWSERS: [&str; 6] = ["firefox", "chromium", "chrome", "brave", "vivaldi", "opera"];
+
+/// The browser an MPRIS player name belongs to, if it is one.
+#[cfg(target_os = "linux")]
+fn browser_name(player: &str) -> Option<&'static str> {
+    let app = player.split('.').next().unwrap_or_default();
+    WEB_BROWSERS
+        .into_iter()
+        .find(|browser| app.eq_ignore_ascii_case(browser))
+}
+
+/// Whether a browser's track is from the Spotify web player: its cover comes
+/// from Spotify's image CDN, or the page is on open.spotify.com.
+#[cfg(target_os = "linux")]
+fn is_spotify_web_track(track: &TrackInfo, page_url: &str) -> bool {
+    page_url.contains("open.spotify.com")
+        || track
+            .album_art_url
+            .as_deref()
+            .is_some_and(|url| url.contains(".scdn.co/"))
+}
+
+#[cfg(target_os = "linux")]
+impl SpotifyClient {
+    /// Read the configured player, or when Spotify's desktop app isn't
+    /// running, a browser playing the Spotify web player.
+    fn get_current_track_linux(&self) -> Result<Option<Playback>> {
+        let error = match self.read_player(&self.player) {
+            Ok(playback) => return Ok(playback),
+            Err(e) => e,
+        };
+        if self.player == DESKTOP_PLAYER {
+            if let Some(playback) = self.read_web_player() {
+                return Ok(Some(playback));
+            }
+        }
+        Err(error)
+    }
+
+    /// The first browser player on the bus that is playing the Spotify web
+    /// player. Browsers report no Spotify track ID, so the track is keyed by
+    /// title and artist.
+    fn read_web_player(&self) -> Option<Playback> {
+        list_players().ok()?.iter().find_map(|player| {
+            let browser = browser_name(player)?;
+            let mut playback = self.read_player(player).ok()??;
+            let page_url = run_player_command(
+                "playerctl",
+                &[&format!("--player={}", player), "metadata", "xesam:url"],
+                "apt install playerctl",
+            )
+            .unwrap_or_default();
+            if !is_spotify_web_track(&playback.track, &page_url) {
+                return None;
+            }
+            let track = &mut playback.track;
+            track.track_id = title_slug(&track.track_name, &track.artist_name);
+            playback.source = format!("{} web player via {}", browser, playback.source);
+            Some(playback)
         })
     }
+
+    /// Read `player` through each of `player.backends` in turn, until one
+    /// answers.
+    fn read_player(&self, player: &str) -> Result<Option<Playback>> {
+        let mut errors = Vec::new();
+        for &backend in &self.backends {
+            let result = match backend {
+                PlayerBackend::Playerctl => self.read_playerctl(player),
+                PlayerBackend::DbusSend => self.read_dbus_send(player),
+            };
+            match result {
+                Ok(playback) => {
+                    return Ok(playback.map(|playback| Playback {
+                        source: backend.label().to_string(),
+                        ..playback
+                    }))
+                }
+                Err(e) => errors.push(format!("{}: {:#}", backend.label(), e)),
+            }
+        }
+        Err(anyhow!(
+            "Player '{}' is not running or no track is playing. \
+             Make sure it is open and playing a song \
+             (`pb --list-players` shows the available players).\n{}",
+            player,
+            errors.join("\n")
+        ))
+    }
+
+    fn read_playerctl(&self, player: &str) -> Result<Option<Playback>> {
+        let format = [
+            "status",
+            "position",
+            "mpris:trackid",
+            "title",
+            "artist",
+            "album",
+            "mpris:length",
+            "xesam:contentCreated",
+            "mpris:artUrl",
+            "xesam:trackNumber",
+            "xesam:discNumber",
+        ]
+        .map(|field| format!("{{{{{}}}}}", field))
+        .join(&PLAYERCTL_SEPARATOR.to_string());
+
+        let output = run_player_command(
+            "playerctl",
+            &[
+                &format!("--player={}", player),
+                "metadata",
+                "--format",
+                &format,
+            ],
+            "apt install playerctl",
+        )?;
+        Ok(parse_playerctl_metadata(&output))
+    }
+
+    fn read_dbus_send(&self, player: &str) -> Result<Option<Playback>> {
+        let Some(track) = parse_dbus_metadata(&dbus_player_property(player, "Metadata")?) else {
+            return Ok(None);
+        };
+        // Players that don't report a status are assumed to be playing.
+        let status = dbus_player_property(player, "PlaybackStatus")
+            .ok()
+            .and_then(|output| parse_dbus_status(&output))
+            .unwrap_or(PlaybackStatus::Playing);
+        let position_ms = dbus_player_property(player, "Position")
+            .ok()
+            .and_then(|output| parse_dbus_position(&output));
+        Ok(Some(Playback {
+            track,
+            status,
+            position_ms,
+            source: String::new(),
+        }))
+    }
+
+    /// Send a transport command through each of `player.backends` in turn,
+    /// until one takes it.
+    fn send_command_linux(&self, command: Transport) -> Result<()> {
+        let method = command.mpris_method();
+        let mut errors = Vec::new();
+        for &backend in &self.backends {
+            let result = match backend {
+                PlayerBackend::Playerctl => run_player_command(
+                    "playerctl",
+                    &[&format!("--player={}", self.player), &method.to_lowercase()],
+                    "apt install playerctl",
+                ),
+                PlayerBackend::DbusSend => run_player_command(
+                    "dbus-send",
+                    &[
+                        "--print-reply",
+                        &format!("--dest={}{}", MPRIS_BUS_PREFIX, self.player),
+                        "/org/mpris/MediaPlayer2",
+                        &format!("org.mpris.MediaPlayer2.Player.{}", method),
+                    ],
+                    "apt install dbus",
+                ),
+            };
+            match result {
+                Ok(_) => return Ok(()),
+                Err(e) => errors.push(format!("{}: {:#}", backend.label(), e)),
+            }
+        }
+        Err(anyhow!(
+            "Player '{}' didn't take the command; is it running?\n{}",
+            self.player,
+            errors.join("\n")
+        ))
+    }
+}
+
+/// `Get` one property of `player`'s MPRIS `Player` interface.
+#[cfg(target_os = "linux")]
+fn dbus_player_property(player: &str, property: &str) -> Result<String> {
+    run_player_command(
+        "dbus-send",
+        &[
+            "--print-reply",
+            &format!("--dest={}{}", MPRIS_BUS_PREFIX, player),
+            "/org/mpris/MediaPlayer2",
+            "org.freedesktop.DBus.Properties.Get",
+            "string:org.mpris.MediaPlayer2.Player",
+            &format!("string:{}", property),
+        ],
+        "apt install dbus",
+    )
+}
+
+/// Bus name prefix shared by every MPRIS player; the rest is the player name.
+#[cfg(target_os = "linux")]
+const MPRIS_BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// List the names of the MPRIS players currently on the session bus, usable
+/// as `player.name`.
+#[cfg(target_os = "linux")]
+pub fn list_players() -> Result<Vec<String>> {
+    let output = run_player_command(
+        "dbus-send",
+        &[
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus.ListNames",
+        ],
+        "apt install dbus",
+    )
+    .context("Failed to list MPRIS players via dbus-send")?;
+    Ok(parse_mpris_players(&output))
+}
+
+/// Player discovery goes through the D-Bus session bus, which only Linux has.
+#[cfg(not(target_os = "linux"))]
+pub fn list_players() -> Result<Vec<String>> {
+    Err(anyhow!(
+        "Listing players is only supported on Linux; macOS always reads Spotify"
+    ))
+}
+
+/// Pick the MPRIS player names out of a `ListNames` reply.
+#[cfg(target_os = "linux")]
+fn parse_mpris_players(output: &str) -> Vec<String> {
+    let mut players: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("string \""))
+        .filter_map(|name| name.strip_suffix('"'))
+        .filter_map(|name| name.strip_prefix(MPRIS_BUS_PREFIX))
+        .map(str::to_string)
+        .collect();
+    players.sort();
+    players
+}
+
+/// Run a player query tool and return its stdout, with a distinct error when
+/// the tool isn't installed.
+#[cfg(target_os = "linux")]
+fn run_player_command(program: &str, args: &[&str], install_hint: &str) -> Result<String> {
+    let output = match Command::new(program).args(args).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(anyhow!("not installed (e.g. `{}`)", install_hint));
+        }
+        Err(e) => return Err(e).with_context(|| format!("Failed to execute {}", program)),
+    };
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("{}", error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse the fields requested by `read_playerctl`.
+#[cfg(target_os = "linux")]
+fn parse_playerctl_metadata(output: &str) -> Option<Playback> {
+    let parts: Vec<&str> = output
+        .trim_end_matches('\n')
+        .split(PLAYERCTL_SEPARATOR)
+        .collect();
+    let [status, position, track_id, track_name, artist_name, album_name, length, created, art_url, track_number, disc_number] =
+        parts[..]
+    else {
+        return None;
+    };
+    // playerctl joins the artist array itself, with the same separator.
+    let artists: Vec<&str> = artist_name.split(ARTIST_SEPARATOR).collect();
+    let track = TrackInfo {
+        track_number: mpris_number(track_number),
+        disc_number: mpris_number(disc_number),
+        ..mpris_track(
+            track_id, track_name, &artists, album_name, length, created, art_url,
+        )?
+    };
+    // A status playerctl doesn't know is reported as-is; assume playing.
+    let status = PlaybackStatus::parse(status).unwrap_or(PlaybackStatus::Playing);
+    Some(Playback {
+        track,
+        status,
+        position_ms: mpris_position(position),
+        source: String::new(),
+    })
+}
+
+/// Parse the MPRIS `Position` property as printed by `dbus-send
+/// --print-reply`: a single `variant int64 12345678` line.
+#[cfg(target_os = "linux")]
+fn parse_dbus_position(output: &str) -> Option<i64> {
+    output.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("variant")?.trim_start();
+        mpris_position(value.strip_prefix("int64 ")?)
+    })
+}
+
+/// Convert an MPRIS position, in microseconds, to milliseconds.
+#[cfg(target_os = "linux")]
+fn mpris_position(microseconds: &str) -> Option<i64> {
+    microseconds.trim().parse::<i64>().ok().map(|us| us / 1000)
+}
+
+/// Parse the MPRIS `PlaybackStatus` property as printed by `dbus-send
+/// --print-reply`: a single `variant string "Playing"` line.
+#[cfg(target_os = "linux")]
+fn parse_dbus_status(output: &str) -> Option<PlaybackStatus> {
+    output.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("variant")?.trim_start();
+        PlaybackStatus::parse(value.strip_prefix("string \"")?.strip_suffix('"')?)
+    })
+}
+
+/// Parse the MPRIS `Metadata` dictionary as printed by `dbus-send
+/// --print-reply`. Each entry is a `dict entry(` block holding the key string
+/// and a variant value, which is a string, a number, or an array of strings
+/// (kept as a list, e.g. every artist of a collaboration).
+#[cfg(target_os = "linux")]
+fn parse_dbus_metadata(output: &str) -> Option<TrackInfo> {
+    let mut fields = std::collections::HashMap::<String, Vec<String>>::new();
+    let mut key: Option<String> = None;
+    let mut expect_key = false;
+
+    for line in output.lines().map(str::trim) {
+        if line.starts_with("dict entry(") {
+            expect_key = true;
+            continue;
+        }
+        if line == ")" {
+            key = None;
+            continue;
+        }
+        let line = line.strip_prefix("variant").unwrap_or(line).trim_start();
+        let value = if let Some(quoted) = line.strip_prefix("string \"") {
+            quoted.strip_suffix('"').unwrap_or(quoted)
+        } else if let Some(number) = ["uint64 ", "int64 ", "int32 ", "uint32 "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+        {
+            number
+        } else {
+            continue;
+        };
+
+        if expect_key {
+            key = Some(value.to_string());
+            expect_key = false;
+        } else if let Some(key) = &key {
+            fields
+                .entry(key.clone())
+                .or_default()
+                .push(value.to_string());
+        }
+    }
+
+    let field = |name: &str| fields.get(name).map_or("", |values| values[0].as_str());
+    Some(TrackInfo {
+        track_number: mpris_number(field("xesam:trackNumber")),
+        disc_number: mpris_number(field("xesam:discNumber")),
+        ..mpris_track(
+            field("mpris:trackid"),
+            field("xesam:title"),
+            fields.get("xesam:artist").map_or(&[][..], Vec::as_slice),
+            field("xesam:album"),
+            field("mpris:length"),
+            field("xesam:contentCreated"),
+            field("mpris:artUrl"),
+        )?
+    })
+}
+
+/// An MPRIS `xesam:trackNumber` or `xesam:discNumber`. Players that don't
+/// know the position leave it out or report 0.
+#[cfg(target_os = "linux")]
+fn mpris_number(value: &str) -> Option<i64> {
+    value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|&number| number > 0)
+}
+
+/// Build a track from raw MPRIS metadata values. Returns `None` when the
+/// title or artist is missing, which happens briefly around track changes.
+#[cfg(target_os = "linux")]
+fn mpris_track<S: AsRef<str>>(
+    track_id: &str,
+    track_name: &str,
+    artists: &[S],
+    album_name: &str,
+    length: &str,
+    created: &str,
+    art_url: &str,
+) -> Option<TrackInfo> {
+    let artist_name = join_artists(artists);
+    if track_name.is_empty() || artist_name.is_empty() {
+        return None;
+    }
+
+    // Some players leave out the track ID; a title/primary-artist slug still
+    // gives the cache a key, if a less stable one, and one that doesn't
+    // change when the player lists featured artists differently.
+    let track_id = if track_id.is_empty() {
+        title_slug(track_name, &artist_name)
+    } else {
+        mpris_track_uri(track_id)
+    };
+
+    Some(TrackInfo {
+        track_id,
+        track_name: track_name.to_string(),
+        artist_name,
+        album_name: album_name.to_string(),
+        // An ISO 8601 timestamp when present; only the date is worth showing.
+        release_date: created.split('T').next().unwrap_or_default().to_string(),
+        // MPRIS reports length in microseconds.
+        duration_ms: length.parse::<i64>().map_or(0, |us| us / 1000),
+        popularity: 0,
+        genres: String::new(),
+        lyrics: None,
+        producers: String::new(),
+        writers: String::new(),
+        lyrics_confidence: None,
+        cached_at: None,
+        play_count: 0,
+        favorite: false,
+        album_art_url: (!art_url.is_empty()).then(|| art_url.to_string()),
+        synced_lyrics: None,
+        track_number: None,
+        disc_number: None,
+        explicit: false,
+        genius_url: None,
+    })
+}
+
+/// Convert an MPRIS track ID such as `/com/spotify/track/xxxxx` to the
+/// `spotify:track:xxxxx` URI used as the cache key on macOS.
+#[cfg(target_os = "linux")]
+fn mpris_track_uri(trackid: &str) -> String {
+    match trackid.strip_prefix("/com/spotify/") {
+        Some(path) => format!("spotify:{}", path.replace('/', ":")),
+        None => trackid.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_kind_follows_uri_scheme() {
+        assert_eq!(
+            ContentKind::from_track_id("spotify:track:4u7EnebtmKWzUH433cf5Qv"),
+            ContentKind::Track
+        );
+        assert_eq!(
+            ContentKind::from_track_id("spotify:local:Artist:Album:Song:215"),
+            ContentKind::LocalFile
+        );
+        assert_eq!(
+            ContentKind::from_track_id("spotify:episode:512ojhOuo1ktJprKbVcKyQ"),
+            ContentKind::Episode
+        );
+        assert_eq!(
+            ContentKind::from_track_id("song-artist"),
+            ContentKind::Track
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_playerctl_metadata() {
+        let output = "Paused\u{1f}83000000\u{1f}/com/spotify/track/4u7EnebtmKWzUH433cf5Qv\u{1f}Bohemian Rhapsody\u{1f}Queen\u{1f}A Night at the Opera\u{1f}354320000\u{1f}1975-10-31T00:00:00Z\u{1f}https://i.scdn.co/image/ab67616d0000b273\u{1f}11\u{1f}1\n";
+        let Playback {
+            track,
+            status,
+            position_ms,
+            ..
+        } = parse_playerctl_metadata(output).unwrap();
+        assert_eq!(status, PlaybackStatus::Paused);
+        assert_eq!(position_ms, Some(83_000));
+        assert_eq!(track.track_id, "spotify:track:4u7EnebtmKWzUH433cf5Qv");
+        assert_eq!(track.track_name, "Bohemian Rhapsody");
+        assert_eq!(track.artist_name, "Queen");
+        assert_eq!(track.album_name, "A Night at the Opera");
+        assert_eq!(track.duration_ms, 354_320);
+        assert_eq!(track.release_date, "1975-10-31");
+        assert_eq!(
+            track.album_art_url.as_deref(),
+            Some("https://i.scdn.co/image/ab67616d0000b273")
+        );
+        assert_eq!((track.track_number, track.disc_number), (Some(11), Some(1)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn playerctl_metadata_without_title_is_incomplete() {
+        assert!(parse_playerctl_metadata(
+            "Playing\u{1f}0\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\n"
+        )
+        .is_none());
+        assert!(parse_playerctl_metadata("").is_none());
+        assert_eq!(mpris_track_uri("spotify:track:abc"), "spotify:track:abc");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_dbus_send_metadata() {
+        let output = r#"method return time=1700000000.1 sender=:1.71 -> destination=:1.120 serial=9 reply_serial=2
+   variant       array [
+         dict entry(
+            string "mpris:trackid"
+            variant                string "/com/spotify/track/4u7EnebtmKWzUH433cf5Qv"
+         )
+         dict entry(
+            string "mpris:length"
+            variant                uint64 354320000
+         )
+         dict entry(
+            string "xesam:album"
+            variant                string "A Night at the Opera"
+         )
+         dict entry(
+            string "xesam:artist"
+            variant                array [
+                  string "Queen"
+                  string "David Bowie"
+               ]
+         )
+         dict entry(
+            string "xesam:title"
+            variant                string "Bohemian Rhapsody"
+         )
+         dict entry(
+            string "xesam:trackNumber"
+            variant                int32 11
+         )
+         dict entry(
+            string "mpris:artUrl"
+            variant                string "https://i.scdn.co/image/ab67616d0000b273"
+         )
+         dict entry(
+            string "xesam:url"
+            variant                string "https://open.spotify.com/track/4u7EnebtmKWzUH433cf5Qv"
+         )
+      ]
+"#;
+        let track = parse_dbus_metadata(output).unwrap();
+        assert_eq!(track.track_id, "spotify:track:4u7EnebtmKWzUH433cf5Qv");
+        assert_eq!(track.track_name, "Bohemian Rhapsody");
+        assert_eq!(track.artist_name, "Queen, David Bowie");
+        assert_eq!(track.album_name, "A Night at the Opera");
+        assert_eq!(track.duration_ms, 354_320);
+        assert_eq!(
+            track.album_art_url.as_deref(),
+            Some("https://i.scdn.co/image/ab67616d0000b273")
+        );
+        assert_eq!((track.track_number, track.disc_number), (Some(11), None));
+        assert!(
+            parse_dbus_metadata("method return\n   variant       array [\n      ]\n").is_none()
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn dbus_send_keeps_every_artist() {
+        let metadata = |artists: &[&str]| {
+            let entries: String = artists
+                .iter()
+                .map(|artist| format!("                  string \"{}\"\n", artist))
+                .collect();
+            format!(
+                "method return\n   variant       array [\n         dict entry(\n            string \"xesam:title\"\n            variant                string \"Under Pressure\"\n         )\n         dict entry(\n            string \"xesam:artist\"\n            variant                array [\n{}               ]\n         )\n      ]\n",
+                entries
+            )
+        };
+
+        let two = parse_dbus_metadata(&metadata(&["Queen", "David Bowie"])).unwrap();
+        assert_eq!(two.artist_name, "Queen, David Bowie");
+        assert_eq!(two.track_id, "Under Pressure-Queen");
+
+        let three =
+            parse_dbus_metadata(&metadata(&["Queen", "David Bowie", "Annie Lennox"])).unwrap();
+        assert_eq!(three.artist_name, "Queen, David Bowie, Annie Lennox");
+        assert_eq!(three.track_id, "Under Pressure-Queen");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn playerctl_artists_give_primary_artist_slug() {
+        let Playback { track, .. } = parse_playerctl_metadata(
+            "Playing\u{1f}0\u{1f}\u{1f}Under Pressure\u{1f}Queen, David Bowie\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}0\u{1f}\n",
+        )
+        .unwrap();
+        assert_eq!(track.artist_name, "Queen, David Bowie");
+        assert_eq!(track.track_id, "Under Pressure-Queen");
+        assert_eq!((track.track_number, track.disc_number), (None, None));
+    }
+
+    #[test]
+    fn artists_join_consistently() {
+        assert_eq!(
+            join_artists(&["Queen", " David Bowie "]),
+            "Queen, David Bowie"
+        );
+        assert_eq!(join_artists(&["Queen", ""]), "Queen");
+        assert_eq!(join_artists::<&str>(&[]), "");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_dbus_send_playback_status() {
+        let output = "method return time=1700000000.1 sender=:1.71 -> destination=:1.120 serial=10 reply_serial=2\n   variant       string \"Paused\"\n";
+        assert_eq!(parse_dbus_status(output), Some(PlaybackStatus::Paused));
+        assert_eq!(
+            parse_dbus_status("   variant       string \"Stopped\"\n"),
+            Some(PlaybackStatus::Stopped)
+        );
+        assert_eq!(parse_dbus_status("   variant       string \"\"\n"), None);
+        assert_eq!(
+            parse_dbus_position("method return\n   variant       int64 83000000\n"),
+            Some(83_000)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn lists_only_mpris_players() {
+        let output = r#"method return time=1700000000.1 sender=org.freedesktop.DBus -> destination=:1.120 serial=3 reply_serial=2
+   array [
+      string "org.freedesktop.DBus"
+      string ":1.7"
+      string "org.mpris.MediaPlayer2.spotify"
+      string "org.freedesktop.Notifications"
+      string "org.mpris.MediaPlayer2.vlc.instance4242"
+   ]
+"#;
+        assert_eq!(
+            parse_mpris_players(output),
+            vec!["spotify".to_string(), "vlc.instance4242".to_string()]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_spotify_in_browser_players() {
+        assert_eq!(browser_name("firefox.instance_1_84"), Some("firefox"));
+        assert_eq!(browser_name("chromium.instance4567"), Some("chromium"));
+        assert_eq!(browser_name("spotify"), None);
+        assert_eq!(browser_name("vlc"), None);
+
+        let mut track = TrackInfo::sample("/org/mpris/MediaPlayer2/firefox", "Creep", "Radiohead");
+        assert!(!is_spotify_web_track(
+            &track,
+            "https://www.youtube.com/watch?v=x"
+        ));
+        assert!(is_spotify_web_track(&track, "https://open.spotify.com/"));
+        track.album_art_url = Some("https://i.scdn.co/image/ab67616d".to_string());
+        assert!(is_spotify_web_track(&track, ""));
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn parses_web_player_tab_titles() {
+        assert_eq!(
+            parse_web_player_title("Bohemian Rhapsody • Queen"),
+            Some(("Bohemian Rhapsody".to_string(), "Queen".to_string()))
+        );
+        assert_eq!(parse_web_player_title("Spotify – Web Player"), None);
+        assert_eq!(parse_web_player_title(" • Queen"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn missing_playerctl_track_id_falls_back_to_slug() {
+        let Playback {
+            track,
+            status,
+            position_ms,
+            ..
+        } = parse_playerctl_metadata(
+            "Playing\u{1f}\u{1f}\u{1f}Creep\u{1f}Radiohead\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\u{1f}\n",
+        )
+        .unwrap();
+        assert_eq!(status, PlaybackStatus::Playing);
+        assert_eq!(position_ms, None);
+        assert_eq!(track.track_id, "Creep-Radiohead");
+        assert_eq!(track.artist_name, "Radiohead");
+        assert_eq!(track.duration_ms, 0);
+        assert_eq!(track.release_date, "");
+        assert_eq!(track.album_art_url, None);
+    }
 }
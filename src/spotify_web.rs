@@ -0,0 +1,477 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::{Config, SpotifyApiConfig};
+use crate::db::TrackInfo;
+use crate::lyrics;
+use crate::spotify::{join_artists, ARTIST_SEPARATOR};
+
+const TOKEN_URL: &str = "https://accounts.spotify.com/api/token";
+const API_URL: &str = "https://api.spotify.com/v1";
+
+/// Access token shared by every client in the process.
+static TOKEN: Mutex<Option<CachedToken>> = Mutex::new(None);
+
+/// A client-credentials access token, also kept in `spotify_token.json` in
+/// the data directory so later runs can reuse it until it expires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedToken {
+    /// The app the token was granted to; changing `client_id` needs a new one.
+    client_id: String,
+    access_token: String,
+    /// Seconds since the Unix epoch.
+    expires_at: u64,
+}
+
+impl CachedToken {
+    fn usable_by(&self, client_id: &str) -> bool {
+        self.client_id == client_id && unix_now() < self.expires_at
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+fn token_path() -> Result<PathBuf> {
+    Ok(Config::get_data_dir()?.join("spotify_token.json"))
+}
+
+/// The token saved by an earlier run. A missing or unreadable file only means
+/// asking for a new one.
+fn load_token(path: &Path) -> Option<CachedToken> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// Save the token for later runs, readable only by the user.
+fn save_token(path: &Path, token: &CachedToken) -> Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // A file left by something else may be readable by others.
+        if let Ok(metadata) = fs::metadata(path) {
+            if metadata.permissions().mode() & 0o077 != 0 {
+                fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    file.write_all(serde_json::to_string(token)?.as_bytes())?;
+    Ok(())
+}
+
+/// Client for the Spotify Web API, used to fill in the metadata the local
+/// player doesn't report (popularity, genres, release date).
+///
+/// Authenticates with the client-credentials flow, so it only needs an app's
+/// client ID and secret, never a user login.
+pub struct SpotifyWebClient {
+    http: reqwest::Client,
+    client_id: String,
+    client_secret: String,
+}
+
+impl SpotifyWebClient {
+    /// Create a client from the `[spotify]` config section, or `None` if
+    /// either credential is blank.
+    pub fn new(config: &SpotifyApiConfig) -> Option<Self> {
+        let client_id = config.client_id.trim();
+        let client_secret = config.client_secret.trim();
+        if client_id.is_empty() || client_secret.is_empty() {
+            return None;
+        }
+        let mut shared = TOKEN.lock().unwrap_or_else(|e| e.into_inner());
+        if shared.is_none() {
+            *shared = token_path().ok().and_then(|path| load_token(&path));
+        }
+        drop(shared);
+        Some(Self {
+            http: reqwest::Client::new(),
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+        })
+    }
+
+    /// Fill in `track`'s popularity, genres, release date, duration, album
+    /// position, and full artist list (and its album, if the player left it
+    /// blank) from the Web API.
+    ///
+    /// Tracks with a `spotify:track:` URI are fetched directly; others are
+    /// found by searching for their title and primary artist, and left alone
+    /// if the best hit scores below `min_confidence` (as lyric matches are
+    /// scored). Fields the API leaves empty keep their local values.
+    pub async fn enrich(&self, track: &mut TrackInfo, min_confidence: f64) -> Result<()> {
+        let item = match catalog_id(&track.track_id) {
+            Some(id) => self.get(&format!("/tracks/{}", id), &[]).await?,
+            None => {
+                let query = format!(
+                    "track:{} artist:{}",
+                    track.track_name,
+                    primary_artist(&track.artist_name)
+                );
+                let mut results = self
+                    .get(
+                        "/search",
+                        &[("q", &query), ("type", "track"), ("limit", "1")],
+                    )
+                    .await?;
+                let Some(item) = results["tracks"]["items"].get_mut(0) else {
+                    bail!(
+                        "No Spotify match for '{}' by '{}'",
+                        track.track_name,
+                        track.artist_name
+                    );
+                };
+                let confidence = hit_confidence(track, item);
+                if confidence < min_confidence {
+                    bail!(
+                        "Best Spotify match for '{}' by '{}' is '{}' ({:.0}% match)",
+                        track.track_name,
+                        track.artist_name,
+                        item["name"].as_str().unwrap_or_default(),
+                        confidence * 100.0
+                    );
+                }
+                item.take()
+            }
+        };
+        apply_track(track, &item);
+
+        // Genres are only recorded on artists.
+        if let Some(artist_id) = item["artists"][0]["id"].as_str() {
+            let artist = self.get(&format!("/artists/{}", artist_id), &[]).await?;
+            apply_artist(track, &artist);
+        }
+        Ok(())
+    }
+
+    /// Fetch a catalog track by its `spotify:track:` URI, with everything
+    /// [`enrich`](Self::enrich) fills in, for a track that isn't playing.
+    pub async fn track(&self, track_id: &str) -> Result<TrackInfo> {
+        let mut track = TrackInfo {
+            track_id: track_id.to_string(),
+            ..TrackInfo::default()
+        };
+        self.enrich(&mut track, 0.0).await?;
+        Ok(track)
+    }
+
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<Value> {
+        let token = self.token().await?;
+        crate::ratelimit::acquire().await;
+        let response = self
+            .http
+            .get(format!("{}{}", API_URL, path))
+            .query(query)
+            .bearer_auth(token)
+            .send()
+            .await
+            .with_context(|| format!("Spotify Web API request {} failed", path))?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("Spotify Web API returned {} for {}", status, path);
+        }
+        Ok(response.json().await?)
+    }
+
+    /// Return the cached access token, requesting (and saving) a new one once
+    /// it expires.
+    async fn token(&self) -> Result<String> {
+        let cached = TOKEN
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .filter(|token| token.usable_by(&self.client_id))
+            .map(|token| token.access_token.clone());
+        if let Some(token) = cached {
+            return Ok(token);
+        }
+
+        crate::ratelimit::acquire().await;
+        let response = self
+            .http
+            .post(TOKEN_URL)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[("grant_type", "client_credentials")])
+            .send()
+            .await
+            .context("Failed to reach Spotify's token endpoint")?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "Spotify rejected the client credentials ({}); check client_id and client_secret in [spotify]",
+                status
+            );
+        }
+        let body: Value = response.json().await?;
+        let token = body["access_token"]
+            .as_str()
+            .context("Spotify's token response has no access_token")?
+            .to_string();
+        // Renew a minute early so a token never expires mid-request.
+        let lifetime = body["expires_in"]
+            .as_u64()
+            .unwrap_or(3600)
+            .saturating_sub(60);
+        let cached = CachedToken {
+            client_id: self.client_id.clone(),
+            access_token: token.clone(),
+            expires_at: unix_now() + lifetime,
+        };
+        // Only later runs lose out if the token can't be saved.
+        if let Err(e) = token_path().and_then(|path| save_token(&path, &cached)) {
+            eprintln!("⚠️  Couldn't save the Spotify token: {:#}", e);
+        }
+        *TOKEN.lock().unwrap_or_else(|e| e.into_inner()) = Some(cached);
+        Ok(token)
+    }
+}
+
+/// The `spotify:track:` URI for a track link as the Spotify apps share it,
+/// either `https://open.spotify.com/track/<id>` (with or without a locale
+/// segment such as `intl-de/`, and ignoring any `?si=` query) or the URI
+/// itself.
+pub fn parse_track_link(link: &str) -> Result<String, String> {
+    let link = link.trim();
+    let id = match link.strip_prefix("spotify:track:") {
+        Some(id) => id,
+        None => link
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .strip_prefix("open.spotify.com/")
+            .and_then(|path| {
+                let path = path.split(['?', '#']).next().unwrap_or_default();
+                let mut segments = path.split('/').skip_while(|s| s.starts_with("intl-"));
+                match (segments.next(), segments.next()) {
+                    (Some("track"), Some(id)) => Some(id),
+                    _ => None,
+                }
+            })
+            .unwrap_or_default(),
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(format!(
+            "expected an open.spotify.com/track/ link or a spotify:track: URI, got '{}'",
+            link
+        ));
+    }
+    Ok(format!("spotify:track:{}", id))
+}
+
+/// The catalog ID in a `spotify:track:` URI.
+fn catalog_id(track_id: &str) -> Option<&str> {
+    track_id
+        .strip_prefix("spotify:track:")
+        .filter(|id| !id.is_empty())
+}
+
+/// Copy the fields of a Web API track object onto `track`.
+fn apply_track(track: &mut TrackInfo, item: &Value) {
+    match item["name"].as_str() {
+        Some(name) if track.track_name.is_empty() => track.track_name = name.to_string(),
+        _ => {}
+    }
+    if let Some(popularity) = item["popularity"].as_i64() {
+        track.popularity = popularity as i32;
+    }
+    if let Some(duration_ms) = item["duration_ms"].as_i64() {
+        track.duration_ms = duration_ms;
+    }
+    if let Some(track_number) = item["track_number"].as_i64() {
+        track.track_number = Some(track_number);
+    }
+    if let Some(disc_number) = item["disc_number"].as_i64() {
+        track.disc_number = Some(disc_number);
+    }
+    if let Some(explicit) = item["explicit"].as_bool() {
+        track.explicit = explicit;
+    }
+    match item["album"]["release_date"].as_str() {
+        Some(date) if !date.is_empty() => track.release_date = date.to_string(),
+        _ => {}
+    }
+    // The local player may list only the primary artist (Spotify on macOS
+    // does); the catalog lists every one.
+    let artists: Vec<&str> = item["artists"]
+        .as_array()
+        .map(|artists| artists.iter().filter_map(|a| a["name"].as_str()).collect())
+        .unwrap_or_default();
+    if !artists.is_empty() {
+        track.artist_name = join_artists(&artists);
+    }
+    match item["album"]["name"].as_str() {
+        Some(album) if track.album_name.is_empty() => track.album_name = album.to_string(),
+        _ => {}
+    }
+    // Images come largest first.
+    match item["album"]["images"][0]["url"].as_str() {
+        Some(url) if track.album_art_url.is_none() => track.album_art_url = Some(url.to_string()),
+        _ => {}
+    }
+}
+
+/// The first of the artists in an `artist_name`.
+fn primary_artist(artist_name: &str) -> &str {
+    artist_name
+        .split(ARTIST_SEPARATOR)
+        .next()
+        .unwrap_or_default()
+}
+
+/// Score how well a search hit corresponds to `track`, comparing titles and
+/// primary artists.
+fn hit_confidence(track: &TrackInfo, item: &Value) -> f64 {
+    lyrics::match_confidence(
+        &track.track_name,
+        primary_artist(&track.artist_name),
+        item["name"].as_str().unwrap_or_default(),
+        item["artists"][0]["name"].as_str().unwrap_or_default(),
+    )
+}
+
+/// Copy the genres of a Web API artist object onto `track`.
+fn apply_artist(track: &mut TrackInfo, artist: &Value) {
+    let genres: Vec<&str> = artist["genres"]
+        .as_array()
+        .map(|genres| genres.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if !genres.is_empty() {
+        track.genres = genres.join(", ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn local_track() -> TrackInfo {
+        TrackInfo {
+            duration_ms: 1000,
+            ..TrackInfo::sample("spotify:track:abc", "Song", "Artist")
+        }
+    }
+
+    #[test]
+    fn web_api_objects_fill_in_metadata() {
+        let mut track = local_track();
+        apply_track(
+            &mut track,
+            &json!({
+                "popularity": 71,
+                "duration_ms": 215000,
+                "track_number": 4,
+                "disc_number": 1,
+                "explicit": true,
+                "album": {
+                    "name": "Record",
+                    "release_date": "1997-05-21",
+                    "images": [{"url": "https://i.scdn.co/image/big"}, {"url": "small"}]
+                },
+                "artists": [{"id": "xyz", "name": "Artist"}, {"id": "uvw", "name": "Guest"}]
+            }),
+        );
+        apply_artist(&mut track, &json!({"genres": ["art rock", "alternative"]}));
+
+        assert_eq!(track.popularity, 71);
+        assert_eq!(track.duration_ms, 215000);
+        assert_eq!((track.track_number, track.disc_number), (Some(4), Some(1)));
+        assert!(track.explicit);
+        assert_eq!(track.release_date, "1997-05-21");
+        assert_eq!(track.album_name, "Record");
+        assert_eq!(track.artist_name, "Artist, Guest");
+        assert_eq!(track.genres, "art rock, alternative");
+        assert_eq!(
+            track.album_art_url.as_deref(),
+            Some("https://i.scdn.co/image/big")
+        );
+    }
+
+    #[test]
+    fn missing_web_api_fields_keep_local_values() {
+        let mut track = local_track();
+        track.album_name = "Local Album".to_string();
+        track.genres = "rock".to_string();
+        apply_track(&mut track, &json!({"album": {"name": "Other"}}));
+        apply_artist(&mut track, &json!({"genres": []}));
+
+        assert_eq!(track.duration_ms, 1000);
+        assert_eq!(track.album_name, "Local Album");
+        assert_eq!(track.genres, "rock");
+    }
+
+    #[test]
+    fn only_catalog_uris_are_fetched_by_id() {
+        assert_eq!(catalog_id("spotify:track:4u7E"), Some("4u7E"));
+        assert_eq!(catalog_id("spotify:local:a:b:c:1"), None);
+        assert_eq!(catalog_id("song-artist"), None);
+        assert_eq!(catalog_id("spotify:track:"), None);
+    }
+
+    #[test]
+    fn search_hits_are_scored_on_the_primary_artist() {
+        let mut track = local_track();
+        track.track_name = "Under Pressure".to_string();
+        track.artist_name = "Queen, David Bowie".to_string();
+        let hit = json!({"name": "Under Pressure", "artists": [{"name": "Queen"}, {"name": "David Bowie"}]});
+        assert_eq!(hit_confidence(&track, &hit), 1.0);
+
+        let wrong = json!({"name": "Pressure", "artists": [{"name": "Billy Joel"}]});
+        assert!(hit_confidence(&track, &wrong) < 0.5);
+    }
+
+    #[test]
+    fn share_links_give_track_uris() {
+        let uri = "spotify:track:4uLU6hMCjMI75M1A2tKUQC";
+        for link in [
+            "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQC?si=abc123",
+            "open.spotify.com/intl-de/track/4uLU6hMCjMI75M1A2tKUQC",
+            " spotify:track:4uLU6hMCjMI75M1A2tKUQC ",
+        ] {
+            assert_eq!(parse_track_link(link).as_deref(), Ok(uri), "{}", link);
+        }
+        assert!(parse_track_link("https://open.spotify.com/album/1DFixLWuPkv3KT3TnV35m3").is_err());
+        assert!(parse_track_link("https://example.com/track/4uLU6hMCjMI75M1A2tKUQC").is_err());
+        assert!(parse_track_link("spotify:track:").is_err());
+    }
+
+    #[test]
+    fn tokens_are_saved_privately_and_read_back() {
+        let path = std::env::temp_dir().join(format!("playbot-token-{}.json", std::process::id()));
+        let token = CachedToken {
+            client_id: "app".to_string(),
+            access_token: "secret".to_string(),
+            expires_at: unix_now() + 600,
+        };
+        save_token(&path, &token).unwrap();
+        assert_eq!(load_token(&path), Some(token.clone()));
+        assert!(token.usable_by("app"));
+        assert!(!token.usable_by("other-app"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        fs::remove_file(&path).unwrap();
+
+        let expired = CachedToken {
+            expires_at: 0,
+            ..token
+        };
+        assert!(!expired.usable_by("app"));
+    }
+}
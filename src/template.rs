@@ -0,0 +1,170 @@
+use crate::db::TrackInfo;
+use crate::format_minutes;
+
+/// Placeholders a `--format` template can use, as listed in `--help`.
+pub const KEYS: &[&str] = &[
+    "title",
+    "artist",
+    "album",
+    "duration",
+    "position",
+    "popularity",
+    "released",
+    "genres",
+    "plays",
+    "id",
+];
+
+/// Check that every `{key}` in a `--format` template is a known placeholder,
+/// so a typo fails up front instead of printing literally on every run.
+pub fn parse(template: &str) -> Result<String, String> {
+    for piece in Piece::split(template) {
+        if let Piece::Key(key) = piece {
+            if !KEYS.contains(&key) {
+                return Err(format!(
+                    "unknown placeholder {{{}}}; available: {}",
+                    key,
+                    KEYS.iter()
+                        .map(|key| format!("{{{}}}", key))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        }
+    }
+    Ok(template.to_string())
+}
+
+/// Fill in a template's placeholders from `track`. `{position}` is empty
+/// when the player didn't report one.
+pub fn render(template: &str, track: &TrackInfo, position_ms: Option<i64>) -> String {
+    render_with(template, track, position_ms, |value| value)
+}
+
+/// Fill in a template as a shell command: every value is single-quoted, so
+/// nothing in a track's title or artist can break out of its argument.
+pub fn render_shell(template: &str, track: &TrackInfo) -> String {
+    render_with(template, track, None, |value| {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    })
+}
+
+fn render_with(
+    template: &str,
+    track: &TrackInfo,
+    position_ms: Option<i64>,
+    quote: impl Fn(String) -> String,
+) -> String {
+    Piece::split(template)
+        .map(|piece| match piece {
+            Piece::Text(text) => text.to_string(),
+            Piece::Key(key) => quote(value(key, track, position_ms)),
+        })
+        .collect()
+}
+
+fn value(key: &str, track: &TrackInfo, position_ms: Option<i64>) -> String {
+    match key {
+        "title" => track.track_name.clone(),
+        "artist" => track.artist_name.clone(),
+        "album" => track.album_name.clone(),
+        "duration" => format_minutes(track.duration_ms),
+        "position" => position_ms.map(format_minutes).unwrap_or_default(),
+        "popularity" => track.popularity.to_string(),
+        "released" => track.release_date.clone(),
+        "genres" => track.genres.clone(),
+        "plays" => track.play_count.to_string(),
+        "id" => track.track_id.clone(),
+        // `parse` rejects anything else; keep it as written.
+        other => format!("{{{}}}", other),
+    }
+}
+
+/// Literal text or a `{key}` placeholder. A `{` without a closing `}` is
+/// literal text.
+enum Piece<'a> {
+    Text(&'a str),
+    Key(&'a str),
+}
+
+impl<'a> Piece<'a> {
+    fn split(template: &'a str) -> impl Iterator<Item = Piece<'a>> {
+        let mut rest = template;
+        std::iter::from_fn(move || {
+            if rest.is_empty() {
+                return None;
+            }
+            let key = rest
+                .strip_prefix('{')
+                .and_then(|after| after.split_once('}'))
+                .filter(|(key, _)| !key.is_empty() && !key.contains('{'));
+            if let Some((key, after)) = key {
+                rest = after;
+                return Some(Piece::Key(key));
+            }
+            // Text runs up to the next `{`, which may start a placeholder.
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|&(_, c)| c == '{')
+                .map_or(rest.len(), |(i, _)| i);
+            let (text, after) = rest.split_at(end);
+            rest = after;
+            Some(Piece::Text(text))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track() -> TrackInfo {
+        TrackInfo {
+            album_name: "Pablo Honey".to_string(),
+            release_date: "1993-02-22".to_string(),
+            duration_ms: 238_640,
+            popularity: 88,
+            genres: "alternative rock".to_string(),
+            play_count: 3,
+            ..TrackInfo::sample("spotify:track:abc", "Creep", "Radiohead")
+        }
+    }
+
+    #[test]
+    fn fills_in_placeholders() {
+        let line = render(
+            "♪ {artist} - {title} [{position}/{duration}] {popularity}%",
+            &track(),
+            Some(61_000),
+        );
+        assert_eq!(line, "♪ Radiohead - Creep [1:01/3:58] 88%");
+        assert_eq!(render("{title}{position}", &track(), None), "Creep");
+    }
+
+    #[test]
+    fn stray_braces_are_literal() {
+        assert_eq!(render("{ {title} }{", &track(), None), "{ Creep }{");
+        assert_eq!(render("{}{{album}}", &track(), None), "{}{Pablo Honey}");
+    }
+
+    #[test]
+    fn shell_values_are_quoted() {
+        let track = TrackInfo {
+            album_name: "'; rm -rf ~ #".to_string(),
+            ..TrackInfo::sample("id", "Don't Stop", "Queen")
+        };
+        assert_eq!(
+            render_shell("notify-send {title} {album}", &track),
+            r"notify-send 'Don'\''t Stop' ''\''; rm -rf ~ #'"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        assert!(parse("{title} by {artist}").is_ok());
+        let error = parse("{title} by {artsit}").unwrap_err();
+        assert!(error.contains("{artsit}"), "{}", error);
+        assert!(error.contains("{artist}"), "{}", error);
+    }
+}
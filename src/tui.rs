@@ -1,6 +1,9 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+        KeyModifiers,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -9,16 +12,32 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
+use std::collections::HashSet;
+use std::fs;
+use std::future::Future;
 use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
-use crate::db::{Database, TrackInfo};
+use crate::album_art::AlbumArt;
+use crate::browser;
+use crate::clipboard::Clipboard;
+use crate::config::{Config, ContentPolicy, LyricsConfig};
+use crate::db::{fold, format_cached_at, Database, TrackInfo};
+use crate::lrc;
+use crate::lyrics::{self, genius_page_url, LyricsProvider};
+use crate::spotify::{ContentKind, Playback, PlaybackStatus, SpotifyClient};
 
 enum InputMode {
     Normal,
     Editing,
+    /// Choosing a genre to narrow the list to (`#`).
+    PickingGenre,
 }
 
 enum ViewMode {
@@ -26,6 +45,89 @@ enum ViewMode {
     Detail,
 }
 
+/// Order of the track list, cycled with `s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListSort {
+    Artist,
+    Title,
+    Album,
+    Recent,
+    Popularity,
+    Plays,
+}
+
+impl ListSort {
+    fn next(self) -> Self {
+        match self {
+            Self::Artist => Self::Title,
+            Self::Title => Self::Album,
+            Self::Album => Self::Recent,
+            Self::Recent => Self::Popularity,
+            Self::Popularity => Self::Plays,
+            Self::Plays => Self::Artist,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Artist => "artist",
+            Self::Title => "title",
+            Self::Album => "album",
+            Self::Recent => "date cached",
+            Self::Popularity => "popularity",
+            Self::Plays => "play count",
+        }
+    }
+
+    /// The field a letter jump matches: the one sorted on, or the artist for
+    /// the numeric orders.
+    fn jump_field(self, track: &TrackInfo) -> &str {
+        match self {
+            Self::Title => &track.track_name,
+            Self::Album => &track.album_name,
+            _ => &track.artist_name,
+        }
+    }
+
+    fn jump_label(self) -> &'static str {
+        match self {
+            Self::Title => "Title",
+            Self::Album => "Album",
+            _ => "Artist",
+        }
+    }
+
+    /// Sort `tracks` in this order. Text compares case-insensitively;
+    /// recency, popularity, and plays put the highest first.
+    fn apply(self, tracks: &mut [TrackInfo]) {
+        use std::cmp::Reverse;
+        let fold = |text: &str| text.to_lowercase();
+        match self {
+            Self::Artist => {
+                tracks.sort_by_cached_key(|t| (fold(&t.artist_name), fold(&t.track_name)))
+            }
+            Self::Title => {
+                tracks.sort_by_cached_key(|t| (fold(&t.track_name), fold(&t.artist_name)))
+            }
+            // Within an album, tracks with a known position come first, in
+            // album order.
+            Self::Album => tracks.sort_by_cached_key(|t| {
+                (
+                    fold(&t.album_name),
+                    fold(&t.artist_name),
+                    t.track_number.is_none(),
+                    t.disc_number.unwrap_or(1),
+                    t.track_number,
+                    fold(&t.track_name),
+                )
+            }),
+            Self::Recent => tracks.sort_by_key(|t| Reverse(t.cached_at.clone())),
+            Self::Popularity => tracks.sort_by_key(|t| Reverse(t.popularity)),
+            Self::Plays => tracks.sort_by_key(|t| Reverse(t.play_count)),
+        }
+    }
+}
+
 struct App {
     db: Database,
     tracks: Vec<TrackInfo>,
@@ -34,11 +136,180 @@ struct App {
     input_mode: InputMode,
     view_mode: ViewMode,
     should_quit: bool,
+    /// First detail view line on screen; never more than `max_detail_scroll`.
     detail_scroll: u16,
+    /// Lines of the detail view on screen at once, and the furthest it can
+    /// scroll before the last line leaves blank space. Set while rendering.
+    detail_page: u16,
+    max_detail_scroll: u16,
+    scroll_step: u16,
+    accelerate_scroll: bool,
+    /// When and in which direction (`true` = down) the last `j`/`k` scroll
+    /// happened, to detect a held key.
+    last_scroll: Option<(Instant, bool)>,
+    /// Number of consecutive auto-repeated `j`/`k` scrolls in one direction.
+    scroll_streak: u16,
+    /// Only tracks tagged with this genre are listed, on top of any search.
+    genre_filter: Option<String>,
+    /// Choices offered by the genre picker, after an "All genres" entry, and
+    /// the one highlighted.
+    genres: Vec<String>,
+    genre_state: ListState,
+    /// Previously committed search queries, oldest first.
+    search_history: Vec<String>,
+    /// Position in `search_history` while cycling with Up/Down.
+    history_index: Option<usize>,
+    /// Scroll offsets of lyric section headers (`[Verse 1]`, `[Chorus]`, ...)
+    /// in the detail view as last drawn.
+    section_offsets: Vec<u16>,
+    /// Flow the detail view into two side-by-side columns on wide terminals.
+    two_column: bool,
+    /// Current list order (`s` cycles it, `p` jumps to most played and back).
+    sort: ListSort,
+    /// IDs of the tracks marked with space for `f` and `d` to act on
+    /// together. IDs rather than positions, so marks survive a re-sort.
+    marked: HashSet<String>,
+    /// `d` was pressed on a track (or with tracks marked); the next `d` or
+    /// `y` deletes, any other key cancels.
+    pending_delete: bool,
+    /// `g` was just pressed in the list; a second `g` jumps to the top, any
+    /// other key cancels.
+    pending_top: bool,
+    /// `'` was just pressed in the list; the next letter or digit jumps to a
+    /// track starting with it, even one bound to a command.
+    pending_jump: bool,
+    /// Short confirmation shown in place of the help bar, and when it was
+    /// set.
+    status: Option<(String, Instant)>,
+    clipboard: Clipboard,
+    /// Inline cover images, when the terminal can show them.
+    album_art: Option<AlbumArt>,
+    /// Cover to draw over the detail view once the frame is on screen, and
+    /// where. Set while rendering.
+    art_slot: Option<(String, Rect)>,
+    /// What the player is playing, once the background lookup answers.
+    now_playing: Option<TrackInfo>,
+    /// How far into `now_playing` the player was at the last lookup.
+    playback_clock: Option<PlaybackClock>,
+    /// When the player was last asked what it is playing.
+    last_poll: Option<Instant>,
+    /// Keep the playing line of synced lyrics in view. Set on opening a
+    /// track's details, cleared by scrolling by hand.
+    follow_lyrics: bool,
+    /// Results of work running off the UI thread, and how many are due.
+    background: mpsc::Receiver<Option<Background>>,
+    background_sender: mpsc::Sender<Option<Background>>,
+    background_pending: usize,
+    /// The now-playing track's lyrics are being fetched.
+    fetching: bool,
+    spotify: Option<Arc<SpotifyClient>>,
+    lyrics: LyricsSettings,
+}
+
+/// The playback position reported by the player, and when.
+struct PlaybackClock {
+    position_ms: i64,
+    at: Instant,
+    playing: bool,
+}
+
+impl PlaybackClock {
+    /// The position now, assuming playback carried on since the report.
+    fn position_ms(&self) -> i64 {
+        if !self.playing {
+            return self.position_ms;
+        }
+        let elapsed = i64::try_from(self.at.elapsed().as_millis()).unwrap_or(i64::MAX);
+        self.position_ms.saturating_add(elapsed)
+    }
+}
+
+/// Result of a background task.
+enum Background {
+    /// What the player is doing, or `None` if nothing is playing.
+    NowPlaying(Option<Playback>),
+    /// The now-playing track with its lyrics, ready to cache, and any
+    /// warnings about them, or why the lookup failed.
+    Fetched(Result<(TrackInfo, Vec<String>), String>),
+}
+
+/// What the TUI needs from `[lyrics]`, `[cache]`, and `[player]` to fetch a
+/// track.
+#[derive(Clone)]
+struct LyricsSettings {
+    lyrics: LyricsConfig,
+    max_lyrics_bytes: usize,
+    local_files: ContentPolicy,
+    podcasts: ContentPolicy,
+}
+
+impl LyricsSettings {
+    fn policy(&self, track_id: &str) -> ContentPolicy {
+        match ContentKind::from_track_id(track_id) {
+            ContentKind::Track => ContentPolicy::Cache,
+            ContentKind::LocalFile => self.local_files,
+            ContentKind::Episode => self.podcasts,
+        }
+    }
+}
+
+/// How long a status message replaces the help bar.
+const STATUS_DURATION: Duration = Duration::from_secs(3);
+
+/// Narrowest detail view (inside its border) that gets the two-column layout.
+const TWO_COLUMN_MIN_WIDTH: u16 = 120;
+
+/// Size of the cover drawn in the detail view, in cells (roughly square, as
+/// cells are about twice as tall as they are wide).
+const ART_COLUMNS: u16 = 30;
+const ART_ROWS: u16 = 15;
+
+/// Narrowest detail view (inside its border) that makes room for the cover.
+const ART_MIN_WIDTH: u16 = 70;
+
+/// Key events closer together than this count as a held-down key.
+const KEY_REPEAT_WINDOW: Duration = Duration::from_millis(80);
+
+/// Maximum lines per `j`/`k` event once acceleration has fully kicked in.
+const MAX_ACCELERATED_STEP: u16 = 5;
+
+/// How often to ask the player for its position while following synced
+/// lyrics; the position is extrapolated in between.
+const PLAYBACK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A search starting with this searches lyrics instead of titles and artists.
+const LYRICS_SEARCH_PREFIX: char = '"';
+
+/// Maximum number of search queries remembered across sessions.
+const SEARCH_HISTORY_LIMIT: usize = 100;
+
+fn search_history_path() -> Result<PathBuf> {
+    Ok(Config::get_data_dir()?.join("search_history"))
+}
+
+/// Load saved search history; a missing file just means no history yet.
+fn load_search_history() -> Result<Vec<String>> {
+    let path = search_history_path()?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(contents.lines().map(str::to_string).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    }
+}
+
+fn save_search_history(history: &[String]) -> Result<()> {
+    let path = search_history_path()?;
+    let start = history.len().saturating_sub(SEARCH_HISTORY_LIMIT);
+    let contents: String = history[start..]
+        .iter()
+        .map(|query| format!("{}\n", query))
+        .collect();
+    fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))
 }
 
 impl App {
-    fn new(db: Database) -> Result<Self> {
+    fn new(db: Database, config: &Config, search_history: Vec<String>) -> Result<Self> {
+        let (background_sender, background) = mpsc::channel();
         let tracks = db.get_all_tracks()?;
         let mut list_state = ListState::default();
         if !tracks.is_empty() {
@@ -54,19 +325,271 @@ impl App {
             view_mode: ViewMode::List,
             should_quit: false,
             detail_scroll: 0,
+            detail_page: 0,
+            max_detail_scroll: 0,
+            scroll_step: config.tui.scroll_step.max(1),
+            accelerate_scroll: config.tui.accelerate_scroll,
+            last_scroll: None,
+            scroll_streak: 0,
+            genre_filter: None,
+            genres: Vec::new(),
+            genre_state: ListState::default(),
+            search_history,
+            history_index: None,
+            section_offsets: Vec::new(),
+            two_column: config.tui.two_column_lyrics,
+            sort: ListSort::Artist,
+            marked: HashSet::new(),
+            pending_delete: false,
+            pending_top: false,
+            pending_jump: false,
+            status: None,
+            clipboard: Clipboard::default(),
+            album_art: config.tui.album_art.then(AlbumArt::detect).flatten(),
+            art_slot: None,
+            now_playing: None,
+            playback_clock: None,
+            last_poll: None,
+            follow_lyrics: true,
+            background,
+            background_sender,
+            background_pending: 0,
+            fetching: false,
+            spotify: SpotifyClient::new(&config.player).ok().map(Arc::new),
+            lyrics: LyricsSettings {
+                lyrics: config.lyrics.clone(),
+                max_lyrics_bytes: config.cache.max_lyrics_bytes,
+                local_files: config.player.local_files,
+                podcasts: config.player.podcasts,
+            },
         })
     }
 
+    /// Run `task` on its own thread and runtime, so a slow player or lyrics
+    /// lookup never holds up the UI, and collect its result in
+    /// [`App::receive_background`].
+    fn spawn_background<F, Fut>(&mut self, task: F)
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = Background>,
+    {
+        self.background_pending += 1;
+        let sender = self.background_sender.clone();
+        std::thread::spawn(move || {
+            let result = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .ok()
+                .map(|runtime| runtime.block_on(task()));
+            // Report back even without a result, so the pending count
+            // stays right.
+            let _ = sender.send(result);
+        });
+    }
+
+    /// Ask the player what it is playing, without waiting for the answer.
+    fn refresh_now_playing(&mut self) {
+        let Some(client) = self.spotify.clone() else {
+            return;
+        };
+        self.last_poll = Some(Instant::now());
+        self.spawn_background(move || async move {
+            Background::NowPlaying(client.get_playback().await.ok())
+        });
+    }
+
+    /// Apply the results of finished background tasks.
+    fn receive_background(&mut self) -> Result<()> {
+        while let Ok(result) = self.background.try_recv() {
+            self.background_pending = self.background_pending.saturating_sub(1);
+            match result {
+                None => self.fetching = false,
+                // Content the config skips is never offered for caching.
+                Some(Background::NowPlaying(playback)) => {
+                    let playback = playback.filter(|playback| {
+                        self.lyrics.policy(&playback.track.track_id) != ContentPolicy::Skip
+                    });
+                    self.playback_clock = playback.as_ref().and_then(|playback| {
+                        Some(PlaybackClock {
+                            position_ms: playback.position_ms?,
+                            at: Instant::now(),
+                            playing: playback.status == PlaybackStatus::Playing,
+                        })
+                    });
+                    self.now_playing = playback.map(|playback| playback.track);
+                }
+                Some(Background::Fetched(Ok((track, warnings)))) => {
+                    self.fetching = false;
+                    self.db.insert_track_info(&track)?;
+                    self.update_search()?;
+                    self.select_track(&track.track_id);
+                    if !warnings.is_empty() {
+                        self.status = Some((warnings.join(" · "), Instant::now()));
+                    }
+                }
+                // Left uncached, so `n` can try again.
+                Some(Background::Fetched(Err(e))) => {
+                    self.fetching = false;
+                    self.status = Some((e, Instant::now()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the player is in the selected track, when its details are open
+    /// and it has synced lyrics to follow along with.
+    fn lyrics_position(&self) -> Option<i64> {
+        let track = self.selected_track()?;
+        let playing = self.now_playing.as_ref()?;
+        let following = matches!(self.view_mode, ViewMode::Detail)
+            && track.synced_lyrics.is_some()
+            && playing.track_id == track.track_id;
+        following
+            .then(|| self.playback_clock.as_ref().map(PlaybackClock::position_ms))
+            .flatten()
+    }
+
+    /// Ask the player for its position again if the last answer is getting
+    /// stale while synced lyrics are being followed.
+    fn poll_playback(&mut self) {
+        let stale = self
+            .last_poll
+            .is_none_or(|at| at.elapsed() >= PLAYBACK_POLL_INTERVAL);
+        if stale && self.lyrics_position().is_some() {
+            self.refresh_now_playing();
+        }
+    }
+
+    /// The now-playing track, when it isn't in the cache yet.
+    fn uncached_now_playing(&self) -> Option<&TrackInfo> {
+        self.now_playing.as_ref().filter(|playing| {
+            self.db
+                .get_track_info(&playing.track_id)
+                .is_ok_and(|cached| cached.is_none())
+        })
+    }
+
+    /// `n`: jump to the now-playing track, or fetch and cache it first if it
+    /// isn't cached yet.
+    fn go_to_now_playing(&mut self) -> Result<()> {
+        let Some(playing) = self.now_playing.clone() else {
+            return Ok(());
+        };
+        if self.uncached_now_playing().is_none() {
+            if !self.select_track(&playing.track_id) {
+                // Hidden by the search; show everything again.
+                self.search_query.clear();
+                self.update_search()?;
+                self.select_track(&playing.track_id);
+            }
+            return Ok(());
+        }
+        if self.fetching {
+            return Ok(());
+        }
+
+        self.fetching = true;
+        let settings = self.lyrics.clone();
+        self.spawn_background(move || async move {
+            Background::Fetched(
+                with_lyrics(playing, &settings)
+                    .await
+                    .map_err(|e| format!("{:#}", e)),
+            )
+        });
+        Ok(())
+    }
+
+    /// Select the track with this ID if it's in the list.
+    fn select_track(&mut self, track_id: &str) -> bool {
+        match self.tracks.iter().position(|t| t.track_id == track_id) {
+            Some(i) => {
+                self.list_state.select(Some(i));
+                true
+            }
+            None => false,
+        }
+    }
+
     fn scroll_down(&mut self) {
-        self.detail_scroll = self.detail_scroll.saturating_add(1);
+        let step = self.repeat_step(true);
+        self.scroll_down_by(step);
     }
 
     fn scroll_up(&mut self) {
-        self.detail_scroll = self.detail_scroll.saturating_sub(1);
+        let step = self.repeat_step(false);
+        self.scroll_up_by(step);
+    }
+
+    fn scroll_down_by(&mut self, lines: u16) {
+        self.follow_lyrics = false;
+        self.detail_scroll = self
+            .detail_scroll
+            .saturating_add(lines)
+            .min(self.max_detail_scroll);
+    }
+
+    fn scroll_up_by(&mut self, lines: u16) {
+        self.follow_lyrics = false;
+        self.detail_scroll = self.detail_scroll.saturating_sub(lines);
+    }
+
+    /// Lines to move for a `j`/`k` press: one normally, growing while the key
+    /// auto-repeats in the same direction.
+    fn repeat_step(&mut self, down: bool) -> u16 {
+        let now = Instant::now();
+        let repeating = matches!(
+            self.last_scroll,
+            Some((at, dir)) if dir == down && now.duration_since(at) < KEY_REPEAT_WINDOW
+        );
+        self.last_scroll = Some((now, down));
+        self.scroll_streak = if repeating { self.scroll_streak + 1 } else { 0 };
+
+        if !self.accelerate_scroll {
+            return 1;
+        }
+        (1 + self.scroll_streak / 10).min(MAX_ACCELERATED_STEP)
+    }
+
+    /// Lines to move for a page (`half` for Ctrl-d/Ctrl-u), at least one.
+    fn page_step(&self, half: bool) -> u16 {
+        let page = if half {
+            self.detail_page / 2
+        } else {
+            self.detail_page
+        };
+        page.max(1)
     }
 
     fn reset_scroll(&mut self) {
         self.detail_scroll = 0;
+        self.follow_lyrics = true;
+    }
+
+    fn next_section(&mut self) {
+        if let Some(&offset) = self
+            .section_offsets
+            .iter()
+            .find(|&&offset| offset > self.detail_scroll)
+        {
+            // A section near the end can only come up as far as the last
+            // screenful allows.
+            self.follow_lyrics = false;
+            self.detail_scroll = offset.min(self.max_detail_scroll);
+        }
+    }
+
+    fn previous_section(&mut self) {
+        if let Some(&offset) = self
+            .section_offsets
+            .iter()
+            .rev()
+            .find(|&&offset| offset < self.detail_scroll)
+        {
+            self.follow_lyrics = false;
+            self.detail_scroll = offset;
+        }
     }
 
     fn next(&mut self) {
@@ -103,12 +626,51 @@ impl App {
         self.list_state.select(Some(i));
     }
 
+    fn select_first(&mut self) {
+        if !self.tracks.is_empty() {
+            self.list_state.select(Some(0));
+        }
+    }
+
+    fn select_last(&mut self) {
+        if let Some(last) = self.tracks.len().checked_sub(1) {
+            self.list_state.select(Some(last));
+        }
+    }
+
+    /// Select the next track after the current one whose sort field (see
+    /// [`ListSort::jump_field`]) starts with `letter`, ignoring case, wrapping
+    /// around to the top of the list.
+    fn jump_to_letter(&mut self, letter: char) {
+        let len = self.tracks.len();
+        let start = self.list_state.selected().map_or(0, |i| i + 1);
+        let found = (0..len).map(|offset| (start + offset) % len).find(|&i| {
+            self.sort
+                .jump_field(&self.tracks[i])
+                .chars()
+                .next()
+                .is_some_and(|c| c.to_lowercase().eq(letter.to_lowercase()))
+        });
+        if let Some(i) = found {
+            self.list_state.select(Some(i));
+        }
+    }
+
     fn update_search(&mut self) -> Result<()> {
         self.tracks = if self.search_query.is_empty() {
             self.db.get_all_tracks()?
+        } else if let Some(phrase) = self.search_query.strip_prefix(LYRICS_SEARCH_PREFIX) {
+            self.db.search_lyrics(phrase)?
         } else {
             self.db.search_tracks(&self.search_query)?
         };
+        if let Some(genre) = &self.genre_filter {
+            self.tracks.retain(|track| has_genre(track, genre));
+        }
+        self.sort.apply(&mut self.tracks);
+        // Marks only ever cover tracks in view.
+        let listed: HashSet<&str> = self.tracks.iter().map(|t| t.track_id.as_str()).collect();
+        self.marked.retain(|id| listed.contains(id.as_str()));
 
         if !self.tracks.is_empty() {
             self.list_state.select(Some(0));
@@ -119,12 +681,273 @@ impl App {
         Ok(())
     }
 
+    /// Remember the current query, skipping empty and repeated searches.
+    fn commit_search(&mut self) {
+        self.history_index = None;
+        if self.search_query.is_empty() || self.search_history.last() == Some(&self.search_query) {
+            return;
+        }
+        self.search_history.push(self.search_query.clone());
+    }
+
+    fn history_previous(&mut self) -> Result<()> {
+        if self.search_history.is_empty() {
+            return Ok(());
+        }
+        let i = match self.history_index {
+            Some(i) => i.saturating_sub(1),
+            None => self.search_history.len() - 1,
+        };
+        self.history_index = Some(i);
+        self.search_query = self.search_history[i].clone();
+        self.update_search()
+    }
+
+    fn history_next(&mut self) -> Result<()> {
+        let Some(i) = self.history_index else {
+            return Ok(());
+        };
+        if i + 1 < self.search_history.len() {
+            self.history_index = Some(i + 1);
+            self.search_query = self.search_history[i + 1].clone();
+        } else {
+            // Stepping past the newest entry returns to an empty query, like a shell.
+            self.history_index = None;
+            self.search_query.clear();
+        }
+        self.update_search()
+    }
+
+    /// Open the genre picker on the genres in the cache, starting at the
+    /// current filter.
+    fn open_genre_picker(&mut self) -> Result<()> {
+        self.genres = self.db.distinct_genres()?;
+        if self.genres.is_empty() {
+            self.status = Some(("No cached track has genres".to_string(), Instant::now()));
+            return Ok(());
+        }
+        let current = self
+            .genre_filter
+            .as_ref()
+            .and_then(|genre| self.genres.iter().position(|g| g == genre))
+            .map_or(0, |i| i + 1);
+        self.genre_state.select(Some(current));
+        self.input_mode = InputMode::PickingGenre;
+        Ok(())
+    }
+
+    /// Move the picker highlight by `delta` entries, wrapping around.
+    fn move_genre_selection(&mut self, delta: isize) {
+        let len = self.genres.len() as isize + 1;
+        let i = self.genre_state.selected().unwrap_or(0) as isize;
+        self.genre_state
+            .select(Some((i + delta).rem_euclid(len) as usize));
+    }
+
+    /// Filter the list by the highlighted genre, or clear the filter for
+    /// "All genres".
+    fn choose_genre(&mut self) -> Result<()> {
+        self.genre_filter = self
+            .genre_state
+            .selected()
+            .and_then(|i| i.checked_sub(1))
+            .and_then(|i| self.genres.get(i).cloned());
+        self.input_mode = InputMode::Normal;
+        self.update_search()
+    }
+
+    /// Re-sort the list, keeping the selection on the same track.
+    fn set_sort(&mut self, sort: ListSort) {
+        let selected_id = self.selected_track().map(|track| track.track_id.clone());
+        self.sort = sort;
+        sort.apply(&mut self.tracks);
+        if let Some(id) = selected_id {
+            let position = self.tracks.iter().position(|track| track.track_id == id);
+            self.list_state.select(position.or(Some(0)));
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        self.set_sort(self.sort.next());
+    }
+
+    /// Switch between the most-played order and the default one.
+    fn toggle_sort_by_plays(&mut self) {
+        let sort = if self.sort == ListSort::Plays {
+            ListSort::Artist
+        } else {
+            ListSort::Plays
+        };
+        self.set_sort(sort);
+    }
+
+    /// Copy the selected track's lyrics, or with `metadata` its title and
+    /// artist, to the clipboard and report how it went in the help bar.
+    /// Tracks without lyrics fall back to the metadata.
+    fn copy_selected(&mut self, metadata: bool) {
+        let Some(track) = self.selected_track() else {
+            return;
+        };
+        let (text, what) = match &track.lyrics {
+            Some(lyrics) if !metadata => (lyrics.clone(), "lyrics"),
+            _ => (
+                format!("{} - {}", track.track_name, track.artist_name),
+                "title and artist",
+            ),
+        };
+        let message = match self.clipboard.copy(&text) {
+            Ok(()) => format!("Copied {} to the clipboard", what),
+            Err(e) => format!("Copy failed: {}", e),
+        };
+        self.status = Some((message, Instant::now()));
+    }
+
+    /// The status message, until it has been shown for `STATUS_DURATION`.
+    fn current_status(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .filter(|(_, set)| set.elapsed() < STATUS_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Mark or unmark the selected track and move to the next one, so runs
+    /// of tracks can be marked by holding space. Stops at the last track
+    /// rather than wrapping around.
+    fn toggle_mark(&mut self) {
+        let Some((i, track)) = self
+            .list_state
+            .selected()
+            .and_then(|i| Some((i, self.tracks.get(i)?)))
+        else {
+            return;
+        };
+        let id = track.track_id.clone();
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        if i + 1 < self.tracks.len() {
+            self.list_state.select(Some(i + 1));
+        }
+    }
+
+    /// Indices of the tracks `f` and `d` act on: the marked ones, or the
+    /// selected one when nothing is marked.
+    fn targets(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            return self
+                .list_state
+                .selected()
+                .filter(|&i| i < self.tracks.len())
+                .into_iter()
+                .collect();
+        }
+        (0..self.tracks.len())
+            .filter(|&i| self.marked.contains(&self.tracks[i].track_id))
+            .collect()
+    }
+
+    /// Star or unstar the selected track. With tracks marked, star them all,
+    /// or unstar them all when every one is already starred.
+    fn toggle_favorite(&mut self) -> Result<()> {
+        let targets = self.targets();
+        let favorite = !targets.iter().all(|&i| self.tracks[i].favorite);
+        for i in targets {
+            let track = &mut self.tracks[i];
+            self.db.set_favorite(&track.track_id, favorite)?;
+            track.favorite = favorite;
+        }
+        Ok(())
+    }
+
+    /// Remove the selected track, or every marked one, from the database and
+    /// the list, keeping the selection on the row that took the selected
+    /// one's place (or the new last row).
+    fn delete_selected(&mut self) -> Result<()> {
+        let Some(selected) = self.list_state.selected() else {
+            return Ok(());
+        };
+        let targets = self.targets();
+        for &i in &targets {
+            self.db.delete_track(&self.tracks[i].track_id)?;
+        }
+        let removed_before = targets.iter().filter(|&&i| i < selected).count();
+        let mut i = 0;
+        self.tracks.retain(|_| {
+            i += 1;
+            !targets.contains(&(i - 1))
+        });
+        self.marked.clear();
+        self.list_state.select(match self.tracks.len() {
+            0 => None,
+            len => Some((selected - removed_before).min(len - 1)),
+        });
+        Ok(())
+    }
+
     fn selected_track(&self) -> Option<&TrackInfo> {
         self.list_state.selected().and_then(|i| self.tracks.get(i))
     }
+
+    /// "37/512" for the selected track within the current list, followed by
+    /// the sort order and the active search filter, if any.
+    fn position_label(&self) -> String {
+        let position = self.list_state.selected().map_or(0, |i| i + 1);
+        let mut label = format!(
+            "{}/{} · sorted by {}",
+            position,
+            self.tracks.len(),
+            self.sort.label()
+        );
+        if !self.search_query.is_empty() {
+            label.push_str(&format!(" · search: \"{}\"", self.search_query));
+        }
+        if let Some(genre) = &self.genre_filter {
+            label.push_str(&format!(" · genre: {}", genre));
+        }
+        if !self.marked.is_empty() {
+            label.push_str(&format!(" · {} marked", self.marked.len()));
+        }
+        label
+    }
 }
 
-pub fn run(db: Database) -> Result<()> {
+/// Look up `track`'s lyrics for the cache, returning it with what vetting
+/// warned about. The TUI can't ask about doubtful matches, so they are vetted
+/// as in a piped run.
+async fn with_lyrics(
+    track: TrackInfo,
+    settings: &LyricsSettings,
+) -> Result<(TrackInfo, Vec<String>)> {
+    if settings.policy(&track.track_id) == ContentPolicy::NoLyrics {
+        return Ok((track, Vec::new()));
+    }
+    let Some(provider) = lyrics::Provider::from_config(&settings.lyrics) else {
+        return Ok((track, Vec::new()));
+    };
+    let found = provider
+        .get_lyrics(&track.track_name, &track.artist_name)
+        .await?;
+    let stored = lyrics::vet_lyrics(
+        found,
+        &settings.lyrics,
+        settings.max_lyrics_bytes,
+        |_, _| Ok(None),
+    )?;
+    let track = TrackInfo {
+        lyrics: stored.text,
+        lyrics_confidence: stored.confidence,
+        synced_lyrics: stored.synced,
+        genius_url: track.genius_url.clone().or(stored.genius_url),
+        ..track
+    };
+    Ok((track, stored.warnings))
+}
+
+pub fn run(db: Database, config: &Config) -> Result<()> {
+    // Create the app before taking over the terminal, so an error loading it
+    // is printed to a usable shell.
+    let mut app = App::new(db, config, load_search_history()?)?;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -132,8 +955,7 @@ pub fn run(db: Database) -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app and run it
-    let app = App::new(db)?;
+    app.refresh_now_playing();
     let res = run_app(&mut terminal, app);
 
     // Restore terminal
@@ -145,76 +967,30 @@ pub fn run(db: Database) -> Result<()> {
     )?;
     terminal.show_cursor()?;
 
-    res
+    save_search_history(&res?.search_history)
 }
 
-fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<()> {
+/// Run the event loop until the user quits, returning the final app state.
+fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: App) -> Result<App> {
     loop {
-        terminal.draw(|f| ui(f, &mut app))?;
+        app.receive_background()?;
+        app.poll_playback();
+        draw(terminal, &mut app)?;
 
+        // Redraw when a cover finishes downloading, a background lookup
+        // answers, or synced lyrics move on, not only on a key press.
+        let waiting = app.background_pending > 0
+            || app.current_status().is_some()
+            || app.album_art.as_ref().is_some_and(AlbumArt::loading)
+            || app.lyrics_position().is_some();
+        if waiting && !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             if key.kind != KeyEventKind::Press {
                 continue;
             }
-
-            match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('q') => app.should_quit = true,
-                    KeyCode::Char('/') => app.input_mode = InputMode::Editing,
-                    KeyCode::Char('j') | KeyCode::Down => match app.view_mode {
-                        ViewMode::List => app.next(),
-                        ViewMode::Detail => app.scroll_down(),
-                    },
-                    KeyCode::Char('k') | KeyCode::Up => match app.view_mode {
-                        ViewMode::List => app.previous(),
-                        ViewMode::Detail => app.scroll_up(),
-                    },
-                    KeyCode::Char('l') | KeyCode::Right => {
-                        if let ViewMode::Detail = app.view_mode {
-                            app.next();
-                            app.reset_scroll();
-                        }
-                    }
-                    KeyCode::Char('h') | KeyCode::Left => {
-                        if let ViewMode::Detail = app.view_mode {
-                            app.previous();
-                            app.reset_scroll();
-                        }
-                    }
-                    KeyCode::Enter => match app.view_mode {
-                        ViewMode::List => {
-                            app.reset_scroll();
-                            app.view_mode = ViewMode::Detail;
-                        }
-                        ViewMode::Detail => {
-                            app.reset_scroll();
-                            app.view_mode = ViewMode::List;
-                        }
-                    },
-                    KeyCode::Esc => {
-                        app.reset_scroll();
-                        app.view_mode = ViewMode::List;
-                    }
-                    _ => {}
-                },
-                InputMode::Editing => match key.code {
-                    KeyCode::Enter => {
-                        app.input_mode = InputMode::Normal;
-                    }
-                    KeyCode::Char(c) => {
-                        app.search_query.push(c);
-                        app.update_search()?;
-                    }
-                    KeyCode::Backspace => {
-                        app.search_query.pop();
-                        app.update_search()?;
-                    }
-                    KeyCode::Esc => {
-                        app.input_mode = InputMode::Normal;
-                    }
-                    _ => {}
-                },
-            }
+            handle_key(&mut app, key)?;
         }
 
         if app.should_quit {
@@ -222,10 +998,251 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, mut app: Ap
         }
     }
 
+    Ok(app)
+}
+
+/// Apply one key press to the app.
+fn handle_key(app: &mut App, key: KeyEvent) -> Result<()> {
+    let after_g = std::mem::take(&mut app.pending_top);
+
+    match app.input_mode {
+        InputMode::Normal if app.pending_delete => {
+            app.pending_delete = false;
+            if let KeyCode::Char('d' | 'y') = key.code {
+                app.delete_selected()?;
+            }
+        }
+        InputMode::Normal if app.pending_jump => {
+            app.pending_jump = false;
+            if let KeyCode::Char(c) = key.code {
+                if c.is_alphanumeric() {
+                    app.jump_to_letter(c);
+                }
+            }
+        }
+        InputMode::Normal => match key.code {
+            KeyCode::Char('q') => app.should_quit = true,
+            KeyCode::Char('/') => app.input_mode = InputMode::Editing,
+            KeyCode::Char('J') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.scroll_down_by(app.scroll_step);
+                }
+            }
+            KeyCode::Char('K') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.scroll_up_by(app.scroll_step);
+                }
+            }
+            KeyCode::Char('d' | 'f') | KeyCode::PageDown
+                if key.code == KeyCode::PageDown
+                    || key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                if let ViewMode::Detail = app.view_mode {
+                    let half = key.code == KeyCode::Char('d');
+                    app.scroll_down_by(app.page_step(half));
+                }
+            }
+            KeyCode::Char('u' | 'b') | KeyCode::PageUp
+                if key.code == KeyCode::PageUp || key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                if let ViewMode::Detail = app.view_mode {
+                    let half = key.code == KeyCode::Char('u');
+                    app.scroll_up_by(app.page_step(half));
+                }
+            }
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.scroll_down_by(app.scroll_step);
+                }
+            }
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.scroll_up_by(app.scroll_step);
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => match app.view_mode {
+                ViewMode::List => app.next(),
+                ViewMode::Detail => app.scroll_down(),
+            },
+            KeyCode::Char('k') | KeyCode::Up => match app.view_mode {
+                ViewMode::List => app.previous(),
+                ViewMode::Detail => app.scroll_up(),
+            },
+            KeyCode::Char('l') | KeyCode::Right => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.next();
+                    app.reset_scroll();
+                }
+            }
+            KeyCode::Char('h') | KeyCode::Left => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.previous();
+                    app.reset_scroll();
+                }
+            }
+            KeyCode::Char('G') => match app.view_mode {
+                ViewMode::List => app.select_last(),
+                ViewMode::Detail => {
+                    if let Some(track) = app.selected_track() {
+                        let url = genius_page_url(track);
+                        // A missing opener shouldn't take down the browser session.
+                        let _ = browser::open(&url);
+                    }
+                }
+            },
+            KeyCode::Char('g') => {
+                if let ViewMode::List = app.view_mode {
+                    if after_g {
+                        app.select_first();
+                    } else {
+                        app.pending_top = true;
+                    }
+                }
+            }
+            KeyCode::Char('\'') => {
+                if let ViewMode::List = app.view_mode {
+                    app.pending_jump = true;
+                }
+            }
+            KeyCode::Char('f') => {
+                if let ViewMode::List = app.view_mode {
+                    app.toggle_favorite()?;
+                }
+            }
+            KeyCode::Char(' ') => {
+                if let ViewMode::List = app.view_mode {
+                    app.toggle_mark();
+                }
+            }
+            KeyCode::Char('p') => {
+                if let ViewMode::List = app.view_mode {
+                    app.toggle_sort_by_plays();
+                }
+            }
+            KeyCode::Char('#') => {
+                if let ViewMode::List = app.view_mode {
+                    app.open_genre_picker()?;
+                }
+            }
+            KeyCode::Char('s') => {
+                if let ViewMode::List = app.view_mode {
+                    app.cycle_sort();
+                }
+            }
+            KeyCode::Char('n') => {
+                if let ViewMode::List = app.view_mode {
+                    app.go_to_now_playing()?;
+                }
+            }
+            KeyCode::Char('d') => {
+                if let (ViewMode::List, Some(_)) = (&app.view_mode, app.selected_track()) {
+                    app.pending_delete = true;
+                }
+            }
+            KeyCode::Char('c') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.two_column = !app.two_column;
+                }
+            }
+            KeyCode::Char('y') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.copy_selected(false);
+                }
+            }
+            KeyCode::Char('Y') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.copy_selected(true);
+                }
+            }
+            KeyCode::Char(']') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.next_section();
+                }
+            }
+            KeyCode::Char('[') => {
+                if let ViewMode::Detail = app.view_mode {
+                    app.previous_section();
+                }
+            }
+            KeyCode::Enter => match app.view_mode {
+                ViewMode::List => {
+                    app.reset_scroll();
+                    app.view_mode = ViewMode::Detail;
+                }
+                ViewMode::Detail => {
+                    app.reset_scroll();
+                    app.view_mode = ViewMode::List;
+                }
+            },
+            KeyCode::Esc => match app.view_mode {
+                ViewMode::List => app.marked.clear(),
+                ViewMode::Detail => {
+                    app.reset_scroll();
+                    app.view_mode = ViewMode::List;
+                }
+            },
+            // Letters and digits not bound above jump without the `'`.
+            KeyCode::Char(c) if c.is_alphanumeric() => {
+                if let ViewMode::List = app.view_mode {
+                    app.jump_to_letter(c);
+                }
+            }
+            _ => {}
+        },
+        InputMode::Editing => match key.code {
+            KeyCode::Enter => {
+                app.commit_search();
+                app.input_mode = InputMode::Normal;
+            }
+            KeyCode::Up => app.history_previous()?,
+            KeyCode::Down => app.history_next()?,
+            KeyCode::Char(c) => {
+                app.history_index = None;
+                app.search_query.push(c);
+                app.update_search()?;
+            }
+            KeyCode::Backspace => {
+                app.history_index = None;
+                app.search_query.pop();
+                app.update_search()?;
+            }
+            KeyCode::Esc => {
+                app.history_index = None;
+                app.input_mode = InputMode::Normal;
+            }
+            _ => {}
+        },
+        InputMode::PickingGenre => match key.code {
+            KeyCode::Char('j') | KeyCode::Down => app.move_genre_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => app.move_genre_selection(-1),
+            KeyCode::Enter => app.choose_genre()?,
+            KeyCode::Esc | KeyCode::Char('q' | '#') => app.input_mode = InputMode::Normal,
+            _ => {}
+        },
+    }
+    Ok(())
+}
+
+/// Draw a frame, then the cover image over it (or take an old one down).
+fn draw<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    terminal.draw(|f| ui(f, app))?;
+    let Some(art) = app.album_art.as_mut() else {
+        return Ok(());
+    };
+    match &app.art_slot {
+        Some((url, area)) => art.draw(url, *area)?,
+        None => {
+            if art.clear()? {
+                terminal.clear()?;
+                terminal.draw(|f| ui(f, app))?;
+            }
+        }
+    }
     Ok(())
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
+    app.art_slot = None;
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -241,13 +1258,16 @@ fn ui(f: &mut Frame, app: &mut App) {
         ViewMode::List => render_track_list(f, app, chunks[1]),
         ViewMode::Detail => render_track_detail(f, app, chunks[1]),
     }
+    if let InputMode::PickingGenre = app.input_mode {
+        render_genre_picker(f, app, chunks[1]);
+    }
 
     render_help(f, app, chunks[2]);
 }
 
 fn render_search_box(f: &mut Frame, app: &App, area: Rect) {
     let (msg, style) = match app.input_mode {
-        InputMode::Normal => (
+        InputMode::Normal | InputMode::PickingGenre => (
             vec![
                 Span::raw("Press "),
                 Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
@@ -272,31 +1292,134 @@ fn render_search_box(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(search, area);
 }
 
-fn render_track_list(f: &mut Frame, app: &mut App, area: Rect) {
-    let items: Vec<ListItem> = app
-        .tracks
+/// The genre picker, as a box over the middle of the track list.
+fn render_genre_picker(f: &mut Frame, app: &mut App, area: Rect) {
+    let width = app
+        .genres
         .iter()
-        .map(|track| {
-            let content = Line::from(vec![
+        .map(|genre| genre.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("All genres".len());
+    let width = u16::try_from(width + 6).unwrap_or(u16::MAX).min(area.width);
+    let height = u16::try_from(app.genres.len() + 3)
+        .unwrap_or(u16::MAX)
+        .min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    let items: Vec<ListItem> = std::iter::once("All genres")
+        .chain(app.genres.iter().map(String::as_str))
+        .map(ListItem::new)
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Genre"))
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut app.genre_state);
+}
+
+fn render_track_list(f: &mut Frame, app: &mut App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Tracks ({})", app.position_label()));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // A playing track that isn't cached yet gets a pinned row above the list.
+    let list_area = match app.uncached_now_playing() {
+        Some(playing) => {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(0)])
+                .split(inner);
+            let action = if app.fetching {
+                "fetching lyrics…"
+            } else {
+                "press n to fetch"
+            };
+            let pinned = Line::from(vec![
+                Span::styled("⚡ Now playing: ", Style::default().fg(Color::LightGreen)),
                 Span::styled(
-                    format!("{} ", track.track_name),
+                    format!("{} ", playing.track_name),
                     Style::default()
-                        .fg(Color::Cyan)
+                        .fg(Color::LightGreen)
                         .add_modifier(Modifier::BOLD),
                 ),
-                Span::raw("by "),
-                Span::styled(&track.artist_name, Style::default().fg(Color::Green)),
+                Span::raw(format!("by {} — ", playing.artist_name)),
+                Span::styled(action, Style::default().fg(Color::DarkGray)),
             ]);
-            ListItem::new(content)
+            f.render_widget(Paragraph::new(pinned), rows[0]);
+            rows[1]
+        }
+        None => inner,
+    };
+
+    // Only the rows on screen are built, so large libraries stay responsive.
+    // The whole list stays loaded, so search, sorting, and jumps still see
+    // every track; only the window scrolls to keep the selection in view.
+    let height = usize::from(list_area.height).max(1);
+    let selected = app.list_state.selected();
+    let offset = visible_offset(app.list_state.offset(), selected, height, app.tracks.len());
+    *app.list_state.offset_mut() = offset;
+    let end = (offset + height).min(app.tracks.len());
+
+    let playing_id = app.now_playing.as_ref().map(|t| t.track_id.clone());
+    let query = app
+        .search_query
+        .strip_prefix(LYRICS_SEARCH_PREFIX)
+        .unwrap_or(&app.search_query);
+    let items: Vec<ListItem> = app.tracks[offset..end]
+        .iter()
+        .map(|track| {
+            let mark = if app.marked.contains(&track.track_id) {
+                "✓ "
+            } else {
+                "  "
+            };
+            let star = if track.favorite { "★ " } else { "  " };
+            let playing = playing_id.as_deref() == Some(track.track_id.as_str());
+            let (marker, title_color) = if playing {
+                ("⚡ ", Color::LightGreen)
+            } else {
+                ("", Color::Cyan)
+            };
+            let mut spans = vec![
+                Span::styled(mark, Style::default().fg(Color::LightMagenta)),
+                Span::styled(star, Style::default().fg(Color::Yellow)),
+                Span::styled(marker, Style::default().fg(Color::LightGreen)),
+            ];
+            spans.extend(highlight_match(
+                &track.track_name,
+                query,
+                Style::default()
+                    .fg(title_color)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            if track.explicit {
+                spans.push(Span::raw(" 🅴"));
+            }
+            spans.push(Span::raw(" by "));
+            spans.extend(highlight_match(
+                &track.artist_name,
+                query,
+                Style::default().fg(Color::Green),
+            ));
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
     let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!("Tracks ({})", app.tracks.len())),
-        )
         .highlight_style(
             Style::default()
                 .bg(Color::DarkGray)
@@ -304,10 +1427,102 @@ fn render_track_list(f: &mut Frame, app: &mut App, area: Rect) {
         )
         .highlight_symbol("> ");
 
-    f.render_stateful_widget(list, area, &mut app.list_state);
+    let mut window = ListState::default().with_selected(selected.map(|i| i - offset));
+    f.render_stateful_widget(list, list_area, &mut window);
 }
 
-fn render_track_detail(f: &mut Frame, app: &App, area: Rect) {
+/// `text` as spans in `style`, with the first match of the search `query`
+/// picked out so it's clear why the row matched.
+fn highlight_match<'a>(text: &'a str, query: &str, style: Style) -> Vec<Span<'a>> {
+    let Some(range) = find_folded(text, query) else {
+        return vec![Span::styled(text, style)];
+    };
+    vec![
+        Span::styled(&text[..range.start], style),
+        Span::styled(
+            &text[range.clone()],
+            style.fg(Color::Yellow).add_modifier(Modifier::UNDERLINED),
+        ),
+        Span::styled(&text[range.end..], style),
+    ]
+}
+
+/// Byte range of the first match of `query` in `text`, comparing the way the
+/// search does (ignoring case and accents), so "beyonce" picks out "Beyoncé".
+fn find_folded(text: &str, query: &str) -> Option<Range<usize>> {
+    let query = fold(query);
+    if query.is_empty() {
+        return None;
+    }
+
+    // Fold one character at a time, remembering which character of `text`
+    // each folded byte came from.
+    let mut folded = String::new();
+    let mut sources = Vec::new();
+    for (i, c) in text.char_indices() {
+        let piece = fold(c.encode_utf8(&mut [0; 4]));
+        sources.extend(std::iter::repeat_n(i, piece.len()));
+        folded.push_str(&piece);
+    }
+
+    let at = folded.find(&query)?;
+    // End after the last matched character, and after any accents written
+    // as separate combining characters that follow it.
+    let last = sources[at + query.len() - 1];
+    let end = text[last..]
+        .char_indices()
+        .skip(1)
+        .find(|&(_, c)| !fold(c.encode_utf8(&mut [0; 4])).is_empty())
+        .map_or(text.len(), |(i, _)| last + i);
+    Some(sources[at]..end)
+}
+
+/// Whether `genre` is one of the track's comma-joined genres.
+fn has_genre(track: &TrackInfo, genre: &str) -> bool {
+    track.genres.split(',').any(|g| g.trim() == genre)
+}
+
+/// First row to show so that `selected` is on screen, moving the previous
+/// `offset` as little as possible and never leaving blank rows at the end.
+fn visible_offset(offset: usize, selected: Option<usize>, height: usize, len: usize) -> usize {
+    let offset = match selected {
+        Some(i) if i < offset => i,
+        Some(i) if i >= offset + height => i + 1 - height,
+        _ => offset,
+    };
+    offset.min(len.saturating_sub(height))
+}
+
+fn render_track_detail(f: &mut Frame, app: &mut App, area: Rect) {
+    let block_inner = Block::default().borders(Borders::ALL).inner(area);
+    let single_column = !app.two_column || block_inner.width < TWO_COLUMN_MIN_WIDTH;
+    // The cover takes a column on the right once it has downloaded; until
+    // then (or without image support) the URL line stands in for it.
+    let art_url = app
+        .selected_track()
+        .and_then(|track| track.album_art_url.clone())
+        .filter(|url| {
+            single_column
+                && block_inner.width >= ART_MIN_WIDTH
+                && app.album_art.as_mut().is_some_and(|art| art.ready(url))
+        });
+    let text_area = match art_url {
+        Some(url) => {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(0), Constraint::Length(ART_COLUMNS)])
+                .spacing(2)
+                .split(block_inner);
+            let art_area = Rect {
+                height: ART_ROWS.min(columns[1].height),
+                ..columns[1]
+            };
+            app.art_slot = Some((url, art_area));
+            columns[0]
+        }
+        None => block_inner,
+    };
+
     let track = match app.selected_track() {
         Some(t) => t,
         None => {
@@ -321,10 +1536,112 @@ fn render_track_detail(f: &mut Frame, app: &App, area: Rect) {
         }
     };
 
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Track Details ({})", app.position_label()));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let (lines, current) = detail_lines(track, app.lyrics_position());
+    // Bring the playing line a third of the way down the view.
+    let follow = |width: u16, height: u16| {
+        let index = current.filter(|_| app.follow_lyrics)?;
+        Some(wrapped_offset(&lines, index, width).saturating_sub(height / 3))
+    };
+    if single_column {
+        let sections = section_offsets(&lines, text_area.width);
+        let scroll = follow(text_area.width, text_area.height).unwrap_or(app.detail_scroll);
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true });
+        let total = paragraph.line_count(text_area.width);
+        let (page, max_scroll) = scroll_extent(total, text_area.height);
+        let scroll = scroll.min(max_scroll);
+        f.render_widget(paragraph.scroll((scroll, 0)), text_area);
+        (app.detail_scroll, app.detail_page, app.max_detail_scroll) = (scroll, page, max_scroll);
+        app.section_offsets = sections;
+        return;
+    }
+
+    // Newspaper layout: the right column picks up one screenful after the left,
+    // and both scroll together. Equal widths keep their wrapping identical.
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .spacing(2)
+        .split(inner);
+    let total = Paragraph::new(lines.clone())
+        .wrap(Wrap { trim: true })
+        .line_count(columns[0].width);
+    let (page, max_scroll) = scroll_extent(total, inner.height.saturating_mul(2));
+    // A section jump brings the header to the top of the left column.
+    let sections = section_offsets(&lines, columns[0].width);
+    let left_scroll = follow(columns[0].width, inner.height)
+        .unwrap_or(app.detail_scroll)
+        .min(max_scroll);
+    let right_scroll = left_scroll.saturating_add(inner.height);
+    for (column, scroll) in columns.iter().zip([left_scroll, right_scroll]) {
+        let paragraph = Paragraph::new(lines.clone())
+            .wrap(Wrap { trim: true })
+            .scroll((scroll, 0));
+        f.render_widget(paragraph, *column);
+    }
+    (app.detail_scroll, app.detail_page, app.max_detail_scroll) = (left_scroll, page, max_scroll);
+    app.section_offsets = sections;
+}
+
+/// The page size and furthest scroll offset for `total` wrapped lines shown
+/// `visible` at a time, so the last line can reach the bottom but no further.
+fn scroll_extent(total: usize, visible: u16) -> (u16, u16) {
+    let total = u16::try_from(total).unwrap_or(u16::MAX);
+    (visible, total.saturating_sub(visible))
+}
+
+/// Whether a lyric line is a section marker such as `[Verse 1]` or `[Chorus]`.
+fn is_section_header(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 2 && line.starts_with('[') && line.ends_with(']')
+}
+
+/// How far down the detail view line `index` starts once `lines` are wrapped
+/// to `width`.
+fn wrapped_offset(lines: &[Line], index: usize, width: u16) -> u16 {
+    let above = Paragraph::new(lines[..index].to_vec())
+        .wrap(Wrap { trim: true })
+        .line_count(width);
+    u16::try_from(above).unwrap_or(u16::MAX)
+}
+
+/// Scroll offsets of the lyric section headers in the detail view lines,
+/// wrapped to `width`.
+fn section_offsets(lines: &[Line], width: u16) -> Vec<u16> {
+    let lyrics_start = lines
+        .iter()
+        .position(|line| line.to_string() == "Lyrics:")
+        .unwrap_or(lines.len());
+
+    lines
+        .iter()
+        .enumerate()
+        .skip(lyrics_start)
+        .filter(|(_, line)| is_section_header(&line.to_string()))
+        .map(|(i, _)| wrapped_offset(lines, i, width))
+        .collect()
+}
+
+/// The detail view of `track`, and which of its lines is being sung.
+///
+/// With the player's position in the track and synced lyrics, the lyrics are
+/// shown line by line as timed, the playing one highlighted. Otherwise they
+/// are the plain text.
+fn detail_lines(track: &TrackInfo, position_ms: Option<i64>) -> (Vec<Line<'_>>, Option<usize>) {
     let mut lines = vec![
         Line::from(vec![
             Span::styled("Track: ", Style::default().add_modifier(Modifier::BOLD)),
             Span::raw(&track.track_name),
+            Span::raw(if track.explicit { " 🅴" } else { "" }),
+            Span::styled(
+                if track.favorite { " ★" } else { "" },
+                Style::default().fg(Color::Yellow),
+            ),
         ]),
         Line::from(vec![
             Span::styled("Artist: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -384,39 +1701,131 @@ fn render_track_detail(f: &mut Frame, app: &App, area: Rect) {
         ]));
     }
 
-    if let Some(lyrics) = &track.lyrics {
-        lines.push(Line::from(""));
-        lines.push(Line::from(Span::styled(
-            "Lyrics:",
-            Style::default().add_modifier(Modifier::BOLD),
-        )));
-        lines.push(Line::from(""));
+    if let Some(confidence) = track.lyrics_confidence {
+        lines.push(Line::from(vec![
+            Span::styled(
+                "Lyrics Match: ",
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(format!("{:.0}%", confidence * 100.0)),
+        ]));
+    }
+
+    if track.play_count > 0 {
+        lines.push(Line::from(vec![
+            Span::styled("Plays: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(track.play_count.to_string()),
+        ]));
+    }
+
+    if let Some(cached_at) = &track.cached_at {
+        lines.push(Line::from(vec![
+            Span::styled("Cached: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{} UTC", format_cached_at(cached_at))),
+        ]));
+    }
+
+    if let Some(url) = &track.album_art_url {
+        lines.push(Line::from(vec![
+            Span::styled("Album Art: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(url.clone()),
+        ]));
+    }
+
+    let synced = track
+        .synced_lyrics
+        .as_deref()
+        .zip(position_ms)
+        .map(|(lrc, position_ms)| (lrc::parse(lrc), position_ms))
+        .filter(|(timed, _)| !timed.is_empty());
+    let mut current = None;
+    if let Some((timed, position_ms)) = synced {
+        push_lyrics_heading(&mut lines);
+        let playing = lrc::current_line(&timed, position_ms);
+        current = Some(lines.len() + playing.unwrap_or(0));
+        for (i, line) in timed.into_iter().enumerate() {
+            let style = if Some(i) == playing {
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::styled(line.text, style));
+        }
+    } else if let Some(lyrics) = &track.lyrics {
+        push_lyrics_heading(&mut lines);
         for line in lyrics.lines() {
-            lines.push(Line::from(line));
+            if is_section_header(line) {
+                lines.push(Line::from(Span::styled(
+                    line,
+                    Style::default()
+                        .fg(Color::Magenta)
+                        .add_modifier(Modifier::BOLD),
+                )));
+            } else {
+                lines.push(Line::from(line));
+            }
         }
     }
 
-    let paragraph = Paragraph::new(lines)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Track Details"),
-        )
-        .wrap(Wrap { trim: true })
-        .scroll((app.detail_scroll, 0));
+    (lines, current)
+}
 
-    f.render_widget(paragraph, area);
+fn push_lyrics_heading(lines: &mut Vec<Line>) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Lyrics:",
+        Style::default().add_modifier(Modifier::BOLD),
+    )));
+    lines.push(Line::from(""));
 }
 
 fn render_help(f: &mut Frame, app: &App, area: Rect) {
+    if let (true, Some(track)) = (app.pending_delete, app.selected_track()) {
+        let what = match app.marked.len() {
+            0 => format!("'{}' by {}", track.track_name, track.artist_name),
+            1 => "1 marked track".to_string(),
+            n => format!("{} marked tracks", n),
+        };
+        let prompt = Paragraph::new(format!(
+            "Delete {} from the cache? d/y: Delete | any other key: Cancel",
+            what
+        ))
+        .style(Style::default().fg(Color::Yellow))
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(prompt, area);
+        return;
+    }
+
+    if let Some(status) = app.current_status() {
+        let message = Paragraph::new(status)
+            .style(Style::default().fg(Color::LightGreen))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(message, area);
+        return;
+    }
+
+    let list_help;
     let help_text = match app.view_mode {
         ViewMode::List => match app.input_mode {
             InputMode::Normal => {
-                "j/k or Up/Down: Navigate | Enter: View Details | /: Search | q: Quit"
+                list_help = format!(
+                    "j/k or Up/Down: Navigate | gg/G: Top/Bottom | 'x: Jump to {} | Enter: View Details | /: Search | Space: Mark | f: Star | s: Sort | p: Most Played | #: Genre | n: Now Playing | d: Delete | q: Quit",
+                    app.sort.jump_label()
+                );
+                &list_help
+            }
+            InputMode::Editing => {
+                "Type to search (start with \" for lyrics) | Up/Down: History | Enter: Finish | Esc: Cancel"
+            }
+            InputMode::PickingGenre => {
+                "j/k or Up/Down: Choose Genre | Enter: Filter (All genres clears it) | Esc: Cancel"
             }
-            InputMode::Editing => "Type to search | Enter: Finish | Esc: Cancel",
         },
-        ViewMode::Detail => "j/k: Scroll | h/l: Prev/Next Song | Enter/Esc: Back to List | q: Quit",
+        ViewMode::Detail => {
+            "j/k: Scroll | J/K: Scroll Faster | ^d/^u: Half Page | PgDn/PgUp: Page | [/]: Prev/Next Section | h/l: Prev/Next Song | c: Columns | y/Y: Copy Lyrics/Title | G: Genius | Enter/Esc: Back to List | q: Quit"
+        }
     };
 
     let help = Paragraph::new(help_text)
@@ -425,3 +1834,110 @@ fn render_help(f: &mut Frame, app: &App, area: Rect) {
 
     f.render_widget(help, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str, title: &str, artist: &str, album: &str) -> TrackInfo {
+        TrackInfo {
+            album_name: album.to_string(),
+            ..TrackInfo::sample(id, title, artist)
+        }
+    }
+
+    fn app() -> App {
+        let db = Database::new(":memory:").unwrap();
+        db.init().unwrap();
+        db.insert_track_info(&track("1", "Airbag", "Adele", "Kid A"))
+            .unwrap();
+        db.insert_track_info(&track("2", "Jigsaw", "Coldplay", "Parachutes"))
+            .unwrap();
+        db.insert_track_info(&track("3", "Creep", "Radiohead", "Pablo Honey"))
+            .unwrap();
+        let config: Config = toml::from_str("").unwrap();
+        App::new(db, &config, Vec::new()).unwrap()
+    }
+
+    fn press(app: &mut App, c: char) {
+        handle_key(app, KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)).unwrap();
+    }
+
+    fn selected_title(app: &App) -> &str {
+        &app.tracks[app.list_state.selected().unwrap()].track_name
+    }
+
+    #[test]
+    fn quote_jumps_to_letters_bound_to_commands() {
+        let mut app = app();
+        assert_eq!(selected_title(&app), "Airbag");
+
+        // `c` copies and `q` quits on their own; after `'` they jump.
+        press(&mut app, '\'');
+        press(&mut app, 'c');
+        assert_eq!(selected_title(&app), "Jigsaw");
+        press(&mut app, '\'');
+        press(&mut app, 'r');
+        assert_eq!(selected_title(&app), "Creep");
+        press(&mut app, '\'');
+        press(&mut app, 'q');
+        assert!(!app.should_quit);
+        assert_eq!(selected_title(&app), "Creep");
+
+        // Unbound letters still jump without the prefix.
+        press(&mut app, 'a');
+        assert_eq!(selected_title(&app), "Airbag");
+    }
+
+    #[test]
+    fn jump_matches_the_sorted_field() {
+        let mut app = app();
+        app.sort = ListSort::Title;
+        press(&mut app, '\'');
+        press(&mut app, 'c');
+        assert_eq!(selected_title(&app), "Creep");
+
+        app.sort = ListSort::Album;
+        press(&mut app, '\'');
+        press(&mut app, 'p');
+        assert_eq!(selected_title(&app), "Jigsaw");
+    }
+
+    #[test]
+    fn marked_tracks_are_starred_and_deleted_together() {
+        let mut app = app();
+        press(&mut app, ' ');
+        press(&mut app, 'j');
+        press(&mut app, ' ');
+        assert_eq!(selected_title(&app), "Creep");
+        assert!(app.position_label().contains("2 marked"));
+
+        press(&mut app, 'f');
+        let starred: Vec<bool> = app.tracks.iter().map(|t| t.favorite).collect();
+        assert_eq!(starred, [true, false, true]);
+        press(&mut app, 'f');
+        assert!(app.tracks.iter().all(|t| !t.favorite));
+
+        press(&mut app, 'd');
+        press(&mut app, 'y');
+        assert_eq!(app.tracks.len(), 1);
+        assert_eq!(selected_title(&app), "Jigsaw");
+        assert_eq!(app.db.count_tracks().unwrap(), 1);
+        assert!(app.marked.is_empty());
+    }
+
+    #[test]
+    fn section_offsets_count_wrapped_lines() {
+        let lines: Vec<Line> = [
+            "Lyrics:",
+            "[Verse 1]",
+            "a line long enough to wrap",
+            "[Chorus]",
+        ]
+        .into_iter()
+        .map(Line::from)
+        .collect();
+        assert_eq!(section_offsets(&lines, 80), vec![1, 3]);
+        assert_eq!(section_offsets(&lines, 10), vec![1, 6]);
+    }
+}